@@ -0,0 +1,15 @@
+fn main() {
+    println!("cargo:rustc-check-cfg=cfg(dorian_traps)");
+
+    // Traps are only caught by the JIT, which needs at least one backend, and only on Linux on x86-64 and AArch64, as
+    // `trap.c` relies on their signal contexts and `trampoline.S` on their calling conventions
+    let jit = ["CARGO_FEATURE_CRANELIFT", "CARGO_FEATURE_LLVM"].iter().any(|x| std::env::var_os(x).is_some());
+    let linux = std::env::var("CARGO_CFG_TARGET_OS").is_ok_and(|x| x == "linux");
+    let arch = std::env::var("CARGO_CFG_TARGET_ARCH").is_ok_and(|x| x == "x86_64" || x == "aarch64");
+    if jit && linux && arch {
+        println!("cargo:rerun-if-changed=src/jit/trap.c");
+        println!("cargo:rerun-if-changed=src/jit/trampoline.S");
+        println!("cargo:rustc-cfg=dorian_traps");
+        cc::Build::new().file("src/jit/trap.c").file("src/jit/trampoline.S").compile("dorian_trap");
+    }
+}
@@ -1,9 +1,57 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use crate::module::Module;
+use crate::resolve::ResolveError;
 
 pub trait Backend {
     type CompiledModule<'ctx>
     where
         Self: 'ctx;
 
-    fn compile_module<'ctx>(&'ctx mut self, ast_module: &Module) -> Self::CompiledModule<'ctx>;
+    fn compile_module<'ctx>(&'ctx mut self, ast_module: &Module) -> Result<Self::CompiledModule<'ctx>, CompileError> {
+        self.compile_modules(std::slice::from_ref(ast_module))
+    }
+
+    /// Compiles modules together into one compiled module, in which functions are shared by name as described by
+    /// [`SymbolTable::resolve_linked`](crate::resolve::SymbolTable::resolve_linked).
+    ///
+    /// Modules that fail to resolve are reported as [`CompileError::Resolve`], while backends panic on modules that
+    /// are invalid in other ways, such as ones that use values of the wrong type.
+    fn compile_modules<'ctx>(
+        &'ctx mut self,
+        ast_modules: &[Module],
+    ) -> Result<Self::CompiledModule<'ctx>, CompileError>;
+}
+
+/// Why a backend failed to compile modules.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompileError {
+    /// The modules failed to resolve, or to type their literals, as reported by
+    /// [`SymbolTable::resolve_linked`](crate::resolve::SymbolTable::resolve_linked) and
+    /// [`infer::type_literals`](crate::infer::type_literals).
+    Resolve(Vec<ResolveError>),
+    /// A feature of the modules that the backend cannot compile, with its explanation.
+    Unsupported(String),
+    /// An optimization pipeline that the backend rejected, with its explanation.
+    Optimization {
+        pipeline: String,
+        message: String,
+    },
 }
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompileError::Resolve(errors) => {
+                let errors = errors.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+                write!(f, "Failed to resolve module: {}", errors.join(", "))
+            }
+            CompileError::Unsupported(message) => write!(f, "{}", message),
+            CompileError::Optimization { pipeline, message } => {
+                write!(f, "Failed to run optimization pipeline '{}': {}", pipeline, message)
+            }
+        }
+    }
+}
+
+impl Error for CompileError {}
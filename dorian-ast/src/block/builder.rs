@@ -1,7 +1,12 @@
 use std::borrow::Cow;
 use crate::block::Block;
-use crate::block::stmt::{AssignStmt, BindStmt, IfElse, IfStmt, ReturnStmt, Stmt, WhileStmt};
-use crate::val::{Value, Var};
+use crate::span::{Span, Spanned};
+use crate::block::stmt::{
+    AssertStmt, AssignStmt, AtomicStoreStmt, BindStmt, BindTupleStmt, Handler, IfElse, IfStmt, MemCopyStmt,
+    MemSetStmt, ReturnStmt, Stmt, TrapCode, TryStmt, WhileStmt,
+};
+use crate::function::Signature;
+use crate::val::{AtomicOrdering, Call, CallIndirect, Value, Var};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct BlockBuilder<'s> {
@@ -50,6 +55,36 @@ impl<'s> BlockBuilder<'s> {
             .push(Stmt::Return(ReturnStmt { values: values.into() }));
     }
 
+    pub fn call(&mut self, function_name: impl Into<Cow<'s, str>>, args: impl Into<Vec<Value<'s>>>) {
+        self.stmts.push(Stmt::Call(Call {
+            function_name: function_name.into(),
+            args: args.into(),
+        }));
+    }
+
+    pub fn call_indirect(&mut self, callee: Value<'s>, signature: Signature, args: impl Into<Vec<Value<'s>>>) {
+        self.stmts.push(Stmt::CallIndirect(Box::new(CallIndirect {
+            callee,
+            signature,
+            args: args.into(),
+        })));
+    }
+
+    pub fn tail_call(&mut self, function_name: impl Into<Cow<'s, str>>, args: impl Into<Vec<Value<'s>>>) {
+        self.stmts.push(Stmt::TailCall(Call {
+            function_name: function_name.into(),
+            args: args.into(),
+        }));
+    }
+
+    pub fn tail_call_indirect(&mut self, callee: Value<'s>, signature: Signature, args: impl Into<Vec<Value<'s>>>) {
+        self.stmts.push(Stmt::TailCallIndirect(Box::new(CallIndirect {
+            callee,
+            signature,
+            args: args.into(),
+        })));
+    }
+
     pub fn bind(&mut self, name: impl Into<Cow<'s, str>>, value: Value<'s>) {
         let name = name.into();
         self.stmts.push(Stmt::Bind(BindStmt {
@@ -58,15 +93,116 @@ impl<'s> BlockBuilder<'s> {
         }));
     }
 
+    pub fn bind_tuple<N: Into<Cow<'s, str>>>(&mut self, names: impl IntoIterator<Item = N>, value: Value<'s>) {
+        self.stmts.push(Stmt::BindTuple(BindTupleStmt {
+            names: names.into_iter().map(Into::into).collect(),
+            value,
+        }));
+    }
+
     pub fn assign(&mut self, var: Var<'s>, value: Value<'s>) {
         self.stmts.push(Stmt::Assign(AssignStmt { var, value }));
     }
 
+    /// Traps with `code`, which must be a user code as described by [`TrapCode`].
+    pub fn trap(&mut self, code: u8) {
+        self.stmts.push(Stmt::Trap(user_trap_code(code)));
+    }
+
+    pub fn unreachable(&mut self) {
+        self.stmts.push(Stmt::Unreachable);
+    }
+
+    /// Traps with `code` unless `condition` is true, where `code` must be a user code as described by [`TrapCode`].
+    pub fn assert(&mut self, condition: Value<'s>, code: u8) {
+        self.stmts.push(Stmt::Assert(AssertStmt { condition, code: user_trap_code(code) }));
+    }
+
+    pub fn atomic_store(&mut self, ptr: Value<'s>, value: Value<'s>, ordering: AtomicOrdering) {
+        self.stmts.push(Stmt::AtomicStore(AtomicStoreStmt { ptr, value, ordering }));
+    }
+
+    pub fn fence(&mut self, ordering: AtomicOrdering) {
+        self.stmts.push(Stmt::Fence(ordering));
+    }
+
+    /// Copies `len` bytes from `src` to `dest`, which must not overlap and are both aligned to `align` bytes.
+    pub fn memcpy(&mut self, dest: Value<'s>, src: Value<'s>, len: Value<'s>, align: u32) {
+        self.stmts.push(Stmt::MemCopy(MemCopyStmt { dest, src, len, align: check_align(align) }));
+    }
+
+    /// Copies `len` bytes from `src` to `dest`, which may overlap and are both aligned to `align` bytes.
+    pub fn memmove(&mut self, dest: Value<'s>, src: Value<'s>, len: Value<'s>, align: u32) {
+        self.stmts.push(Stmt::MemMove(MemCopyStmt { dest, src, len, align: check_align(align) }));
+    }
+
+    /// Sets `len` bytes at `dest`, which is aligned to `align` bytes, to `value`.
+    pub fn memset(&mut self, dest: Value<'s>, value: Value<'s>, len: Value<'s>, align: u32) {
+        self.stmts.push(Stmt::MemSet(MemSetStmt { dest, value, len, align: check_align(align) }));
+    }
+
+    /// Runs the body built by `build`, running the block built by `cleanup` if unwinding out of it.
+    pub fn try_cleanup(&mut self, build: impl FnOnce(&mut BlockBuilder), cleanup: impl FnOnce(&mut BlockBuilder)) {
+        let mut body = Block::build();
+        build(&mut body);
+        let mut cleanup_block = Block::build();
+        cleanup(&mut cleanup_block);
+
+        self.stmts.push(Stmt::Try(TryStmt {
+            body: body.finish(),
+            handler: Handler::Cleanup(cleanup_block.finish()),
+        }));
+    }
+
+    /// Runs the body built by `build`, catching any exception unwinding out of it with the block built by `catch`, in
+    /// which the exception object is bound to `name`. The block must finish the exception, see [`Handler::Catch`].
+    pub fn try_catch(
+        &mut self,
+        build: impl FnOnce(&mut BlockBuilder),
+        name: impl Into<Cow<'s, str>>,
+        catch: impl FnOnce(&mut BlockBuilder),
+    ) {
+        let mut body = Block::build();
+        build(&mut body);
+        let mut catch_block = Block::build();
+        catch(&mut catch_block);
+
+        self.stmts.push(Stmt::Try(TryStmt {
+            body: body.finish(),
+            handler: Handler::Catch {
+                name: name.into(),
+                block: catch_block.finish(),
+            },
+        }));
+    }
+
+    /// Annotates every statement added by `build` with `span`.
+    pub fn spanned(&mut self, span: Span<'s>, build: impl FnOnce(&mut BlockBuilder<'s>)) {
+        let start = self.stmts.len();
+        build(self);
+
+        for stmt in &mut self.stmts[start..] {
+            let node = std::mem::replace(stmt, Stmt::Return(ReturnStmt { values: vec![] }));
+            *stmt = Stmt::Spanned(Box::new(Spanned::new(span.clone(), node)));
+        }
+    }
+
     pub fn finish(self) -> Block<'s> {
         Block { stmts: self.stmts }
     }
 }
 
+fn user_trap_code(code: u8) -> TrapCode {
+    TrapCode::user(code).unwrap_or_else(|| panic!("Trap code {} is reserved", code))
+}
+
+fn check_align(align: u32) -> u32 {
+    if !align.is_power_of_two() {
+        panic!("Alignment {} is not a power of two", align);
+    }
+    align
+}
+
 enum Parent<'s, 'p> {
     Block(&'p mut BlockBuilder<'s>),
     IfStmt(&'p mut IfStmtBuilder<'s, 'p>),
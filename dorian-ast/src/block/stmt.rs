@@ -1,6 +1,8 @@
 use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
 use crate::block::Block;
-use crate::val::{Value, Var};
+use crate::span::Spanned;
+use crate::val::{AtomicOrdering, Call, CallIndirect, Value, Var};
 
 /*
 List of LLVM builder methods that are either implemented ([x]), partially implemented ([/]), or not implemented ([ ]),
@@ -11,28 +13,28 @@ NP  build_aggregate_return
 [x] build_call
 NP  build_direct_call
 [ ] build_direct_call_with_operand_bundles
-[ ] build_indirect_call
+[x] build_indirect_call
 [ ] build_indirect_call_with_operand_bundles
 [ ] build_call_help
 [ ] build_call_with_operand_bundles_help
-[ ] build_invoke
+[x] build_invoke
 [ ] build_direct_invoke
-[ ] build_indirect_invoke
+[x] build_indirect_invoke
 [ ] build_invoke_help
-[ ] build_landing_pad
-[ ] build_resume
+[x] build_landing_pad
+[x] build_resume
 [ ] build_gep
 [ ] build_in_bounds_gep
 [ ] build_struct_gep
 [ ] build_ptr_diff
-[ ] build_phi
+[x] build_phi
 [/] build_store
 [/] build_load
 [/] build_alloca
 [ ] build_array_alloca
-[ ] build_memcpy
-[ ] build_memmove
-[ ] build_memset
+[x] build_memcpy
+[x] build_memmove
+[x] build_memset
 [ ] build_malloc
 [ ] build_array_malloc
 [ ] build_free
@@ -76,7 +78,7 @@ NP  build_direct_call
 [x] build_int_nsw_mul
 [x] build_int_nuw_mul
 [x] build_float_mul
-[ ] build_binop
+[x] build_binop
 [ ] build_cast
 [ ] build_pointer_cast
 [x] build_int_compare
@@ -89,12 +91,12 @@ NP  build_direct_call
 [x] build_int_nuw_neg
 [x] build_float_neg
 [x] build_not
-[ ] build_extract_value
+[/] build_extract_value
 [ ] build_insert_value
-[ ] build_extract_element
-[ ] build_insert_element
-[ ] build_unreachable
-[ ] build_fence
+[x] build_extract_element
+[x] build_insert_element
+[x] build_unreachable
+[x] build_fence
 [ ] build_is_null
 [ ] build_is_not_null
 [ ] build_int_to_ptr
@@ -103,10 +105,10 @@ NP  build_direct_call
 [ ] build_select
 [ ] build_global_string
 [ ] build_global_string_ptr
-[ ] build_shuffle_vector
+[x] build_shuffle_vector
 [ ] build_va_arg
-[ ] build_atomicrmw
-[ ] build_cmpxchg
+[x] build_atomicrmw
+[x] build_cmpxchg
  */
 
 #[derive(Debug, Clone, PartialEq)]
@@ -115,7 +117,41 @@ pub enum Stmt<'s> {
     While(WhileStmt<'s>),
     Return(ReturnStmt<'s>),
     Bind(BindStmt<'s>),
+    BindTuple(BindTupleStmt<'s>),
     Assign(AssignStmt<'s>),
+    /// Aborts the program with a code, which the JIT reports as an error.
+    Trap(TrapCode),
+    /// Marks a point that control never reaches, which traps with [`TrapCode::UNREACHABLE`] if it does.
+    Unreachable,
+    Assert(AssertStmt<'s>),
+    AtomicStore(AtomicStoreStmt<'s>),
+    /// Orders the memory operations before and after it as an atomic operation with the ordering would, without
+    /// accessing memory itself.
+    Fence(AtomicOrdering),
+    /// Copies bytes between memory that must not overlap.
+    MemCopy(MemCopyStmt<'s>),
+    /// Copies bytes between memory that may overlap, as if through a temporary buffer.
+    MemMove(MemCopyStmt<'s>),
+    MemSet(MemSetStmt<'s>),
+    /// Calls a function for its effects and discards its outputs, which may be none.
+    Call(Call<'s>),
+    /// Like [`Stmt::Call`], but calls a function by its address.
+    CallIndirect(Box<CallIndirect<'s>>),
+    /// Returns the outputs of a call that reuses the stack frame of the caller, so that recursion in tail position
+    /// runs in constant stack space. The caller and the callee must both use [`CallConv::Tail`] and have the same
+    /// outputs.
+    ///
+    /// [`CallConv::Tail`]: crate::function::CallConv::Tail
+    TailCall(Call<'s>),
+    /// Like [`Stmt::TailCall`], but calls a function by its address.
+    TailCallIndirect(Box<CallIndirect<'s>>),
+    /// Runs a block and handles unwinding out of the calls in it, e.g. a C++ exception or a Rust panic raised by a
+    /// function that may unwind. The function must have a personality, see [`Function::with_personality`], and tail
+    /// calls cannot be in the body.
+    ///
+    /// [`Function::with_personality`]: crate::function::Function::with_personality
+    Try(TryStmt<'s>),
+    Spanned(Box<Spanned<'s, Stmt<'s>>>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -148,8 +184,125 @@ pub struct BindStmt<'s> {
     pub value: Value<'s>,
 }
 
+/// Binds each of the values produced by a call with multiple outputs or a checked operation to its own name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BindTupleStmt<'s> {
+    pub names: Vec<Cow<'s, str>>,
+    pub value: Value<'s>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct AssignStmt<'s> {
     pub var: Var<'s>,
     pub value: Value<'s>,
-}
\ No newline at end of file
+}
+
+/// Traps with `code` unless `condition` is true.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssertStmt<'s> {
+    pub condition: Value<'s>,
+    pub code: TrapCode,
+}
+
+/// Atomically stores `value` at the address `ptr`, which must be aligned to the size of its type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtomicStoreStmt<'s> {
+    pub ptr: Value<'s>,
+    pub value: Value<'s>,
+    pub ordering: AtomicOrdering,
+}
+
+/// Copies `len` bytes, a `u64`, from the address `src` to the address `dest`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemCopyStmt<'s> {
+    pub dest: Value<'s>,
+    pub src: Value<'s>,
+    pub len: Value<'s>,
+    /// The alignment in bytes that both addresses are known to have, which is a power of two.
+    pub align: u32,
+}
+
+/// Sets `len` bytes, a `u64`, at the address `dest` to `value`, a `u8`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemSetStmt<'s> {
+    pub dest: Value<'s>,
+    pub value: Value<'s>,
+    pub len: Value<'s>,
+    /// The alignment in bytes that the address is known to have, which is a power of two.
+    pub align: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TryStmt<'s> {
+    pub body: Block<'s>,
+    pub handler: Handler<'s>,
+}
+
+/// What a [`TryStmt`] does when unwinding out of its body.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Handler<'s> {
+    /// Runs the block, after which unwinding continues.
+    Cleanup(Block<'s>),
+    /// Stops unwinding and runs the block with the exception object bound to `name` as a `ptr`, after which execution
+    /// continues after the try statement. Which exceptions are caught is up to the personality, which is asked to
+    /// catch all of them.
+    ///
+    /// The try statement does not finish the exception, so the block must hand the exception object to the runtime
+    /// that raised it, such as by calling `__cxa_begin_catch` and then `__cxa_end_catch` with [`Stmt::Call`] for a C++
+    /// exception. A Rust panic can only be finished by Rust, by raising it again with `_Unwind_RaiseException` inside
+    /// `std::panic::catch_unwind`. Otherwise the exception object leaks, and its runtime still considers it in flight.
+    Catch {
+        name: Cow<'s, str>,
+        block: Block<'s>,
+    },
+}
+
+/// A code identifying why a program trapped.
+///
+/// Codes from 1 to [`TrapCode::MAX_USER`] are free for [`Stmt::Trap`] and [`Stmt::Assert`] to use, while the rest are
+/// reserved for traps raised by the backends themselves. The reserved codes have the same values as the trap codes
+/// reserved by Cranelift.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TrapCode(u8);
+
+impl TrapCode {
+    pub const MAX_USER: u8 = 249;
+    /// Raised by reaching [`Stmt::Unreachable`].
+    pub const UNREACHABLE: TrapCode = TrapCode(250);
+    pub const STACK_OVERFLOW: TrapCode = TrapCode(251);
+    /// Raised by a trapping checked operation that overflowed.
+    pub const INTEGER_OVERFLOW: TrapCode = TrapCode(252);
+    pub const INTEGER_DIVISION_BY_ZERO: TrapCode = TrapCode(254);
+
+    /// A code for [`Stmt::Trap`] and [`Stmt::Assert`], or `None` if `code` is zero or reserved.
+    pub const fn user(code: u8) -> Option<TrapCode> {
+        match code {
+            1..=Self::MAX_USER => Some(TrapCode(code)),
+            _ => None,
+        }
+    }
+
+    /// Any code, including reserved ones, or `None` if `code` is zero.
+    pub const fn from_raw(code: u8) -> Option<TrapCode> {
+        match code {
+            0 => None,
+            _ => Some(TrapCode(code)),
+        }
+    }
+
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl Display for TrapCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            TrapCode::UNREACHABLE => write!(f, "unreachable code"),
+            TrapCode::STACK_OVERFLOW => write!(f, "stack overflow"),
+            TrapCode::INTEGER_OVERFLOW => write!(f, "integer overflow"),
+            TrapCode::INTEGER_DIVISION_BY_ZERO => write!(f, "integer division by zero"),
+            TrapCode(code) => write!(f, "code {}", code),
+        }
+    }
+}
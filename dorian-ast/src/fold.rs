@@ -0,0 +1,571 @@
+//! Transformation of an owned AST into a new one.
+//!
+//! Every method of [`Fold`] defaults to the `walk_*` function of the same node, which folds its children and rebuilds
+//! the node from them. A pass that replaces one kind of node with another, e.g. an expression with a literal, overrides
+//! [`Fold::fold_value`].
+
+use crate::block::Block;
+use crate::block::stmt::{
+    AssertStmt, AssignStmt, AtomicStoreStmt, BindStmt, BindTupleStmt, Handler, IfElse, IfStmt, MemCopyStmt,
+    MemSetStmt, ReturnStmt, Stmt, TryStmt, WhileStmt,
+};
+use crate::function::Function;
+use crate::global::Global;
+use crate::module::Module;
+use crate::span::{Span, Spanned};
+use crate::structure::Struct;
+use crate::val::{
+    Arg, AtomicLoad, AtomicRmw, Bin, Call, CallIndirect, Checked, CmpXchg, ContextValue, Expr, ExtractElement, FloatCmp,
+    FuncAddr, InsertElement, Intrinsic, Lit, Reduce, Shuffle, Splat, Una, Value, Var, Vector,
+};
+
+pub trait Fold<'s> {
+    fn fold_module(&mut self, module: Module<'s>) -> Module<'s> {
+        walk_module(self, module)
+    }
+
+    fn fold_struct(&mut self, ast_struct: Struct<'s>) -> Struct<'s> {
+        ast_struct
+    }
+
+    fn fold_global(&mut self, global: Global<'s>) -> Global<'s> {
+        walk_global(self, global)
+    }
+
+    fn fold_function(&mut self, function: Function<'s>) -> Function<'s> {
+        walk_function(self, function)
+    }
+
+    fn fold_span(&mut self, span: Span<'s>) -> Span<'s> {
+        span
+    }
+
+    fn fold_block(&mut self, block: Block<'s>) -> Block<'s> {
+        walk_block(self, block)
+    }
+
+    fn fold_stmt(&mut self, stmt: Stmt<'s>) -> Stmt<'s> {
+        walk_stmt(self, stmt)
+    }
+
+    fn fold_if_stmt(&mut self, stmt: IfStmt<'s>) -> IfStmt<'s> {
+        walk_if_stmt(self, stmt)
+    }
+
+    fn fold_while_stmt(&mut self, stmt: WhileStmt<'s>) -> WhileStmt<'s> {
+        walk_while_stmt(self, stmt)
+    }
+
+    fn fold_return_stmt(&mut self, stmt: ReturnStmt<'s>) -> ReturnStmt<'s> {
+        walk_return_stmt(self, stmt)
+    }
+
+    fn fold_bind_stmt(&mut self, stmt: BindStmt<'s>) -> BindStmt<'s> {
+        walk_bind_stmt(self, stmt)
+    }
+
+    fn fold_bind_tuple_stmt(&mut self, stmt: BindTupleStmt<'s>) -> BindTupleStmt<'s> {
+        walk_bind_tuple_stmt(self, stmt)
+    }
+
+    fn fold_assign_stmt(&mut self, stmt: AssignStmt<'s>) -> AssignStmt<'s> {
+        walk_assign_stmt(self, stmt)
+    }
+
+    fn fold_assert_stmt(&mut self, stmt: AssertStmt<'s>) -> AssertStmt<'s> {
+        walk_assert_stmt(self, stmt)
+    }
+
+    fn fold_atomic_store_stmt(&mut self, stmt: AtomicStoreStmt<'s>) -> AtomicStoreStmt<'s> {
+        walk_atomic_store_stmt(self, stmt)
+    }
+
+    /// Folds both [`Stmt::MemCopy`] and [`Stmt::MemMove`].
+    fn fold_mem_copy_stmt(&mut self, stmt: MemCopyStmt<'s>) -> MemCopyStmt<'s> {
+        walk_mem_copy_stmt(self, stmt)
+    }
+
+    fn fold_mem_set_stmt(&mut self, stmt: MemSetStmt<'s>) -> MemSetStmt<'s> {
+        walk_mem_set_stmt(self, stmt)
+    }
+
+    fn fold_try_stmt(&mut self, stmt: TryStmt<'s>) -> TryStmt<'s> {
+        walk_try_stmt(self, stmt)
+    }
+
+    fn fold_value(&mut self, value: Value<'s>) -> Value<'s> {
+        walk_value(self, value)
+    }
+
+    fn fold_arg(&mut self, arg: Arg) -> Arg {
+        arg
+    }
+
+    fn fold_var(&mut self, var: Var<'s>) -> Var<'s> {
+        var
+    }
+
+    fn fold_expr(&mut self, expr: Expr<'s>) -> Expr<'s> {
+        walk_expr(self, expr)
+    }
+
+    fn fold_bin(&mut self, bin: Bin<'s>) -> Bin<'s> {
+        walk_bin(self, bin)
+    }
+
+    fn fold_una(&mut self, una: Una<'s>) -> Una<'s> {
+        walk_una(self, una)
+    }
+
+    fn fold_checked(&mut self, checked: Checked<'s>) -> Checked<'s> {
+        walk_checked(self, checked)
+    }
+
+    fn fold_float_cmp(&mut self, float_cmp: FloatCmp<'s>) -> FloatCmp<'s> {
+        walk_float_cmp(self, float_cmp)
+    }
+
+    fn fold_splat(&mut self, splat: Splat<'s>) -> Splat<'s> {
+        walk_splat(self, splat)
+    }
+
+    fn fold_vector(&mut self, vector: Vector<'s>) -> Vector<'s> {
+        walk_vector(self, vector)
+    }
+
+    fn fold_extract_element(&mut self, extract: ExtractElement<'s>) -> ExtractElement<'s> {
+        walk_extract_element(self, extract)
+    }
+
+    fn fold_insert_element(&mut self, insert: InsertElement<'s>) -> InsertElement<'s> {
+        walk_insert_element(self, insert)
+    }
+
+    fn fold_shuffle(&mut self, shuffle: Shuffle<'s>) -> Shuffle<'s> {
+        walk_shuffle(self, shuffle)
+    }
+
+    fn fold_reduce(&mut self, reduce: Reduce<'s>) -> Reduce<'s> {
+        walk_reduce(self, reduce)
+    }
+
+    fn fold_lit(&mut self, lit: Lit) -> Lit {
+        lit
+    }
+
+    fn fold_call(&mut self, call: Call<'s>) -> Call<'s> {
+        walk_call(self, call)
+    }
+
+    fn fold_func_addr(&mut self, func_addr: FuncAddr<'s>) -> FuncAddr<'s> {
+        func_addr
+    }
+
+    fn fold_call_indirect(&mut self, call: CallIndirect<'s>) -> CallIndirect<'s> {
+        walk_call_indirect(self, call)
+    }
+
+    fn fold_intrinsic(&mut self, intrinsic: Intrinsic<'s>) -> Intrinsic<'s> {
+        walk_intrinsic(self, intrinsic)
+    }
+
+    fn fold_atomic_load(&mut self, load: AtomicLoad<'s>) -> AtomicLoad<'s> {
+        walk_atomic_load(self, load)
+    }
+
+    fn fold_atomic_rmw(&mut self, rmw: AtomicRmw<'s>) -> AtomicRmw<'s> {
+        walk_atomic_rmw(self, rmw)
+    }
+
+    fn fold_cmpxchg(&mut self, cmpxchg: CmpXchg<'s>) -> CmpXchg<'s> {
+        walk_cmpxchg(self, cmpxchg)
+    }
+}
+
+pub fn walk_module<'s, F: Fold<'s> + ?Sized>(folder: &mut F, module: Module<'s>) -> Module<'s> {
+    Module {
+        structs: module.structs.into_iter().map(|x| folder.fold_struct(x)).collect(),
+        globals: module.globals.into_iter().map(|x| folder.fold_global(x)).collect(),
+        functions: module.functions.into_iter().map(|x| folder.fold_function(x)).collect(),
+        ..module
+    }
+}
+
+pub fn walk_global<'s, F: Fold<'s> + ?Sized>(folder: &mut F, global: Global<'s>) -> Global<'s> {
+    Global {
+        value: global.value.map(|x| folder.fold_value(x)),
+        ..global
+    }
+}
+
+pub fn walk_function<'s, F: Fold<'s> + ?Sized>(folder: &mut F, function: Function<'s>) -> Function<'s> {
+    Function {
+        span: function.span.map(|x| folder.fold_span(x)),
+        body: folder.fold_block(function.body),
+        ..function
+    }
+}
+
+pub fn walk_block<'s, F: Fold<'s> + ?Sized>(folder: &mut F, block: Block<'s>) -> Block<'s> {
+    Block {
+        stmts: block.stmts.into_iter().map(|x| folder.fold_stmt(x)).collect(),
+    }
+}
+
+pub fn walk_stmt<'s, F: Fold<'s> + ?Sized>(folder: &mut F, stmt: Stmt<'s>) -> Stmt<'s> {
+    match stmt {
+        Stmt::If(x) => Stmt::If(folder.fold_if_stmt(x)),
+        Stmt::While(x) => Stmt::While(folder.fold_while_stmt(x)),
+        Stmt::Return(x) => Stmt::Return(folder.fold_return_stmt(x)),
+        Stmt::Bind(x) => Stmt::Bind(folder.fold_bind_stmt(x)),
+        Stmt::BindTuple(x) => Stmt::BindTuple(folder.fold_bind_tuple_stmt(x)),
+        Stmt::Assign(x) => Stmt::Assign(folder.fold_assign_stmt(x)),
+        Stmt::Trap(x) => Stmt::Trap(x),
+        Stmt::Unreachable => Stmt::Unreachable,
+        Stmt::Assert(x) => Stmt::Assert(folder.fold_assert_stmt(x)),
+        Stmt::AtomicStore(x) => Stmt::AtomicStore(folder.fold_atomic_store_stmt(x)),
+        Stmt::Fence(x) => Stmt::Fence(x),
+        Stmt::MemCopy(x) => Stmt::MemCopy(folder.fold_mem_copy_stmt(x)),
+        Stmt::MemMove(x) => Stmt::MemMove(folder.fold_mem_copy_stmt(x)),
+        Stmt::MemSet(x) => Stmt::MemSet(folder.fold_mem_set_stmt(x)),
+        Stmt::Call(x) => Stmt::Call(folder.fold_call(x)),
+        Stmt::CallIndirect(x) => Stmt::CallIndirect(Box::new(folder.fold_call_indirect(*x))),
+        Stmt::TailCall(x) => Stmt::TailCall(folder.fold_call(x)),
+        Stmt::TailCallIndirect(x) => Stmt::TailCallIndirect(Box::new(folder.fold_call_indirect(*x))),
+        Stmt::Try(x) => Stmt::Try(folder.fold_try_stmt(x)),
+        Stmt::Spanned(x) => {
+            let Spanned { span, node } = *x;
+            let span = folder.fold_span(span);
+            Stmt::Spanned(Box::new(Spanned::new(span, folder.fold_stmt(node))))
+        }
+    }
+}
+
+pub fn walk_if_stmt<'s, F: Fold<'s> + ?Sized>(folder: &mut F, stmt: IfStmt<'s>) -> IfStmt<'s> {
+    IfStmt {
+        condition: folder.fold_value(stmt.condition),
+        then_block: folder.fold_block(stmt.then_block),
+        if_else: stmt.if_else.map(|x| match x {
+            IfElse::If(x) => IfElse::If(Box::new(folder.fold_if_stmt(*x))),
+            IfElse::Else(x) => IfElse::Else(folder.fold_block(x)),
+        }),
+    }
+}
+
+pub fn walk_while_stmt<'s, F: Fold<'s> + ?Sized>(folder: &mut F, stmt: WhileStmt<'s>) -> WhileStmt<'s> {
+    WhileStmt {
+        condition: folder.fold_value(stmt.condition),
+        loop_block: folder.fold_block(stmt.loop_block),
+    }
+}
+
+pub fn walk_return_stmt<'s, F: Fold<'s> + ?Sized>(folder: &mut F, stmt: ReturnStmt<'s>) -> ReturnStmt<'s> {
+    ReturnStmt {
+        values: stmt.values.into_iter().map(|x| folder.fold_value(x)).collect(),
+    }
+}
+
+pub fn walk_bind_stmt<'s, F: Fold<'s> + ?Sized>(folder: &mut F, stmt: BindStmt<'s>) -> BindStmt<'s> {
+    BindStmt {
+        value: folder.fold_value(stmt.value),
+        ..stmt
+    }
+}
+
+pub fn walk_bind_tuple_stmt<'s, F: Fold<'s> + ?Sized>(folder: &mut F, stmt: BindTupleStmt<'s>) -> BindTupleStmt<'s> {
+    BindTupleStmt {
+        value: folder.fold_value(stmt.value),
+        ..stmt
+    }
+}
+
+pub fn walk_assign_stmt<'s, F: Fold<'s> + ?Sized>(folder: &mut F, stmt: AssignStmt<'s>) -> AssignStmt<'s> {
+    AssignStmt {
+        var: folder.fold_var(stmt.var),
+        value: folder.fold_value(stmt.value),
+    }
+}
+
+pub fn walk_assert_stmt<'s, F: Fold<'s> + ?Sized>(folder: &mut F, stmt: AssertStmt<'s>) -> AssertStmt<'s> {
+    AssertStmt {
+        condition: folder.fold_value(stmt.condition),
+        ..stmt
+    }
+}
+
+pub fn walk_atomic_store_stmt<'s, F: Fold<'s> + ?Sized>(
+    folder: &mut F,
+    stmt: AtomicStoreStmt<'s>,
+) -> AtomicStoreStmt<'s> {
+    AtomicStoreStmt {
+        ptr: folder.fold_value(stmt.ptr),
+        value: folder.fold_value(stmt.value),
+        ..stmt
+    }
+}
+
+pub fn walk_mem_copy_stmt<'s, F: Fold<'s> + ?Sized>(folder: &mut F, stmt: MemCopyStmt<'s>) -> MemCopyStmt<'s> {
+    MemCopyStmt {
+        dest: folder.fold_value(stmt.dest),
+        src: folder.fold_value(stmt.src),
+        len: folder.fold_value(stmt.len),
+        ..stmt
+    }
+}
+
+pub fn walk_mem_set_stmt<'s, F: Fold<'s> + ?Sized>(folder: &mut F, stmt: MemSetStmt<'s>) -> MemSetStmt<'s> {
+    MemSetStmt {
+        dest: folder.fold_value(stmt.dest),
+        value: folder.fold_value(stmt.value),
+        len: folder.fold_value(stmt.len),
+        ..stmt
+    }
+}
+
+pub fn walk_try_stmt<'s, F: Fold<'s> + ?Sized>(folder: &mut F, stmt: TryStmt<'s>) -> TryStmt<'s> {
+    TryStmt {
+        body: folder.fold_block(stmt.body),
+        handler: match stmt.handler {
+            Handler::Cleanup(x) => Handler::Cleanup(folder.fold_block(x)),
+            Handler::Catch { name, block } => Handler::Catch { name, block: folder.fold_block(block) },
+        },
+    }
+}
+
+pub fn walk_value<'s, F: Fold<'s> + ?Sized>(folder: &mut F, value: Value<'s>) -> Value<'s> {
+    match value {
+        Value::Context(ContextValue::Arg(x)) => folder.fold_arg(x).into(),
+        Value::Context(ContextValue::Var(x)) => folder.fold_var(x).into(),
+        Value::Expr(x) => folder.fold_expr(*x).into(),
+        Value::Lit(x) => folder.fold_lit(x).into(),
+        Value::Call(x) => folder.fold_call(x).into(),
+        Value::FuncAddr(x) => folder.fold_func_addr(x).into(),
+        Value::CallIndirect(x) => folder.fold_call_indirect(*x).into(),
+        Value::Intrinsic(x) => folder.fold_intrinsic(x).into(),
+        Value::AtomicLoad(x) => folder.fold_atomic_load(*x).into(),
+        Value::AtomicRmw(x) => folder.fold_atomic_rmw(*x).into(),
+        Value::CmpXchg(x) => folder.fold_cmpxchg(*x).into(),
+        Value::Spanned(x) => {
+            let Spanned { span, node } = *x;
+            let span = folder.fold_span(span);
+            Spanned::new(span, folder.fold_value(node)).into()
+        }
+    }
+}
+
+pub fn walk_expr<'s, F: Fold<'s> + ?Sized>(folder: &mut F, expr: Expr<'s>) -> Expr<'s> {
+    match expr {
+        Expr::Bin(x) => folder.fold_bin(x).into(),
+        Expr::Una(x) => folder.fold_una(x).into(),
+        Expr::Checked(x) => folder.fold_checked(x).into(),
+        Expr::FloatCmp(x) => folder.fold_float_cmp(x).into(),
+        Expr::Splat(x) => folder.fold_splat(x).into(),
+        Expr::Vector(x) => folder.fold_vector(x).into(),
+        Expr::ExtractElement(x) => folder.fold_extract_element(x).into(),
+        Expr::InsertElement(x) => folder.fold_insert_element(x).into(),
+        Expr::Shuffle(x) => folder.fold_shuffle(x).into(),
+        Expr::Reduce(x) => folder.fold_reduce(x).into(),
+    }
+}
+
+pub fn walk_bin<'s, F: Fold<'s> + ?Sized>(folder: &mut F, bin: Bin<'s>) -> Bin<'s> {
+    Bin {
+        lhs: folder.fold_value(bin.lhs),
+        rhs: folder.fold_value(bin.rhs),
+        ..bin
+    }
+}
+
+pub fn walk_una<'s, F: Fold<'s> + ?Sized>(folder: &mut F, una: Una<'s>) -> Una<'s> {
+    Una {
+        operand: folder.fold_value(una.operand),
+        ..una
+    }
+}
+
+pub fn walk_checked<'s, F: Fold<'s> + ?Sized>(folder: &mut F, checked: Checked<'s>) -> Checked<'s> {
+    Checked {
+        lhs: folder.fold_value(checked.lhs),
+        rhs: folder.fold_value(checked.rhs),
+        ..checked
+    }
+}
+
+pub fn walk_float_cmp<'s, F: Fold<'s> + ?Sized>(folder: &mut F, float_cmp: FloatCmp<'s>) -> FloatCmp<'s> {
+    FloatCmp {
+        lhs: folder.fold_value(float_cmp.lhs),
+        rhs: folder.fold_value(float_cmp.rhs),
+        ..float_cmp
+    }
+}
+
+pub fn walk_splat<'s, F: Fold<'s> + ?Sized>(folder: &mut F, splat: Splat<'s>) -> Splat<'s> {
+    Splat {
+        value: folder.fold_value(splat.value),
+        ..splat
+    }
+}
+
+pub fn walk_vector<'s, F: Fold<'s> + ?Sized>(folder: &mut F, vector: Vector<'s>) -> Vector<'s> {
+    Vector {
+        elems: vector.elems.into_iter().map(|x| folder.fold_value(x)).collect(),
+    }
+}
+
+pub fn walk_extract_element<'s, F: Fold<'s> + ?Sized>(
+    folder: &mut F,
+    extract: ExtractElement<'s>,
+) -> ExtractElement<'s> {
+    ExtractElement {
+        vector: folder.fold_value(extract.vector),
+        ..extract
+    }
+}
+
+pub fn walk_insert_element<'s, F: Fold<'s> + ?Sized>(folder: &mut F, insert: InsertElement<'s>) -> InsertElement<'s> {
+    InsertElement {
+        vector: folder.fold_value(insert.vector),
+        value: folder.fold_value(insert.value),
+        ..insert
+    }
+}
+
+pub fn walk_shuffle<'s, F: Fold<'s> + ?Sized>(folder: &mut F, shuffle: Shuffle<'s>) -> Shuffle<'s> {
+    Shuffle {
+        lhs: folder.fold_value(shuffle.lhs),
+        rhs: folder.fold_value(shuffle.rhs),
+        ..shuffle
+    }
+}
+
+pub fn walk_reduce<'s, F: Fold<'s> + ?Sized>(folder: &mut F, reduce: Reduce<'s>) -> Reduce<'s> {
+    Reduce {
+        vector: folder.fold_value(reduce.vector),
+        ..reduce
+    }
+}
+
+pub fn walk_call<'s, F: Fold<'s> + ?Sized>(folder: &mut F, call: Call<'s>) -> Call<'s> {
+    Call {
+        args: call.args.into_iter().map(|x| folder.fold_value(x)).collect(),
+        ..call
+    }
+}
+
+pub fn walk_call_indirect<'s, F: Fold<'s> + ?Sized>(folder: &mut F, call: CallIndirect<'s>) -> CallIndirect<'s> {
+    CallIndirect {
+        callee: folder.fold_value(call.callee),
+        args: call.args.into_iter().map(|x| folder.fold_value(x)).collect(),
+        ..call
+    }
+}
+
+pub fn walk_intrinsic<'s, F: Fold<'s> + ?Sized>(folder: &mut F, intrinsic: Intrinsic<'s>) -> Intrinsic<'s> {
+    Intrinsic {
+        args: intrinsic.args.into_iter().map(|x| folder.fold_value(x)).collect(),
+        ..intrinsic
+    }
+}
+
+pub fn walk_atomic_load<'s, F: Fold<'s> + ?Sized>(folder: &mut F, load: AtomicLoad<'s>) -> AtomicLoad<'s> {
+    AtomicLoad {
+        ptr: folder.fold_value(load.ptr),
+        ..load
+    }
+}
+
+pub fn walk_atomic_rmw<'s, F: Fold<'s> + ?Sized>(folder: &mut F, rmw: AtomicRmw<'s>) -> AtomicRmw<'s> {
+    AtomicRmw {
+        ptr: folder.fold_value(rmw.ptr),
+        value: folder.fold_value(rmw.value),
+        ..rmw
+    }
+}
+
+pub fn walk_cmpxchg<'s, F: Fold<'s> + ?Sized>(folder: &mut F, cmpxchg: CmpXchg<'s>) -> CmpXchg<'s> {
+    CmpXchg {
+        ptr: folder.fold_value(cmpxchg.ptr),
+        expected: folder.fold_value(cmpxchg.expected),
+        replacement: folder.fold_value(cmpxchg.replacement),
+        ..cmpxchg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{walk_value, Fold};
+    use crate::function::Function;
+    use crate::span::Span;
+    use crate::val::util::*;
+    use crate::val::{ContextValue, Value, Var};
+    use crate::visit_mut::VisitMut;
+
+    /// Replaces every variable with a literal of its name's length.
+    struct Lengths;
+
+    impl<'s> Fold<'s> for Lengths {
+        fn fold_value(&mut self, value: Value<'s>) -> Value<'s> {
+            match value {
+                Value::Context(ContextValue::Var(x)) => lit(x.name.len() as u32),
+                value => walk_value(self, value),
+            }
+        }
+    }
+
+    struct Uppercase;
+
+    impl<'s> Fold<'s> for Uppercase {
+        fn fold_var(&mut self, var: Var<'s>) -> Var<'s> {
+            Var { name: var.name.to_uppercase().into() }
+        }
+    }
+
+    impl VisitMut for Uppercase {
+        fn visit_var_mut(&mut self, var: &mut Var) {
+            var.name = var.name.to_uppercase().into();
+        }
+    }
+
+    fn function() -> Function<'static> {
+        Function::new("f").build_block(|scope| {
+            scope.bind("a", call("g", vec![var("bb")]));
+            scope.if_then(var("c"), |scope| {
+                scope.bind("x", call("h", vec![add(var("dd"), var("e"))]));
+            });
+            scope.loop_while(var("f"), |scope| {
+                scope.assign(var("g"), var("hhh"));
+            });
+            scope.spanned(Span::new("f.dn", 1, 1), |scope| {
+                scope.ret([var("ii")]);
+            });
+        })
+    }
+
+    #[test]
+    fn folds_replace_values_with_other_kinds() {
+        let expected = Function::new("f").build_block(|scope| {
+            scope.bind("a", call("g", vec![lit(2u32)]));
+            scope.if_then(lit(1u32), |scope| {
+                scope.bind("x", call("h", vec![add(lit(2u32), lit(1u32))]));
+            });
+            scope.loop_while(lit(1u32), |scope| {
+                // The target of an assignment is a variable rather than a value
+                scope.assign(var("g"), lit(3u32));
+            });
+            scope.spanned(Span::new("f.dn", 1, 1), |scope| {
+                scope.ret([lit(2u32)]);
+            });
+        });
+
+        assert_eq!(Lengths.fold_function(function()), expected);
+    }
+
+    #[test]
+    fn folds_reach_the_same_nodes_as_mutable_visitors() {
+        let mut visited = function();
+        Uppercase.visit_function_mut(&mut visited);
+
+        assert_eq!(Uppercase.fold_function(function()), visited);
+        assert_ne!(visited, function());
+    }
+}
@@ -1,6 +1,7 @@
 use std::borrow::Cow;
 use crate::block::Block;
 use crate::block::builder::BlockBuilder;
+use crate::span::Span;
 use crate::ty::Type;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -8,17 +9,22 @@ pub struct Function<'s> {
     pub name: Cow<'s, str>,
     pub signature: Signature,
     pub body: Block<'s>,
+    pub linkage: Linkage,
+    /// The function that decides how unwinding proceeds through the function, e.g. `__gxx_personality_v0` for C++
+    /// exceptions. It is required by [`Stmt::Try`](crate::block::stmt::Stmt::Try).
+    pub personality: Option<Cow<'s, str>>,
+    pub span: Option<Span<'s>>,
 }
 
 impl<'s> Function<'s> {
     pub fn new(name: impl Into<Cow<'s, str>>) -> Self {
         Function {
             name: name.into(),
-            signature: Signature {
-                input: Vec::new(),
-                output: Vec::new(),
-            },
+            signature: Signature::default(),
             body: Block::new(),
+            linkage: Linkage::Export,
+            personality: None,
+            span: None,
         }
     }
 
@@ -32,6 +38,31 @@ impl<'s> Function<'s> {
         self
     }
 
+    pub fn with_call_conv(mut self, call_conv: CallConv) -> Self {
+        self.signature.call_conv = call_conv;
+        self
+    }
+
+    pub fn with_variadic(mut self, variadic: bool) -> Self {
+        self.signature.variadic = variadic;
+        self
+    }
+
+    pub fn with_linkage(mut self, linkage: Linkage) -> Self {
+        self.linkage = linkage;
+        self
+    }
+
+    pub fn with_personality(mut self, personality: impl Into<Cow<'s, str>>) -> Self {
+        self.personality = Some(personality.into());
+        self
+    }
+
+    pub fn with_span(mut self, span: Span<'s>) -> Self {
+        self.span = Some(span);
+        self
+    }
+
     pub fn with_block(mut self, block: Block<'s>) -> Self {
         self.body = block;
         self
@@ -45,8 +76,63 @@ impl<'s> Function<'s> {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
 pub struct Signature {
     pub input: Vec<Type>,
     pub output: Vec<Type>,
+    pub call_conv: CallConv,
+    /// Whether calls pass any number of arguments after the inputs, like `...` in C. Only imported functions with the
+    /// C calling convention can be variadic, and the extra arguments are passed after the default argument promotions
+    /// of C, see [`Type::c_promoted`].
+    pub variadic: bool,
+}
+
+impl Signature {
+    pub fn add_input(mut self, param: Type) -> Self {
+        self.input.push(param);
+        self
+    }
+
+    pub fn add_output(mut self, output: Type) -> Self {
+        self.output.push(output);
+        self
+    }
+
+    pub fn with_call_conv(mut self, call_conv: CallConv) -> Self {
+        self.call_conv = call_conv;
+        self
+    }
+
+    pub fn with_variadic(mut self, variadic: bool) -> Self {
+        self.variadic = variadic;
+        self
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum CallConv {
+    /// The C calling convention of the target, e.g. System V on x86-64 Linux. Only functions with this convention can
+    /// be called from outside of the module.
+    #[default]
+    C,
+    /// A convention that the backend is free to choose in order to make calls faster.
+    Fast,
+    /// A convention that keeps the caller fast by making calls to rarely executed functions slower.
+    Cold,
+    /// A convention that supports guaranteed tail calls, see [`Stmt::TailCall`](crate::block::stmt::Stmt::TailCall).
+    Tail,
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum Linkage {
+    /// Visible outside of the module.
+    #[default]
+    Export,
+    /// Only visible inside of the module.
+    Internal,
+    /// Visible outside of the module, but may be replaced by a definition with the same name when linked.
+    Weak,
+    /// Defined outside of the module, e.g. by a C library, and found by name when the module is loaded. The body of
+    /// the function is ignored.
+    Import,
 }
\ No newline at end of file
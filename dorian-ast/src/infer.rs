@@ -0,0 +1,535 @@
+//! Inference of the types of untyped literals, i.e. [`Int::Untyped`] and [`Float::Untyped`].
+//!
+//! Every value is given a type variable, and the variables of values that must have the same type are unified: the
+//! operands of a binary operation, the arguments of an intrinsic, a variable and the values bound or assigned to it, an
+//! argument and its parameter, a returned value and its output, a condition and `bool`, an address and `ptr`, and the
+//! length of a memory operation and `u64`. An untyped literal takes the type of its variable, and an untyped integer
+//! may also become a float.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use crate::block::Block;
+use crate::block::stmt::{
+    AssertStmt, AssignStmt, AtomicStoreStmt, BindStmt, BindTupleStmt, Handler, IfElse, IfStmt, MemCopyStmt,
+    MemSetStmt, ReturnStmt, TryStmt, WhileStmt,
+};
+use crate::function::{Function, Signature};
+use crate::global::Global;
+use crate::module::Module;
+use crate::resolve::ResolveError;
+use crate::ty::{BoolType, FloatType, IntType, IntWidth, NumType, PtrType, ScalarType, Type};
+use crate::val::{
+    BinOp, Call, CallIndirect, Checked, ContextValue, Expr, ExtractElement, Float, Int, Lit, Num, OnOverflow, Reduce,
+    SignedInt, UnaOp, UnsignedInt, Value,
+};
+use crate::visit::{walk_block, Visit};
+use crate::visit_mut::VisitMut;
+
+/// Returns `module` with the type of every untyped literal inferred, only copying it if it has any.
+pub fn type_literals<'m, 's>(module: &'m Module<'s>) -> Result<Cow<'m, Module<'s>>, Vec<ResolveError>> {
+    let mut inferrer = Inferrer {
+        signatures: module.functions.iter().map(|x| (x.name.as_ref(), &x.signature)).collect(),
+        function: None,
+        levels: Vec::new(),
+        parents: Vec::new(),
+        types: Vec::new(),
+        literals: Vec::new(),
+    };
+    inferrer.visit_module(module);
+
+    if inferrer.literals.is_empty() {
+        return Ok(Cow::Borrowed(module));
+    }
+
+    let mut typer = Typer {
+        literals: inferrer.literals
+            .iter()
+            .map(|(node, function)| (inferrer.type_of(*node), *function))
+            .collect::<Vec<_>>()
+            .into_iter(),
+        errors: Vec::new(),
+    };
+    let mut module = module.clone();
+    typer.visit_module_mut(&mut module);
+
+    if typer.errors.is_empty() {
+        Ok(Cow::Owned(module))
+    } else {
+        Err(typer.errors)
+    }
+}
+
+/// A type variable in the union-find forest of [`Inferrer`].
+type Node = usize;
+
+struct Inferrer<'m> {
+    signatures: HashMap<&'m str, &'m Signature>,
+    function: Option<&'m Function<'m>>,
+    levels: Vec<HashMap<&'m str, Node>>,
+    parents: Vec<Node>,
+    types: Vec<Option<Type>>,
+    // The variable of every untyped literal in the order they are visited, and the function it is in
+    literals: Vec<(Node, Option<&'m str>)>,
+}
+
+impl<'m> Visit<'m> for Inferrer<'m> {
+    fn visit_global(&mut self, global: &'m Global) {
+        if let Some(value) = &global.value {
+            let node = self.infer_value(value);
+            let ty = self.known(global.ty);
+            self.unify(node, ty);
+        }
+    }
+
+    fn visit_function(&mut self, function: &'m Function) {
+        self.function = Some(function);
+        self.visit_block(&function.body);
+        self.function = None;
+    }
+
+    fn visit_block(&mut self, block: &'m Block) {
+        self.levels.push(HashMap::new());
+        walk_block(self, block);
+        self.levels.pop();
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &'m IfStmt) {
+        self.infer_condition(&stmt.condition);
+        self.visit_block(&stmt.then_block);
+
+        match &stmt.if_else {
+            Some(IfElse::If(x)) => self.visit_if_stmt(x),
+            Some(IfElse::Else(x)) => self.visit_block(x),
+            None => {}
+        }
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &'m WhileStmt) {
+        self.infer_condition(&stmt.condition);
+        self.visit_block(&stmt.loop_block);
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &'m TryStmt) {
+        self.visit_block(&stmt.body);
+
+        match &stmt.handler {
+            Handler::Cleanup(x) => self.visit_block(x),
+            Handler::Catch { name, block } => {
+                let node = self.known(PtrType.into());
+                self.levels.push(HashMap::from([(name.as_ref(), node)]));
+                self.visit_block(block);
+                self.levels.pop();
+            }
+        }
+    }
+
+    fn visit_assert_stmt(&mut self, stmt: &'m AssertStmt) {
+        self.infer_condition(&stmt.condition);
+    }
+
+    fn visit_atomic_store_stmt(&mut self, stmt: &'m AtomicStoreStmt) {
+        self.infer_ptr(&stmt.ptr);
+        self.infer_value(&stmt.value);
+    }
+
+    fn visit_mem_copy_stmt(&mut self, stmt: &'m MemCopyStmt) {
+        self.infer_ptr(&stmt.dest);
+        self.infer_ptr(&stmt.src);
+        self.infer_known(&stmt.len, IntType::U64.into());
+    }
+
+    fn visit_mem_set_stmt(&mut self, stmt: &'m MemSetStmt) {
+        self.infer_ptr(&stmt.dest);
+        self.infer_known(&stmt.value, IntType::U8.into());
+        self.infer_known(&stmt.len, IntType::U64.into());
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &'m ReturnStmt) {
+        let outputs = self.function.map(|x| x.signature.output.as_slice()).unwrap_or_default();
+
+        // A single value may produce all of the outputs
+        let nodes = match stmt.values.as_slice() {
+            [value] => self.infer_values(value),
+            values => values.iter().map(|x| self.infer_value(x)).collect(),
+        };
+
+        for (node, output) in nodes.into_iter().zip(outputs) {
+            let ty = self.known(*output);
+            self.unify(node, ty);
+        }
+    }
+
+    // Values are inferred without visiting them, so calls are only visited as statements
+    fn visit_call(&mut self, call: &'m Call) {
+        self.infer_call(call);
+    }
+
+    fn visit_call_indirect(&mut self, call: &'m CallIndirect) {
+        self.infer_call_indirect(call);
+    }
+
+    fn visit_bind_stmt(&mut self, stmt: &'m BindStmt) {
+        let node = self.infer_value(&stmt.value);
+        self.levels.last_mut().unwrap().insert(&stmt.name, node);
+    }
+
+    fn visit_bind_tuple_stmt(&mut self, stmt: &'m BindTupleStmt) {
+        let nodes = self.infer_values(&stmt.value);
+        for (i, name) in stmt.names.iter().enumerate() {
+            let node = nodes.get(i).copied().unwrap_or_else(|| self.fresh());
+            self.levels.last_mut().unwrap().insert(name, node);
+        }
+    }
+
+    fn visit_assign_stmt(&mut self, stmt: &'m AssignStmt) {
+        let node = self.infer_value(&stmt.value);
+        if let Some(var) = self.var(&stmt.var.name) {
+            self.unify(var, node);
+        }
+    }
+}
+
+impl<'m> Inferrer<'m> {
+    fn infer_condition(&mut self, condition: &'m Value) {
+        self.infer_known(condition, BoolType.into());
+    }
+
+    fn infer_ptr(&mut self, ptr: &'m Value) {
+        self.infer_known(ptr, PtrType.into());
+    }
+
+    fn infer_known(&mut self, value: &'m Value, ty: Type) {
+        let node = self.infer_value(value);
+        let ty = self.known(ty);
+        self.unify(node, ty);
+    }
+
+    fn infer_value(&mut self, value: &'m Value) -> Node {
+        match value {
+            Value::Context(ContextValue::Arg(x)) => {
+                let input = self.function.and_then(|f| f.signature.input.get(x.param_index as usize));
+                match input {
+                    Some(ty) => self.known(*ty),
+                    None => self.fresh(),
+                }
+            }
+            Value::Context(ContextValue::Var(x)) => self.var(&x.name).unwrap_or_else(|| self.fresh()),
+            Value::Expr(x) => match x.as_ref() {
+                Expr::Bin(x) => {
+                    let lhs = self.infer_value(&x.lhs);
+                    let rhs = self.infer_value(&x.rhs);
+                    self.unify(lhs, rhs);
+
+                    match x.op {
+                        BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => {
+                            self.known(BoolType.into())
+                        }
+                        _ => lhs,
+                    }
+                }
+                Expr::Una(x) => {
+                    let operand = self.infer_value(&x.operand);
+                    match x.op {
+                        UnaOp::IsNan => self.known(BoolType.into()),
+                        _ => operand,
+                    }
+                }
+                Expr::Checked(x) => {
+                    let lhs = self.infer_value(&x.lhs);
+                    let rhs = self.infer_value(&x.rhs);
+                    self.unify(lhs, rhs);
+                    lhs
+                }
+                Expr::FloatCmp(x) => {
+                    let lhs = self.infer_value(&x.lhs);
+                    let rhs = self.infer_value(&x.rhs);
+                    self.unify(lhs, rhs);
+                    self.known(BoolType.into())
+                }
+                // The type of a lane is not the type of its vector, so it is only inferred among the lanes
+                Expr::Splat(x) => {
+                    self.infer_value(&x.value);
+                    self.fresh()
+                }
+                Expr::Vector(x) => {
+                    let node = self.fresh();
+                    for elem in &x.elems {
+                        let elem = self.infer_value(elem);
+                        self.unify(node, elem);
+                    }
+                    self.fresh()
+                }
+                Expr::ExtractElement(ExtractElement { vector, .. }) | Expr::Reduce(Reduce { vector, .. }) => {
+                    self.infer_value(vector);
+                    self.fresh()
+                }
+                Expr::InsertElement(x) => {
+                    self.infer_value(&x.value);
+                    self.infer_value(&x.vector)
+                }
+                Expr::Shuffle(x) => {
+                    let lhs = self.infer_value(&x.lhs);
+                    let rhs = self.infer_value(&x.rhs);
+                    self.unify(lhs, rhs);
+                    lhs
+                }
+            },
+            Value::Lit(x) => match x.ty() {
+                Some(ty) => self.known(ty),
+                None => {
+                    let node = self.fresh();
+                    self.literals.push((node, self.function.map(|x| x.name.as_ref())));
+                    node
+                }
+            },
+            Value::Call(x) => match self.infer_call(x).as_slice() {
+                [output] => *output,
+                _ => self.fresh(),
+            },
+            Value::FuncAddr(_) => self.known(PtrType.into()),
+            Value::CallIndirect(x) => match self.infer_call_indirect(x).as_slice() {
+                [output] => *output,
+                _ => self.fresh(),
+            },
+            Value::Intrinsic(x) => {
+                let node = self.fresh();
+                for arg in &x.args {
+                    let arg = self.infer_value(arg);
+                    self.unify(node, arg);
+                }
+                node
+            }
+            Value::AtomicLoad(x) => {
+                self.infer_ptr(&x.ptr);
+                self.known(x.ty)
+            }
+            Value::AtomicRmw(x) => {
+                self.infer_ptr(&x.ptr);
+                self.infer_value(&x.value)
+            }
+            Value::CmpXchg(x) => {
+                self.infer_ptr(&x.ptr);
+                let expected = self.infer_value(&x.expected);
+                let replacement = self.infer_value(&x.replacement);
+                self.unify(expected, replacement);
+                expected
+            }
+            Value::Spanned(x) => self.infer_value(&x.node),
+        }
+    }
+
+    /// The variables of the values produced by `value`, which is more than one for a call with multiple outputs or a
+    /// checked operation that flags overflow.
+    fn infer_values(&mut self, value: &'m Value) -> Vec<Node> {
+        match value {
+            Value::Expr(x) if matches!(x.as_ref(), Expr::Checked(Checked { on_overflow: OnOverflow::Flag, .. })) => {
+                let result = self.infer_value(value);
+                vec![result, self.known(BoolType.into())]
+            }
+            Value::Call(x) => self.infer_call(x),
+            Value::CallIndirect(x) => self.infer_call_indirect(x),
+            Value::Spanned(x) => self.infer_values(&x.node),
+            _ => vec![self.infer_value(value)],
+        }
+    }
+
+    fn infer_call(&mut self, call: &'m Call) -> Vec<Node> {
+        let signature = self.signatures.get(call.function_name.as_ref()).copied();
+        self.infer_args(&call.args, signature)
+    }
+
+    fn infer_call_indirect(&mut self, call: &'m CallIndirect) -> Vec<Node> {
+        self.infer_ptr(&call.callee);
+        self.infer_args(&call.args, Some(&call.signature))
+    }
+
+    /// Unifies each argument with its parameter in `signature`, if it is known, producing the variables of the outputs.
+    fn infer_args(&mut self, args: &'m [Value], signature: Option<&Signature>) -> Vec<Node> {
+        let inputs = signature.map(|x| x.input.as_slice()).unwrap_or_default();
+
+        for (i, arg) in args.iter().enumerate() {
+            let node = self.infer_value(arg);
+            if let Some(input) = inputs.get(i) {
+                let ty = self.known(*input);
+                self.unify(node, ty);
+            }
+        }
+
+        let outputs = signature.map(|x| x.output.as_slice()).unwrap_or_default();
+        outputs.iter().map(|x| self.known(*x)).collect()
+    }
+
+    fn var(&self, name: &str) -> Option<Node> {
+        self.levels.iter().rev().find_map(|level| level.get(name)).copied()
+    }
+
+    fn fresh(&mut self) -> Node {
+        self.parents.push(self.parents.len());
+        self.types.push(None);
+        self.parents.len() - 1
+    }
+
+    fn known(&mut self, ty: Type) -> Node {
+        let node = self.fresh();
+        self.types[node] = Some(ty);
+        node
+    }
+
+    fn root(&self, mut node: Node) -> Node {
+        while self.parents[node] != node {
+            node = self.parents[node];
+        }
+        node
+    }
+
+    fn unify(&mut self, a: Node, b: Node) {
+        let (a, b) = (self.root(a), self.root(b));
+        if a == b {
+            return;
+        }
+
+        // Conflicting types are left for the backend to report, so the first one is kept
+        self.parents[b] = a;
+        if self.types[a].is_none() {
+            self.types[a] = self.types[b];
+        }
+    }
+
+    fn type_of(&self, node: Node) -> Option<Type> {
+        self.types[self.root(node)]
+    }
+}
+
+/// Replaces untyped literals with typed ones, visiting them in the same order as [`Inferrer`].
+struct Typer<'m, I: Iterator<Item = (Option<Type>, Option<&'m str>)>> {
+    literals: I,
+    errors: Vec<ResolveError>,
+}
+
+impl<'m, I: Iterator<Item = (Option<Type>, Option<&'m str>)>> VisitMut for Typer<'m, I> {
+    fn visit_lit_mut(&mut self, lit: &mut Lit) {
+        if lit.ty().is_some() {
+            return;
+        }
+
+        let (ty, function) = self.literals.next().unwrap();
+        match ty.and_then(|ty| type_literal(*lit, ty)) {
+            Some(typed) => *lit = typed,
+            None => self.errors.push(ResolveError::UntypedLiteral {
+                function: function.map(str::to_string),
+                lit: *lit,
+                ty,
+            }),
+        }
+    }
+}
+
+/// The literal `lit` as a value of type `ty`, if it can be represented by it.
+fn type_literal(lit: Lit, ty: Type) -> Option<Lit> {
+    let Type::Scalar(ScalarType::Num(ty)) = ty else {
+        return None;
+    };
+
+    let lit = match (lit, ty) {
+        (Lit::Num(Num::Int(Int::Untyped(x))), NumType::Int(ty)) => type_int(x, ty)?.into(),
+        (Lit::Num(Num::Int(Int::Untyped(x))), NumType::Float(ty)) => type_float(x as f64, ty)?.into(),
+        (Lit::Num(Num::Float(Float::Untyped(x))), NumType::Float(ty)) => type_float(x, ty)?.into(),
+        _ => return None,
+    };
+
+    Some(lit)
+}
+
+fn type_int(value: i128, ty: IntType) -> Option<Int> {
+    let int = match (ty.width, ty.signed) {
+        (IntWidth::I8, true) => SignedInt::B8(value.try_into().ok()?).into(),
+        (IntWidth::I16, true) => SignedInt::B16(value.try_into().ok()?).into(),
+        (IntWidth::I32, true) => SignedInt::B32(value.try_into().ok()?).into(),
+        (IntWidth::I64, true) => SignedInt::B64(value.try_into().ok()?).into(),
+        (IntWidth::I128, true) => SignedInt::B128(value).into(),
+        (IntWidth::I8, false) => UnsignedInt::U8(value.try_into().ok()?).into(),
+        (IntWidth::I16, false) => UnsignedInt::U16(value.try_into().ok()?).into(),
+        (IntWidth::I32, false) => UnsignedInt::U32(value.try_into().ok()?).into(),
+        (IntWidth::I64, false) => UnsignedInt::U64(value.try_into().ok()?).into(),
+        (IntWidth::I128, false) => UnsignedInt::U128(value.try_into().ok()?).into(),
+    };
+
+    Some(int)
+}
+
+fn type_float(value: f64, ty: FloatType) -> Option<Float> {
+    match ty {
+        FloatType::F32 => Some(Float::F32(value as f32)),
+        FloatType::F64 => Some(Float::F64(value)),
+        // Half and quadruple precision literals cannot be represented yet
+        FloatType::F16 | FloatType::F128 => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::type_literals;
+    use crate::block::stmt::{BindStmt, ReturnStmt, Stmt};
+    use crate::function::Function;
+    use crate::module::Module;
+    use crate::resolve::ResolveError;
+    use crate::ty::util as ty;
+    use crate::val::util::*;
+    use crate::val::{Int, Lit, Value};
+
+    /// The values that the statements of `function` bind or return once its literals are typed.
+    fn typed_values(function: Function<'static>) -> Result<Vec<Value<'static>>, Vec<ResolveError>> {
+        let mut module = Module::new("infer");
+        module.add_function(function);
+
+        let typed = type_literals(&module)?.into_owned();
+        let values = typed.functions[0].body.stmts
+            .iter()
+            .flat_map(|stmt| match stmt {
+                Stmt::Bind(BindStmt { value, .. }) => vec![value.clone()],
+                Stmt::Return(ReturnStmt { values }) => values.clone(),
+                _ => vec![],
+            })
+            .collect();
+        Ok(values)
+    }
+
+    #[test]
+    fn literals_take_the_type_of_what_they_meet() {
+        let function = Function::new("f")
+            .add_input(ty::s16())
+            .add_output(ty::f64())
+            .build_block(|scope| {
+                scope.bind("sum", add(arg(0), lit_int(-3)));
+                scope.bind("late", lit_int(7));
+                scope.bind("product", mul(var("late"), lit(2u8)));
+                scope.ret([lit_int(1)]);
+            });
+
+        assert_eq!(typed_values(function), Ok(vec![
+            add(arg(0), lit(-3i16)),
+            lit(7u8),
+            mul(var("late"), lit(2u8)),
+            lit(1.0f64),
+        ]));
+    }
+
+    #[test]
+    fn literals_without_a_fitting_type_are_reported() {
+        let function = Function::new("f")
+            .add_input(ty::u8())
+            .build_block(|scope| {
+                scope.bind("unknown", lit_int(1));
+                scope.bind("large", add(arg(0), lit_int(256)));
+                scope.bind("fraction", add(arg(0), lit_float(0.5)));
+                scope.ret([]);
+            });
+
+        let untyped = |lit, ty| ResolveError::UntypedLiteral { function: Some("f".to_string()), lit, ty };
+        assert_eq!(typed_values(function), Err(vec![
+            untyped(Lit::from(Int::Untyped(1)), None),
+            untyped(Lit::from(Int::Untyped(256)), Some(ty::u8())),
+            untyped(lit_float(0.5), Some(ty::u8())),
+        ]));
+    }
+}
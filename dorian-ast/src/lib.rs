@@ -1,8 +1,15 @@
 pub mod backend;
 pub mod block;
+pub mod fold;
 pub mod function;
 pub mod global;
+pub mod infer;
 pub mod module;
+pub mod opt;
+pub mod resolve;
+pub mod span;
 pub mod structure;
 pub mod ty;
 pub mod val;
+pub mod visit;
+pub mod visit_mut;
@@ -0,0 +1,626 @@
+//! Constant folding and algebraic simplification of the AST.
+//!
+//! Folding follows the semantics every backend gives an operation, so an optimized module computes the same results as
+//! the original on all of them. Operations whose result is poison or a trap, such as an overflowing `no_wrap` addition,
+//! a division by zero or a shift by at least the width of the integer, are left to the backend.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::{Add, Div, Mul, Sub};
+use crate::block::Block;
+use crate::block::stmt::{BindStmt, BindTupleStmt, Handler, TryStmt};
+use crate::function::Function;
+use crate::module::Module;
+use crate::ty::{BoolType, IntType, IntWidth, NumType, PtrType, ScalarType, Type, VectorType};
+use crate::val::{
+    Bin, BinOp, Checked, ContextValue, Expr, ExtractElement, Float, FloatCmp, FloatCmpOp, Int, Lit, Num, OnOverflow,
+    Reduce, SignedInt, Una, UnaOp, UnsignedInt, Value,
+};
+use crate::visit_mut::{walk_bind_stmt_mut, walk_bind_tuple_stmt_mut, walk_block_mut, walk_value_mut, VisitMut};
+
+/// Folds and simplifies every value in `module`.
+pub fn optimize_module(module: &mut Module) {
+    let outputs = module.functions
+        .iter()
+        .map(|x| (x.name.to_string(), x.signature.output.clone()))
+        .collect::<HashMap<_, _>>();
+
+    for function in &mut module.functions {
+        optimize_function(function, &outputs);
+    }
+}
+
+fn optimize_function(function: &mut Function, outputs: &HashMap<String, Vec<Type>>) {
+    let Function { signature, body, .. } = function;
+
+    let mut folder = Folder {
+        inputs: &signature.input,
+        outputs,
+        levels: Vec::new(),
+    };
+    folder.visit_block_mut(body);
+}
+
+struct Folder<'f> {
+    inputs: &'f [Type],
+    outputs: &'f HashMap<String, Vec<Type>>,
+    // The type of every variable in scope, if it could be inferred
+    levels: Vec<HashMap<String, Option<Type>>>,
+}
+
+impl VisitMut for Folder<'_> {
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        self.levels.push(HashMap::new());
+        walk_block_mut(self, block);
+        self.levels.pop();
+    }
+
+    fn visit_bind_stmt_mut(&mut self, stmt: &mut BindStmt) {
+        walk_bind_stmt_mut(self, stmt);
+        let ty = self.type_of(&stmt.value);
+        self.levels.last_mut().unwrap().insert(stmt.name.to_string(), ty);
+    }
+
+    fn visit_bind_tuple_stmt_mut(&mut self, stmt: &mut BindTupleStmt) {
+        walk_bind_tuple_stmt_mut(self, stmt);
+        let types = self.types_of(&stmt.value);
+        for (i, name) in stmt.names.iter().enumerate() {
+            let ty = types.get(i).copied().flatten();
+            self.levels.last_mut().unwrap().insert(name.to_string(), ty);
+        }
+    }
+
+    fn visit_try_stmt_mut(&mut self, stmt: &mut TryStmt) {
+        self.visit_block_mut(&mut stmt.body);
+
+        match &mut stmt.handler {
+            Handler::Cleanup(x) => self.visit_block_mut(x),
+            Handler::Catch { name, block } => {
+                self.levels.push(HashMap::from([(name.to_string(), Some(PtrType.into()))]));
+                self.visit_block_mut(block);
+                self.levels.pop();
+            }
+        }
+    }
+
+    fn visit_value_mut(&mut self, value: &mut Value) {
+        // Operands are folded first, so simplification only has to look one level deep
+        walk_value_mut(self, value);
+
+        let simplified = match value {
+            Value::Expr(x) => match x.as_ref() {
+                Expr::Bin(x) => self.simplify_bin(x),
+                Expr::Una(x) => simplify_una(x),
+                Expr::FloatCmp(x) => simplify_float_cmp(x),
+                Expr::Checked(_) | Expr::Splat(_) | Expr::Vector(_) | Expr::ExtractElement(_)
+                | Expr::InsertElement(_) | Expr::Shuffle(_) | Expr::Reduce(_) => None,
+            },
+            _ => None,
+        };
+
+        if let Some(simplified) = simplified {
+            *value = simplified;
+        }
+    }
+}
+
+impl Folder<'_> {
+    fn simplify_bin<'s>(&self, bin: &Bin<'s>) -> Option<Value<'s>> {
+        let (lhs, rhs) = (lit_of(&bin.lhs), lit_of(&bin.rhs));
+        if let (Some(lhs), Some(rhs)) = (lhs, rhs) {
+            return fold_bin(bin.op, bin.no_wrap, lhs, rhs).map(Value::Lit);
+        }
+
+        let is = |lit: Option<Lit>, identity: fn(Lit) -> bool| lit.is_some_and(identity);
+        match bin.op {
+            // x + 0, 0 + x
+            BinOp::Add if is(rhs, is_additive_identity) => return Some(bin.lhs.clone()),
+            BinOp::Add if is(lhs, is_additive_identity) => return Some(bin.rhs.clone()),
+            // x - 0
+            BinOp::Sub if is(rhs, is_subtractive_identity) => return Some(bin.lhs.clone()),
+            // x * 1, 1 * x, x / 1
+            BinOp::Mul if is(rhs, is_one) => return Some(bin.lhs.clone()),
+            BinOp::Mul if is(lhs, is_one) => return Some(bin.rhs.clone()),
+            BinOp::Div if is(rhs, is_one) => return Some(bin.lhs.clone()),
+            // x * 0, 0 * x
+            BinOp::Mul if is(rhs, is_int_zero) && is_pure(&bin.lhs) => return Some(bin.rhs.clone()),
+            BinOp::Mul if is(lhs, is_int_zero) && is_pure(&bin.rhs) => return Some(bin.lhs.clone()),
+            // x % 1
+            BinOp::Rem if is(rhs, is_int_one) && is_pure(&bin.lhs) => return rhs.and_then(|x| x.ty()).and_then(zero),
+            // x | 0, x ^ 0, x & -1 and their mirrors
+            BinOp::Or | BinOp::BitOr | BinOp::BitXor if is(rhs, is_zero_bits) => return Some(bin.lhs.clone()),
+            BinOp::Or | BinOp::BitOr | BinOp::BitXor if is(lhs, is_zero_bits) => return Some(bin.rhs.clone()),
+            BinOp::And | BinOp::BitAnd if is(rhs, is_one_bits) => return Some(bin.lhs.clone()),
+            BinOp::And | BinOp::BitAnd if is(lhs, is_one_bits) => return Some(bin.rhs.clone()),
+            // x & 0, x | -1 and their mirrors
+            BinOp::And | BinOp::BitAnd if is(rhs, is_zero_bits) && is_pure(&bin.lhs) => return Some(bin.rhs.clone()),
+            BinOp::And | BinOp::BitAnd if is(lhs, is_zero_bits) && is_pure(&bin.rhs) => return Some(bin.lhs.clone()),
+            BinOp::Or | BinOp::BitOr if is(rhs, is_one_bits) && is_pure(&bin.lhs) => return Some(bin.rhs.clone()),
+            BinOp::Or | BinOp::BitOr if is(lhs, is_one_bits) && is_pure(&bin.rhs) => return Some(bin.lhs.clone()),
+            // x << 0, x >> 0
+            BinOp::Shl | BinOp::Shr if is(rhs, is_int_zero) => return Some(bin.lhs.clone()),
+            _ => {}
+        }
+
+        // Identical operands are only the same value if evaluating them has no effects
+        if bin.lhs != bin.rhs || !is_pure(&bin.lhs) {
+            return None;
+        }
+
+        // Floats are excluded, as NaN is not equal to itself and `inf - inf` is NaN
+        let ty = self.type_of(&bin.lhs).filter(|x| is_int_type(x) || *x == Type::from(BoolType))?;
+        match bin.op {
+            BinOp::And | BinOp::Or | BinOp::BitAnd | BinOp::BitOr => Some(bin.lhs.clone()),
+            BinOp::Sub | BinOp::BitXor => zero(ty),
+            BinOp::Eq | BinOp::Le | BinOp::Ge => Some(Value::Lit(Lit::Bool(true))),
+            BinOp::Ne | BinOp::Lt | BinOp::Gt => Some(Value::Lit(Lit::Bool(false))),
+            _ => None,
+        }
+    }
+
+    fn type_of(&self, value: &Value) -> Option<Type> {
+        match value {
+            Value::Context(ContextValue::Arg(x)) => self.inputs.get(x.param_index as usize).copied(),
+            Value::Context(ContextValue::Var(x)) => self.levels
+                .iter()
+                .rev()
+                .find_map(|level| level.get(x.name.as_ref()))
+                .copied()
+                .flatten(),
+            Value::Expr(x) => match x.as_ref() {
+                Expr::Bin(x) if is_comparison(x.op) => Some(BoolType.into()),
+                Expr::Bin(x) => self.type_of(&x.lhs),
+                Expr::Una(x) if x.op == UnaOp::IsNan => Some(BoolType.into()),
+                Expr::Una(x) => self.type_of(&x.operand),
+                Expr::Checked(x) if x.on_overflow == OnOverflow::Trap => self.type_of(&x.lhs),
+                Expr::Checked(_) => None,
+                Expr::FloatCmp(_) => Some(BoolType.into()),
+                Expr::Splat(x) => vector_of(self.type_of(&x.value)?, x.len),
+                Expr::Vector(x) => vector_of(self.type_of(x.elems.first()?)?, x.elems.len() as u32),
+                Expr::ExtractElement(ExtractElement { vector, .. }) | Expr::Reduce(Reduce { vector, .. }) => {
+                    match self.type_of(vector)? {
+                        Type::Vector(x) => Some(x.elem.into()),
+                        Type::Scalar(_) => None,
+                    }
+                }
+                Expr::InsertElement(x) => self.type_of(&x.vector),
+                Expr::Shuffle(x) => self.type_of(&x.lhs),
+            },
+            Value::Lit(x) => x.ty(),
+            Value::Call(x) => match self.outputs.get(x.function_name.as_ref())?.as_slice() {
+                [output] => Some(*output),
+                _ => None,
+            },
+            Value::FuncAddr(_) => Some(PtrType.into()),
+            Value::CallIndirect(x) => match x.signature.output.as_slice() {
+                [output] => Some(*output),
+                _ => None,
+            },
+            Value::Intrinsic(x) => x.args.iter().find_map(|x| self.type_of(x)),
+            Value::AtomicLoad(x) => Some(x.ty),
+            Value::AtomicRmw(x) => self.type_of(&x.value),
+            Value::CmpXchg(x) => self.type_of(&x.expected).or_else(|| self.type_of(&x.replacement)),
+            Value::Spanned(x) => self.type_of(&x.node),
+        }
+    }
+
+    /// The types of the values produced by `value`, which is more than one for a call with multiple outputs or a
+    /// checked operation that flags overflow.
+    fn types_of(&self, value: &Value) -> Vec<Option<Type>> {
+        match value {
+            Value::Expr(x) => match x.as_ref() {
+                Expr::Checked(Checked { lhs, on_overflow: OnOverflow::Flag, .. }) => {
+                    vec![self.type_of(lhs), Some(BoolType.into())]
+                }
+                _ => vec![self.type_of(value)],
+            },
+            Value::Call(x) => match self.outputs.get(x.function_name.as_ref()) {
+                Some(outputs) => outputs.iter().copied().map(Some).collect(),
+                None => vec![],
+            },
+            Value::CallIndirect(x) => x.signature.output.iter().copied().map(Some).collect(),
+            Value::Spanned(x) => self.types_of(&x.node),
+            _ => vec![self.type_of(value)],
+        }
+    }
+}
+
+fn simplify_una<'s>(una: &Una<'s>) -> Option<Value<'s>> {
+    let operand = lit_of(&una.operand)?;
+
+    let lit = match (una.op, operand) {
+        (op, Lit::Num(Num::Int(x))) => fold_int_una(op, una.no_wrap, x)?,
+        (UnaOp::Neg, Lit::Num(Num::Float(Float::F32(x)))) => Float::F32(-x).into(),
+        (UnaOp::Neg, Lit::Num(Num::Float(Float::F64(x)))) => Float::F64(-x).into(),
+        (UnaOp::IsNan, Lit::Num(Num::Float(Float::F32(x)))) => Lit::Bool(x.is_nan()),
+        (UnaOp::IsNan, Lit::Num(Num::Float(Float::F64(x)))) => Lit::Bool(x.is_nan()),
+        (UnaOp::Not, Lit::Bool(x)) => Lit::Bool(!x),
+        _ => return None,
+    };
+
+    Some(Value::Lit(lit))
+}
+
+fn simplify_float_cmp<'s>(float_cmp: &FloatCmp<'s>) -> Option<Value<'s>> {
+    let order = match (lit_of(&float_cmp.lhs)?, lit_of(&float_cmp.rhs)?) {
+        (Lit::Num(Num::Float(Float::F32(lhs))), Lit::Num(Num::Float(Float::F32(rhs)))) => lhs.partial_cmp(&rhs),
+        (Lit::Num(Num::Float(Float::F64(lhs))), Lit::Num(Num::Float(Float::F64(rhs)))) => lhs.partial_cmp(&rhs),
+        _ => return None,
+    };
+
+    let Some(order) = order else {
+        // Unordered operands make every ordered comparison false and every unordered one true
+        let ordered = matches!(
+            float_cmp.op,
+            FloatCmpOp::Ordered | FloatCmpOp::OrderedEq | FloatCmpOp::OrderedNe | FloatCmpOp::OrderedLt
+                | FloatCmpOp::OrderedGt | FloatCmpOp::OrderedLe | FloatCmpOp::OrderedGe
+        );
+        return Some(Value::Lit(Lit::Bool(!ordered)));
+    };
+
+    let result = match float_cmp.op {
+        FloatCmpOp::Ordered => true,
+        FloatCmpOp::Unordered => false,
+        FloatCmpOp::OrderedEq | FloatCmpOp::UnorderedEq => order == Ordering::Equal,
+        FloatCmpOp::OrderedNe | FloatCmpOp::UnorderedNe => order != Ordering::Equal,
+        FloatCmpOp::OrderedLt | FloatCmpOp::UnorderedLt => order == Ordering::Less,
+        FloatCmpOp::OrderedGt | FloatCmpOp::UnorderedGt => order == Ordering::Greater,
+        FloatCmpOp::OrderedLe | FloatCmpOp::UnorderedLe => order != Ordering::Greater,
+        FloatCmpOp::OrderedGe | FloatCmpOp::UnorderedGe => order != Ordering::Less,
+    };
+
+    Some(Value::Lit(Lit::Bool(result)))
+}
+
+fn fold_bin(op: BinOp, no_wrap: bool, lhs: Lit, rhs: Lit) -> Option<Lit> {
+    match (lhs, rhs) {
+        (Lit::Num(Num::Int(lhs)), Lit::Num(Num::Int(rhs))) => fold_int_bin(op, no_wrap, lhs, rhs),
+        (Lit::Num(Num::Float(Float::F32(lhs))), Lit::Num(Num::Float(Float::F32(rhs)))) => {
+            fold_float_bin(op, lhs, rhs, Float::F32)
+        }
+        (Lit::Num(Num::Float(Float::F64(lhs))), Lit::Num(Num::Float(Float::F64(rhs)))) => {
+            fold_float_bin(op, lhs, rhs, Float::F64)
+        }
+        (Lit::Bool(lhs), Lit::Bool(rhs)) => fold_bool_bin(op, lhs, rhs),
+        _ => None,
+    }
+}
+
+fn fold_int_bin(op: BinOp, no_wrap: bool, lhs: Int, rhs: Int) -> Option<Lit> {
+    let (a, ty) = int_bits(lhs);
+    let (b, rhs_ty) = int_bits(rhs);
+    if ty != rhs_ty {
+        return None;
+    }
+
+    let width = width_bits(ty.width);
+    let mask = u128::MAX >> (128 - width);
+    let (sa, sb) = (sign_extend(a, width), sign_extend(b, width));
+    let int = |bits: u128| Some(Lit::from(int_from_bits(bits, ty)));
+
+    // The exact result of an operation decides whether a `no_wrap` operation overflows, which is poison in LLVM
+    let wrapping = |signed: Option<i128>, unsigned: Option<u128>, wrapped: u128| {
+        let overflows = if ty.signed {
+            signed.is_none_or(|x| sign_extend(x as u128 & mask, width) != x)
+        } else {
+            unsigned.is_none_or(|x| x > mask)
+        };
+
+        if no_wrap && overflows {
+            None
+        } else {
+            int(wrapped)
+        }
+    };
+
+    let order = if ty.signed { sa.cmp(&sb) } else { a.cmp(&b) };
+    // Division by zero and `MIN / -1` trap in Cranelift and are undefined in LLVM
+    let divides = b != 0 && !(ty.signed && sb == -1 && sa == sign_extend(1 << (width - 1), width));
+    // Shifts by at least the width are masked in Cranelift but poison in LLVM
+    let shift = (b < width as u128 && !(ty.signed && sb < 0)).then_some(b as u32);
+
+    match op {
+        BinOp::Add => wrapping(sa.checked_add(sb), a.checked_add(b), a.wrapping_add(b)),
+        BinOp::Sub => wrapping(sa.checked_sub(sb), a.checked_sub(b), a.wrapping_sub(b)),
+        BinOp::Mul => wrapping(sa.checked_mul(sb), a.checked_mul(b), a.wrapping_mul(b)),
+        BinOp::Div if !divides => None,
+        BinOp::Div if ty.signed => int((sa / sb) as u128),
+        BinOp::Div => int(a / b),
+        BinOp::Rem if !divides => None,
+        BinOp::Rem if ty.signed => int((sa % sb) as u128),
+        BinOp::Rem => int(a % b),
+        BinOp::And | BinOp::BitAnd => int(a & b),
+        BinOp::Or | BinOp::BitOr => int(a | b),
+        BinOp::BitXor => int(a ^ b),
+        BinOp::Shl => int(a << shift?),
+        BinOp::Shr if ty.signed => int((sa >> shift?) as u128),
+        BinOp::Shr => int(a >> shift?),
+        BinOp::Eq => Some(Lit::Bool(order == Ordering::Equal)),
+        BinOp::Ne => Some(Lit::Bool(order != Ordering::Equal)),
+        BinOp::Lt => Some(Lit::Bool(order == Ordering::Less)),
+        BinOp::Gt => Some(Lit::Bool(order == Ordering::Greater)),
+        BinOp::Le => Some(Lit::Bool(order != Ordering::Greater)),
+        BinOp::Ge => Some(Lit::Bool(order != Ordering::Less)),
+    }
+}
+
+fn fold_int_una(op: UnaOp, no_wrap: bool, operand: Int) -> Option<Lit> {
+    let (a, ty) = int_bits(operand);
+    let width = width_bits(ty.width);
+
+    let bits = match op {
+        UnaOp::Neg => {
+            // Only zero can be negated without wrapping if unsigned, and every value but `MIN` if signed
+            let overflows = if ty.signed { a == 1 << (width - 1) } else { a != 0 };
+            if no_wrap && overflows {
+                return None;
+            }
+
+            a.wrapping_neg()
+        }
+        UnaOp::Not => !a,
+        UnaOp::IsNan => return None,
+    };
+
+    Some(int_from_bits(bits, ty).into())
+}
+
+fn fold_float_bin<T>(op: BinOp, lhs: T, rhs: T, float: fn(T) -> Float) -> Option<Lit>
+where
+    T: Copy + PartialOrd + Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Div<Output = T>,
+{
+    let order = lhs.partial_cmp(&rhs);
+
+    let lit = match op {
+        BinOp::Add => float(lhs + rhs).into(),
+        BinOp::Sub => float(lhs - rhs).into(),
+        BinOp::Mul => float(lhs * rhs).into(),
+        BinOp::Div => float(lhs / rhs).into(),
+        BinOp::Eq => Lit::Bool(order == Some(Ordering::Equal)),
+        BinOp::Ne => Lit::Bool(order != Some(Ordering::Equal)),
+        BinOp::Lt => Lit::Bool(order == Some(Ordering::Less)),
+        BinOp::Gt => Lit::Bool(order == Some(Ordering::Greater)),
+        BinOp::Le => Lit::Bool(matches!(order, Some(Ordering::Less | Ordering::Equal))),
+        BinOp::Ge => Lit::Bool(matches!(order, Some(Ordering::Greater | Ordering::Equal))),
+        _ => return None,
+    };
+
+    Some(lit)
+}
+
+fn fold_bool_bin(op: BinOp, lhs: bool, rhs: bool) -> Option<Lit> {
+    let value = match op {
+        BinOp::And | BinOp::BitAnd => lhs & rhs,
+        BinOp::Or | BinOp::BitOr => lhs | rhs,
+        BinOp::BitXor | BinOp::Ne => lhs ^ rhs,
+        BinOp::Eq => lhs == rhs,
+        _ => return None,
+    };
+
+    Some(Lit::Bool(value))
+}
+
+// Untyped literals are folded once their type is inferred
+fn lit_of(value: &Value) -> Option<Lit> {
+    match value {
+        Value::Lit(x) => x.ty().map(|_| *x),
+        Value::Spanned(x) => lit_of(&x.node),
+        _ => None,
+    }
+}
+
+fn zero<'s>(ty: Type) -> Option<Value<'s>> {
+    match ty {
+        Type::Scalar(ScalarType::Num(NumType::Int(x))) => Some(int_from_bits(0, x).into()),
+        Type::Scalar(ScalarType::Bool(_)) => Some(Value::Lit(Lit::Bool(false))),
+        _ => None,
+    }
+}
+
+fn vector_of(elem: Type, len: u32) -> Option<Type> {
+    match elem {
+        Type::Scalar(elem) => Some(VectorType { elem, len }.into()),
+        Type::Vector(_) => None,
+    }
+}
+
+fn is_int_type(ty: &Type) -> bool {
+    matches!(ty, Type::Scalar(ScalarType::Num(NumType::Int(_))))
+}
+
+fn is_comparison(op: BinOp) -> bool {
+    matches!(op, BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge)
+}
+
+/// Whether evaluating `value` can be skipped or repeated without changing the behavior of the program.
+fn is_pure(value: &Value) -> bool {
+    match value {
+        Value::Context(_) | Value::Lit(_) | Value::FuncAddr(_) => true,
+        Value::Expr(x) => match x.as_ref() {
+            // Division may trap
+            Expr::Bin(x) => !matches!(x.op, BinOp::Div | BinOp::Rem) && is_pure(&x.lhs) && is_pure(&x.rhs),
+            Expr::Una(x) => is_pure(&x.operand),
+            // Overflow may trap
+            Expr::Checked(x) => x.on_overflow == OnOverflow::Flag && is_pure(&x.lhs) && is_pure(&x.rhs),
+            Expr::FloatCmp(x) => is_pure(&x.lhs) && is_pure(&x.rhs),
+            Expr::Splat(x) => is_pure(&x.value),
+            Expr::Vector(x) => x.elems.iter().all(is_pure),
+            Expr::ExtractElement(x) => is_pure(&x.vector),
+            Expr::InsertElement(x) => is_pure(&x.vector) && is_pure(&x.value),
+            Expr::Shuffle(x) => is_pure(&x.lhs) && is_pure(&x.rhs),
+            Expr::Reduce(x) => is_pure(&x.vector),
+        },
+        Value::Call(_) | Value::CallIndirect(_) => false,
+        Value::AtomicLoad(_) | Value::AtomicRmw(_) | Value::CmpXchg(_) => false,
+        Value::Intrinsic(x) => x.args.iter().all(is_pure),
+        Value::Spanned(x) => is_pure(&x.node),
+    }
+}
+
+fn is_int_zero(lit: Lit) -> bool {
+    matches!(lit, Lit::Num(Num::Int(x)) if int_bits(x).0 == 0)
+}
+
+fn is_int_one(lit: Lit) -> bool {
+    matches!(lit, Lit::Num(Num::Int(x)) if int_bits(x).0 == 1)
+}
+
+fn is_one(lit: Lit) -> bool {
+    match lit {
+        Lit::Num(Num::Float(Float::F32(x))) => x == 1.0,
+        Lit::Num(Num::Float(Float::F64(x))) => x == 1.0,
+        _ => is_int_one(lit),
+    }
+}
+
+// `-0.0 + x` is `x` for every float, but `0.0 + -0.0` is `0.0`
+fn is_additive_identity(lit: Lit) -> bool {
+    match lit {
+        Lit::Num(Num::Float(Float::F32(x))) => x == 0.0 && x.is_sign_negative(),
+        Lit::Num(Num::Float(Float::F64(x))) => x == 0.0 && x.is_sign_negative(),
+        _ => is_int_zero(lit),
+    }
+}
+
+fn is_subtractive_identity(lit: Lit) -> bool {
+    match lit {
+        Lit::Num(Num::Float(Float::F32(x))) => x == 0.0 && x.is_sign_positive(),
+        Lit::Num(Num::Float(Float::F64(x))) => x == 0.0 && x.is_sign_positive(),
+        _ => is_int_zero(lit),
+    }
+}
+
+fn is_zero_bits(lit: Lit) -> bool {
+    lit == Lit::Bool(false) || is_int_zero(lit)
+}
+
+fn is_one_bits(lit: Lit) -> bool {
+    match lit {
+        Lit::Bool(x) => x,
+        Lit::Num(Num::Int(x)) => {
+            let (bits, ty) = int_bits(x);
+            bits == u128::MAX >> (128 - width_bits(ty.width))
+        }
+        _ => false,
+    }
+}
+
+/// The bits of an integer zero-extended to 128 bits, and its type.
+fn int_bits(value: Int) -> (u128, IntType) {
+    match value {
+        Int::Signed(SignedInt::B8(x)) => (x as u8 as u128, IntType::S8),
+        Int::Signed(SignedInt::B16(x)) => (x as u16 as u128, IntType::S16),
+        Int::Signed(SignedInt::B32(x)) => (x as u32 as u128, IntType::S32),
+        Int::Signed(SignedInt::B64(x)) => (x as u64 as u128, IntType::S64),
+        Int::Signed(SignedInt::B128(x)) => (x as u128, IntType::S128),
+        Int::Unsigned(UnsignedInt::U8(x)) => (x as u128, IntType::U8),
+        Int::Unsigned(UnsignedInt::U16(x)) => (x as u128, IntType::U16),
+        Int::Unsigned(UnsignedInt::U32(x)) => (x as u128, IntType::U32),
+        Int::Unsigned(UnsignedInt::U64(x)) => (x as u128, IntType::U64),
+        Int::Unsigned(UnsignedInt::U128(x)) => (x, IntType::U128),
+        Int::Untyped(_) => unreachable!("Untyped literals are not folded"),
+    }
+}
+
+/// An integer of type `ty` from the low bits of `bits`.
+fn int_from_bits(bits: u128, ty: IntType) -> Int {
+    match (ty.width, ty.signed) {
+        (IntWidth::I8, true) => Int::Signed(SignedInt::B8(bits as i8)),
+        (IntWidth::I16, true) => Int::Signed(SignedInt::B16(bits as i16)),
+        (IntWidth::I32, true) => Int::Signed(SignedInt::B32(bits as i32)),
+        (IntWidth::I64, true) => Int::Signed(SignedInt::B64(bits as i64)),
+        (IntWidth::I128, true) => Int::Signed(SignedInt::B128(bits as i128)),
+        (IntWidth::I8, false) => Int::Unsigned(UnsignedInt::U8(bits as u8)),
+        (IntWidth::I16, false) => Int::Unsigned(UnsignedInt::U16(bits as u16)),
+        (IntWidth::I32, false) => Int::Unsigned(UnsignedInt::U32(bits as u32)),
+        (IntWidth::I64, false) => Int::Unsigned(UnsignedInt::U64(bits as u64)),
+        (IntWidth::I128, false) => Int::Unsigned(UnsignedInt::U128(bits)),
+    }
+}
+
+fn width_bits(width: IntWidth) -> u32 {
+    match width {
+        IntWidth::I8 => 8,
+        IntWidth::I16 => 16,
+        IntWidth::I32 => 32,
+        IntWidth::I64 => 64,
+        IntWidth::I128 => 128,
+    }
+}
+
+fn sign_extend(bits: u128, width: u32) -> i128 {
+    ((bits << (128 - width)) as i128) >> (128 - width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::optimize_module;
+    use crate::block::stmt::{ReturnStmt, Stmt};
+    use crate::function::Function;
+    use crate::module::Module;
+    use crate::ty::util as ty;
+    use crate::val::util::*;
+    use crate::val::{Bin, BinOp, Value};
+
+    /// Optimizes `value` as the result of a function that takes an `s32` and an `f64`.
+    fn optimize(value: Value<'static>) -> Value<'static> {
+        let function = Function::new("f")
+            .add_input(ty::s32())
+            .add_input(ty::f64())
+            .add_output(ty::s32())
+            .build_block(|scope| {
+                scope.ret([value]);
+            });
+        let mut module = Module::new("opt");
+        module.add_function(function);
+
+        optimize_module(&mut module);
+        match module.functions.remove(0).body.stmts.remove(0) {
+            Stmt::Return(ReturnStmt { mut values }) => values.remove(0),
+            stmt => panic!("Expected a return statement but found {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn overflowing_no_wrap_arithmetic_is_not_folded() {
+        let no_wrap = |op, lhs: i32, rhs: i32| Value::from(Bin { lhs: lit(lhs), rhs: lit(rhs), op, no_wrap: true });
+
+        for (op, lhs, rhs) in [(BinOp::Add, i32::MAX, 1), (BinOp::Sub, i32::MIN, 1), (BinOp::Mul, i32::MIN, -1)] {
+            let overflowing = no_wrap(op, lhs, rhs);
+            assert_eq!(optimize(overflowing.clone()), overflowing);
+        }
+        assert_eq!(optimize(no_wrap(BinOp::Add, i32::MAX - 1, 1)), lit(i32::MAX));
+        // Without `no_wrap` the result wraps around
+        assert_eq!(optimize(add(lit(i32::MAX), lit(1i32))), lit(i32::MIN));
+    }
+
+    #[test]
+    fn signed_min_divided_by_minus_one_is_not_folded() {
+        let min = || lit(i32::MIN);
+        let values: [Value; 3] = [div(min(), lit(-1i32)), rem(min(), lit(-1i32)), div(lit(7i32), lit(0i32))];
+        for value in values {
+            assert_eq!(optimize(value.clone()), value);
+        }
+        assert_eq!(optimize(div(lit(i32::MIN), lit(1i32))), lit(i32::MIN));
+        assert_eq!(optimize(div(lit(-7i32), lit(2i32))), lit(-3i32));
+        assert_eq!(optimize(rem(lit(-7i32), lit(2i32))), lit(-1i32));
+    }
+
+    #[test]
+    fn shifts_by_at_least_the_width_are_not_folded() {
+        let values: [Value; 3] = [shl(lit(1i32), lit(32i32)), shr(lit(-8i32), lit(40i32)), shl(lit(1i32), lit(-1i32))];
+        for value in values {
+            assert_eq!(optimize(value.clone()), value);
+        }
+        assert_eq!(optimize(shl(lit(1i32), lit(31i32))), lit(i32::MIN));
+        assert_eq!(optimize(shr(lit(-8i32), lit(1i32))), lit(-4i32));
+    }
+
+    #[test]
+    fn self_subtraction_is_only_folded_on_integers() {
+        // `x - x` is NaN rather than zero if `x` is NaN or infinite
+        let floats: Value = sub(arg(1), arg(1));
+        assert_eq!(optimize(floats.clone()), floats);
+        assert_eq!(optimize(sub(arg(0), arg(0))), lit(0i32));
+    }
+}
@@ -0,0 +1,551 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use crate::block::Block;
+use crate::block::stmt::{BindStmt, BindTupleStmt, Handler, Stmt, TryStmt};
+use crate::function::{CallConv, Function, Linkage, Signature};
+use crate::global::Global;
+use crate::module::Module;
+use crate::structure::Struct;
+use crate::ty::Type;
+use crate::val::{Arg, Call, CallIndirect, FuncAddr, Lit, Var};
+use crate::visit::{walk_bind_stmt, walk_bind_tuple_stmt, walk_block, walk_call, walk_call_indirect, walk_stmt, Visit};
+
+/// The index of a function in [`Module::functions`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct FunctionId(pub u32);
+
+/// The index of a global in [`Module::globals`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct GlobalId(pub u32);
+
+/// The index of a struct in [`Module::structs`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct StructId(pub u32);
+
+/// The functions, globals and structs of a module by name, built by [`SymbolTable::resolve`].
+///
+/// Functions and globals share a namespace, as they are both symbols in the compiled module, while structs have their
+/// own.
+#[derive(Debug, Clone)]
+pub struct SymbolTable<'m, 's> {
+    module: &'m Module<'s>,
+    functions: HashMap<&'m str, FunctionId>,
+    globals: HashMap<&'m str, GlobalId>,
+    structs: HashMap<&'m str, StructId>,
+    // Weak definitions that another module linked with this one overrides
+    overridden: HashSet<FunctionId>,
+    // The functions that calls and function addresses refer to, keyed by the address of their node in the module
+    callees: HashMap<usize, FunctionId>,
+    addressed: HashMap<usize, FunctionId>,
+    personalities: HashMap<FunctionId, FunctionId>,
+}
+
+impl<'m, 's> SymbolTable<'m, 's> {
+    /// Builds the symbol table of `module`, checking that no name is defined twice, that every call, argument and
+    /// variable refers to a definition, and that calls pass as many arguments as their callees take. The functions
+    /// that calls refer to are then found by [`SymbolTable::callee`] rather than by name.
+    pub fn resolve(module: &'m Module<'s>) -> Result<Self, Vec<ResolveError>> {
+        let mut errors = Vec::new();
+        let mut table = SymbolTable {
+            module,
+            functions: HashMap::new(),
+            globals: HashMap::new(),
+            structs: HashMap::new(),
+            overridden: HashSet::new(),
+            callees: HashMap::new(),
+            addressed: HashMap::new(),
+            personalities: HashMap::new(),
+        };
+
+        for (i, ast_struct) in module.structs.iter().enumerate() {
+            if table.structs.insert(&ast_struct.name, StructId(i as u32)).is_some() {
+                errors.push(ResolveError::DuplicateStruct(ast_struct.name.to_string()));
+            }
+        }
+
+        for (i, ast_global) in module.globals.iter().enumerate() {
+            if table.is_symbol(&ast_global.name) {
+                errors.push(ResolveError::DuplicateSymbol(ast_global.name.to_string()));
+            }
+            table.globals.insert(&ast_global.name, GlobalId(i as u32));
+        }
+
+        for (i, ast_function) in module.functions.iter().enumerate() {
+            if table.is_symbol(&ast_function.name) {
+                errors.push(ResolveError::DuplicateSymbol(ast_function.name.to_string()));
+            }
+            table.functions.insert(&ast_function.name, FunctionId(i as u32));
+        }
+
+        let mut references = References::default();
+        for (id, ast_function) in table.functions() {
+            let mut resolver = Resolver {
+                table: &table,
+                function: ast_function,
+                levels: Vec::new(),
+                tries: 0,
+                references: &mut references,
+                errors: &mut errors,
+            };
+            let personality = ast_function.personality.as_ref().and_then(|x| resolver.resolve_function(x));
+            if let Some(personality) = personality {
+                resolver.references.personalities.insert(id, personality);
+            }
+            resolver.visit_block(&ast_function.body);
+        }
+        table.callees = references.callees;
+        table.addressed = references.addressed;
+        table.personalities = references.personalities;
+
+        if errors.is_empty() {
+            Ok(table)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Builds the symbol tables of modules that are compiled together, checking each of them like
+    /// [`SymbolTable::resolve`] and that they agree on the symbols they share.
+    ///
+    /// Functions other than [`Linkage::Internal`] ones are shared by name, so a function imported by one module calls
+    /// the definition of another. Every shared symbol must be declared with the same signature, and defined by at most
+    /// one module unless all but one of the definitions are weak. Weak definitions are overridden by a strong one, or
+    /// otherwise by the first one.
+    pub fn resolve_linked(modules: impl IntoIterator<Item = &'m Module<'s>>) -> Result<Vec<Self>, Vec<ResolveError>> {
+        let mut errors = Vec::new();
+        let mut tables = Vec::new();
+        for module in modules {
+            match SymbolTable::resolve(module) {
+                Ok(table) => tables.push(table),
+                Err(module_errors) => errors.extend(module_errors),
+            }
+        }
+
+        // The signature of every shared symbol, which globals do not have, and those with a strong definition
+        let mut signatures = HashMap::new();
+        let mut strong = HashSet::new();
+        for table in &tables {
+            let globals = table.module.globals.iter().map(|x| (x.name.as_ref(), None, true));
+            let functions = table.module.functions
+                .iter()
+                .filter(|x| x.linkage != Linkage::Internal)
+                .map(|x| (x.name.as_ref(), Some(&x.signature), x.linkage == Linkage::Export));
+
+            for (name, signature, is_strong) in globals.chain(functions) {
+                if *signatures.entry(name).or_insert(signature) != signature {
+                    errors.push(ResolveError::IncompatibleDeclarations(name.to_string()));
+                }
+                if is_strong && !strong.insert(name) {
+                    errors.push(ResolveError::DuplicateSymbol(name.to_string()));
+                }
+            }
+        }
+
+        let mut first_weak = HashMap::new();
+        for (i, table) in tables.iter_mut().enumerate() {
+            let weak = table.functions()
+                .filter(|(_, x)| x.linkage == Linkage::Weak)
+                .map(|(id, x)| (id, x.name.as_ref()))
+                .collect::<Vec<_>>();
+
+            for (id, name) in weak {
+                if strong.contains(name) || *first_weak.entry(name).or_insert(i) != i {
+                    table.overridden.insert(id);
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(tables)
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn module(&self) -> &'m Module<'s> {
+        self.module
+    }
+
+    pub fn function_id(&self, name: &str) -> Option<FunctionId> {
+        self.functions.get(name).copied()
+    }
+
+    pub fn global_id(&self, name: &str) -> Option<GlobalId> {
+        self.globals.get(name).copied()
+    }
+
+    pub fn struct_id(&self, name: &str) -> Option<StructId> {
+        self.structs.get(name).copied()
+    }
+
+    pub fn function(&self, id: FunctionId) -> &'m Function<'s> {
+        &self.module.functions[id.0 as usize]
+    }
+
+    pub fn global(&self, id: GlobalId) -> &'m Global<'s> {
+        &self.module.globals[id.0 as usize]
+    }
+
+    pub fn structure(&self, id: StructId) -> &'m Struct<'s> {
+        &self.module.structs[id.0 as usize]
+    }
+
+    pub fn functions(&self) -> impl Iterator<Item = (FunctionId, &'m Function<'s>)> + use<'m, 's> {
+        self.module.functions
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (FunctionId(i as u32), x))
+    }
+
+    /// The function that `call` calls, which must be a call of the resolved module.
+    pub fn callee(&self, call: &Call) -> FunctionId {
+        *self.callees.get(&node_key(call)).expect("Call is not part of the resolved module")
+    }
+
+    /// The function whose address `func_addr` takes, which must be a function address of the resolved module.
+    pub fn addressed(&self, func_addr: &FuncAddr) -> FunctionId {
+        *self.addressed.get(&node_key(func_addr)).expect("Function address is not part of the resolved module")
+    }
+
+    pub fn personality(&self, id: FunctionId) -> Option<FunctionId> {
+        self.personalities.get(&id).copied()
+    }
+
+    /// The linkage that the function is compiled with, which is [`Linkage::Import`] for a weak definition that another
+    /// linked module overrides.
+    pub fn linkage(&self, id: FunctionId) -> Linkage {
+        match self.overridden.contains(&id) {
+            true => Linkage::Import,
+            false => self.function(id).linkage,
+        }
+    }
+
+    fn is_symbol(&self, name: &str) -> bool {
+        self.functions.contains_key(name) || self.globals.contains_key(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolveError {
+    DuplicateSymbol(String),
+    DuplicateStruct(String),
+    /// A symbol that linked modules declare with different signatures, or as both a function and a global.
+    IncompatibleDeclarations(String),
+    UndefinedFunction {
+        function: String,
+        name: String,
+    },
+    UndefinedVariable {
+        function: String,
+        name: String,
+    },
+    /// An argument of a function that has fewer inputs.
+    UndefinedArg {
+        function: String,
+        param_index: u32,
+    },
+    /// A call with a different number of arguments than the callee has inputs, or fewer if it is variadic. The callee
+    /// is `None` if it is called by its address.
+    ArgumentCount {
+        function: String,
+        callee: Option<String>,
+        expected: usize,
+        variadic: bool,
+        found: usize,
+    },
+    /// A tail call from or to a function that does not use [`CallConv::Tail`], to a function whose outputs differ from
+    /// those of the caller, or in the body of a try statement. The callee is `None` if it is called by its address.
+    InvalidTailCall {
+        function: String,
+        callee: Option<String>,
+    },
+    /// A try statement in a function without a personality.
+    MissingPersonality {
+        function: String,
+    },
+    /// An untyped literal that is used without a type, or as a type that cannot represent it.
+    UntypedLiteral {
+        function: Option<String>,
+        lit: Lit,
+        ty: Option<Type>,
+    },
+}
+
+impl Display for ResolveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolveError::DuplicateSymbol(name) => write!(f, "Symbol '{}' is defined more than once", name),
+            ResolveError::DuplicateStruct(name) => write!(f, "Struct '{}' is defined more than once", name),
+            ResolveError::IncompatibleDeclarations(name) => {
+                write!(f, "Symbol '{}' is declared differently by the linked modules", name)
+            }
+            ResolveError::UndefinedFunction { function, name } => {
+                write!(f, "Function '{}' refers to undefined function '{}'", function, name)
+            }
+            ResolveError::UndefinedVariable { function, name } => {
+                write!(f, "Function '{}' uses undefined variable '{}'", function, name)
+            }
+            ResolveError::UndefinedArg { function, param_index } => {
+                write!(f, "Function '{}' uses argument {}, which it does not have", function, param_index)
+            }
+            ResolveError::ArgumentCount { function, callee, expected, variadic, found } => {
+                match callee {
+                    Some(callee) => write!(f, "Function '{}' calls function '{}'", function, callee)?,
+                    None => write!(f, "Function '{}' calls a function by its address", function)?,
+                }
+                match variadic {
+                    true => write!(f, " with {} arguments instead of at least {}", found, expected),
+                    false => write!(f, " with {} arguments instead of {}", found, expected),
+                }
+            }
+            ResolveError::InvalidTailCall { function, callee } => {
+                match callee {
+                    Some(callee) => write!(f, "Function '{}' cannot tail call function '{}'", function, callee)?,
+                    None => write!(f, "Function '{}' cannot tail call a function by its address", function)?,
+                }
+                write!(f, ", which requires both to use the tail calling convention and have the same outputs")?;
+                write!(f, " and the call to be outside of try statements")
+            }
+            ResolveError::MissingPersonality { function } => {
+                write!(f, "Function '{}' uses try statements without a personality", function)
+            }
+            ResolveError::UntypedLiteral { function, lit, ty } => {
+                match function {
+                    Some(function) => write!(f, "Function '{}' uses literal {:?}", function, lit)?,
+                    None => write!(f, "Global uses literal {:?}", lit)?,
+                }
+                match ty {
+                    Some(ty) => write!(f, " as {:?}, which cannot represent it", ty),
+                    None => write!(f, " without a type"),
+                }
+            }
+        }
+    }
+}
+
+impl Error for ResolveError {}
+
+// The module borrows its nodes for as long as the symbol table lives, so they cannot move in the meantime
+fn node_key<T>(node: &T) -> usize {
+    node as *const T as usize
+}
+
+#[derive(Default)]
+struct References {
+    callees: HashMap<usize, FunctionId>,
+    addressed: HashMap<usize, FunctionId>,
+    personalities: HashMap<FunctionId, FunctionId>,
+}
+
+struct Resolver<'t, 'm, 's> {
+    table: &'t SymbolTable<'m, 's>,
+    function: &'m Function<'s>,
+    levels: Vec<HashSet<&'m str>>,
+    // The number of try statements whose body is being resolved
+    tries: u32,
+    references: &'t mut References,
+    errors: &'t mut Vec<ResolveError>,
+}
+
+impl Resolver<'_, '_, '_> {
+    fn check_tail_call(&mut self, callee: Option<&str>, signature: &Signature) {
+        let caller = &self.function.signature;
+        let valid = caller.call_conv == CallConv::Tail
+            && signature.call_conv == CallConv::Tail
+            && caller.output == signature.output
+            && self.tries == 0;
+
+        if !valid {
+            self.errors.push(ResolveError::InvalidTailCall {
+                function: self.function.name.to_string(),
+                callee: callee.map(str::to_string),
+            });
+        }
+    }
+
+    fn check_args(&mut self, callee: Option<&str>, signature: &Signature, found: usize) {
+        let expected = signature.input.len();
+        let valid = match signature.variadic {
+            true => found >= expected,
+            false => found == expected,
+        };
+
+        if !valid {
+            self.errors.push(ResolveError::ArgumentCount {
+                function: self.function.name.to_string(),
+                callee: callee.map(str::to_string),
+                expected,
+                variadic: signature.variadic,
+                found,
+            });
+        }
+    }
+
+    fn resolve_function(&mut self, name: &str) -> Option<FunctionId> {
+        let id = self.table.function_id(name);
+        if id.is_none() {
+            self.errors.push(ResolveError::UndefinedFunction {
+                function: self.function.name.to_string(),
+                name: name.to_string(),
+            });
+        }
+
+        id
+    }
+}
+
+impl<'m> Visit<'m> for Resolver<'_, 'm, '_> {
+    fn visit_block(&mut self, block: &'m Block) {
+        self.levels.push(HashSet::new());
+        walk_block(self, block);
+        self.levels.pop();
+    }
+
+    fn visit_bind_stmt(&mut self, stmt: &'m BindStmt) {
+        // The bound value is resolved first, as a binding cannot refer to itself
+        walk_bind_stmt(self, stmt);
+        self.levels.last_mut().unwrap().insert(&stmt.name);
+    }
+
+    fn visit_bind_tuple_stmt(&mut self, stmt: &'m BindTupleStmt) {
+        walk_bind_tuple_stmt(self, stmt);
+        self.levels.last_mut().unwrap().extend(stmt.names.iter().map(AsRef::as_ref));
+    }
+
+    fn visit_arg(&mut self, arg: &'m Arg) {
+        if arg.param_index as usize >= self.function.signature.input.len() {
+            self.errors.push(ResolveError::UndefinedArg {
+                function: self.function.name.to_string(),
+                param_index: arg.param_index,
+            });
+        }
+    }
+
+    fn visit_var(&mut self, var: &'m Var) {
+        if !self.levels.iter().any(|level| level.contains(var.name.as_ref())) {
+            self.errors.push(ResolveError::UndefinedVariable {
+                function: self.function.name.to_string(),
+                name: var.name.to_string(),
+            });
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &'m Stmt) {
+        match stmt {
+            Stmt::TailCall(x) => {
+                // An undefined callee is reported by `visit_call`
+                if let Some(id) = self.table.function_id(&x.function_name) {
+                    self.check_tail_call(Some(&x.function_name), &self.table.function(id).signature);
+                }
+            }
+            Stmt::TailCallIndirect(x) => self.check_tail_call(None, &x.signature),
+            _ => {}
+        }
+
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &'m TryStmt) {
+        if self.function.personality.is_none() {
+            self.errors.push(ResolveError::MissingPersonality {
+                function: self.function.name.to_string(),
+            });
+        }
+
+        self.tries += 1;
+        self.visit_block(&stmt.body);
+        self.tries -= 1;
+
+        match &stmt.handler {
+            Handler::Cleanup(x) => self.visit_block(x),
+            Handler::Catch { name, block } => {
+                // The exception object is only bound in the handler
+                self.levels.push(HashSet::from([name.as_ref()]));
+                self.visit_block(block);
+                self.levels.pop();
+            }
+        }
+    }
+
+    fn visit_call(&mut self, call: &'m Call) {
+        if let Some(id) = self.resolve_function(&call.function_name) {
+            self.references.callees.insert(node_key(call), id);
+            self.check_args(Some(&call.function_name), &self.table.function(id).signature, call.args.len());
+        }
+        walk_call(self, call);
+    }
+
+    fn visit_call_indirect(&mut self, call: &'m CallIndirect) {
+        self.check_args(None, &call.signature, call.args.len());
+        walk_call_indirect(self, call);
+    }
+
+    fn visit_func_addr(&mut self, func_addr: &'m FuncAddr) {
+        if let Some(id) = self.resolve_function(&func_addr.function_name) {
+            self.references.addressed.insert(node_key(func_addr), id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ResolveError, SymbolTable};
+    use crate::function::{Function, Linkage};
+    use crate::global::Global;
+    use crate::module::Module;
+    use crate::ty::util as ty;
+
+    fn module<'s>(globals: Vec<Global<'s>>, functions: Vec<Function<'s>>) -> Module<'s> {
+        let mut module = Module::new("resolve");
+        globals.into_iter().for_each(|x| module.add_global(x));
+        functions.into_iter().for_each(|x| module.add_function(x));
+        module
+    }
+
+    fn function(name: &str, linkage: Linkage) -> Function<'_> {
+        Function::new(name)
+            .with_linkage(linkage)
+            .build_block(|scope| {
+                scope.ret([]);
+            })
+    }
+
+    #[test]
+    fn symbols_defined_twice_are_reported() {
+        let counter = Global { name: "counter".into(), ty: ty::u32(), value: None };
+        let functions = vec![function("run", Linkage::Export), function("run", Linkage::Internal)];
+        let ast_module = module(vec![counter.clone(), counter], functions);
+
+        let errors = SymbolTable::resolve(&ast_module).err();
+        assert_eq!(errors, Some(vec![
+            ResolveError::DuplicateSymbol("counter".to_string()),
+            ResolveError::DuplicateSymbol("run".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn functions_and_globals_share_names() {
+        let run = Global { name: "run".into(), ty: ty::u32(), value: None };
+        let ast_module = module(vec![run], vec![function("run", Linkage::Export)]);
+
+        let errors = SymbolTable::resolve(&ast_module).err();
+        assert_eq!(errors, Some(vec![ResolveError::DuplicateSymbol("run".to_string())]));
+    }
+
+    #[test]
+    fn linked_modules_define_shared_symbols_once() {
+        let strong = || module(vec![], vec![function("run", Linkage::Export)]);
+        let weak = module(vec![], vec![function("run", Linkage::Weak)]);
+        let internal = module(vec![], vec![function("run", Linkage::Internal)]);
+
+        let errors = SymbolTable::resolve_linked([&strong(), &strong()]).err();
+        assert_eq!(errors, Some(vec![ResolveError::DuplicateSymbol("run".to_string())]));
+
+        // A weak definition is overridden by the strong one, and an internal one is not shared at all
+        let strong = strong();
+        let tables = SymbolTable::resolve_linked([&weak, &strong, &internal]).unwrap();
+        let linkages = tables.iter().map(|x| x.linkage(x.function_id("run").unwrap())).collect::<Vec<_>>();
+        assert_eq!(linkages, vec![Linkage::Import, Linkage::Export, Linkage::Internal]);
+    }
+}
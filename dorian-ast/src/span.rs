@@ -0,0 +1,32 @@
+use std::borrow::Cow;
+
+/// A location in the source file that a frontend produced an AST node from.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Span<'s> {
+    pub file: Cow<'s, str>,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl<'s> Span<'s> {
+    pub fn new(file: impl Into<Cow<'s, str>>, line: u32, column: u32) -> Self {
+        Span {
+            file: file.into(),
+            line,
+            column,
+        }
+    }
+}
+
+/// An AST node annotated with the [`Span`] it was produced from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned<'s, T> {
+    pub span: Span<'s>,
+    pub node: T,
+}
+
+impl<'s, T> Spanned<'s, T> {
+    pub fn new(span: Span<'s>, node: T) -> Self {
+        Spanned { span, node }
+    }
+}
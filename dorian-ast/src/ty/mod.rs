@@ -10,12 +10,36 @@ pub enum Type {
 }
 
 impl Type {
+    /// The signage of an integer, or of the lanes of a vector of integers.
     pub fn get_signage(&self) -> Option<Bool> {
+        let (Type::Scalar(ty) | Type::Vector(VectorType { elem: ty, .. })) = self;
+        match ty {
+            ScalarType::Num(NumType::Int(IntType { signed, .. })) => Some(*signed),
+            _ => None,
+        }
+    }
+
+    /// The type that a value of the type is passed as after the default argument promotions of C, which apply to the
+    /// arguments of a variadic function after its inputs: booleans and integers narrower than 32 bits are extended to
+    /// 32 bits, and `f32` to `f64`.
+    pub fn c_promoted(&self) -> Type {
         match self {
-            Type::Scalar(ScalarType::Num(NumType::Int(IntType { signed, .. }))) => {
-                Some(*signed)
+            Type::Scalar(ScalarType::Num(NumType::Int(x))) if matches!(x.width, IntWidth::I8 | IntWidth::I16) => {
+                IntType { width: IntWidth::I32, signed: x.signed }.into()
             }
-            _ => None,
+            Type::Scalar(ScalarType::Bool(_)) => IntType::U32.into(),
+            Type::Scalar(ScalarType::Num(NumType::Float(FloatType::F32))) => FloatType::F64.into(),
+            _ => *self,
+        }
+    }
+
+    /// Whether values of the type can be loaded and stored atomically, which are integers of up to 64 bits and
+    /// pointers.
+    pub fn is_atomic(&self) -> bool {
+        match self {
+            Type::Scalar(ScalarType::Num(NumType::Int(x))) => x.width != IntWidth::I128,
+            Type::Scalar(ScalarType::Ptr(_)) => true,
+            _ => false,
         }
     }
 }
@@ -1,7 +1,9 @@
 use crate::val::{
-    Arg, Bin, Call, ContextValue, Expr, Float, Int, Lit, Num, SignedInt, Una, UnsignedInt, Value,
-    Var,
+    Arg, AtomicLoad, AtomicRmw, Bin, Call, CallIndirect, Checked, CmpXchg, ContextValue, Expr, ExtractElement, Float,
+    FloatCmp, FuncAddr, InsertElement, Int, Intrinsic, Lit, Num, Reduce, Shuffle, SignedInt, Splat, Una, UnsignedInt,
+    Value, Var, Vector,
 };
+use crate::span::Spanned;
 
 // impl From<...> for Value
 
@@ -41,6 +43,60 @@ impl<'s> From<Una<'s>> for Value<'s> {
     }
 }
 
+impl<'s> From<Checked<'s>> for Value<'s> {
+    fn from(value: Checked<'s>) -> Self {
+        Value::Expr(Box::new(value.into()))
+    }
+}
+
+impl<'s> From<FloatCmp<'s>> for Value<'s> {
+    fn from(value: FloatCmp<'s>) -> Self {
+        Value::Expr(Box::new(value.into()))
+    }
+}
+
+impl<'s> From<Splat<'s>> for Value<'s> {
+    fn from(value: Splat<'s>) -> Self {
+        Value::Expr(Box::new(value.into()))
+    }
+}
+
+impl<'s> From<Vector<'s>> for Value<'s> {
+    fn from(value: Vector<'s>) -> Self {
+        Value::Expr(Box::new(value.into()))
+    }
+}
+
+impl<'s> From<ExtractElement<'s>> for Value<'s> {
+    fn from(value: ExtractElement<'s>) -> Self {
+        Value::Expr(Box::new(value.into()))
+    }
+}
+
+impl<'s> From<InsertElement<'s>> for Value<'s> {
+    fn from(value: InsertElement<'s>) -> Self {
+        Value::Expr(Box::new(value.into()))
+    }
+}
+
+impl<'s> From<Shuffle<'s>> for Value<'s> {
+    fn from(value: Shuffle<'s>) -> Self {
+        Value::Expr(Box::new(value.into()))
+    }
+}
+
+impl<'s> From<Reduce<'s>> for Value<'s> {
+    fn from(value: Reduce<'s>) -> Self {
+        Value::Expr(Box::new(value.into()))
+    }
+}
+
+impl<'s> From<Spanned<'s, Value<'s>>> for Value<'s> {
+    fn from(value: Spanned<'s, Value<'s>>) -> Self {
+        Value::Spanned(Box::new(value))
+    }
+}
+
 impl From<Lit> for Value<'_> {
     fn from(value: Lit) -> Self {
         Value::Lit(value)
@@ -161,6 +217,42 @@ impl<'s> From<Call<'s>> for Value<'s> {
     }
 }
 
+impl<'s> From<FuncAddr<'s>> for Value<'s> {
+    fn from(value: FuncAddr<'s>) -> Self {
+        Value::FuncAddr(value)
+    }
+}
+
+impl<'s> From<CallIndirect<'s>> for Value<'s> {
+    fn from(value: CallIndirect<'s>) -> Self {
+        Value::CallIndirect(Box::new(value))
+    }
+}
+
+impl<'s> From<Intrinsic<'s>> for Value<'s> {
+    fn from(value: Intrinsic<'s>) -> Self {
+        Value::Intrinsic(value)
+    }
+}
+
+impl<'s> From<AtomicLoad<'s>> for Value<'s> {
+    fn from(value: AtomicLoad<'s>) -> Self {
+        Value::AtomicLoad(Box::new(value))
+    }
+}
+
+impl<'s> From<AtomicRmw<'s>> for Value<'s> {
+    fn from(value: AtomicRmw<'s>) -> Self {
+        Value::AtomicRmw(Box::new(value))
+    }
+}
+
+impl<'s> From<CmpXchg<'s>> for Value<'s> {
+    fn from(value: CmpXchg<'s>) -> Self {
+        Value::CmpXchg(Box::new(value))
+    }
+}
+
 // impl From<...> for ContextValue
 
 impl From<Arg> for ContextValue<'_> {
@@ -189,6 +281,54 @@ impl<'s> From<Una<'s>> for Expr<'s> {
     }
 }
 
+impl<'s> From<Checked<'s>> for Expr<'s> {
+    fn from(value: Checked<'s>) -> Self {
+        Expr::Checked(value)
+    }
+}
+
+impl<'s> From<FloatCmp<'s>> for Expr<'s> {
+    fn from(value: FloatCmp<'s>) -> Self {
+        Expr::FloatCmp(value)
+    }
+}
+
+impl<'s> From<Splat<'s>> for Expr<'s> {
+    fn from(value: Splat<'s>) -> Self {
+        Expr::Splat(value)
+    }
+}
+
+impl<'s> From<Vector<'s>> for Expr<'s> {
+    fn from(value: Vector<'s>) -> Self {
+        Expr::Vector(value)
+    }
+}
+
+impl<'s> From<ExtractElement<'s>> for Expr<'s> {
+    fn from(value: ExtractElement<'s>) -> Self {
+        Expr::ExtractElement(value)
+    }
+}
+
+impl<'s> From<InsertElement<'s>> for Expr<'s> {
+    fn from(value: InsertElement<'s>) -> Self {
+        Expr::InsertElement(value)
+    }
+}
+
+impl<'s> From<Shuffle<'s>> for Expr<'s> {
+    fn from(value: Shuffle<'s>) -> Self {
+        Expr::Shuffle(value)
+    }
+}
+
+impl<'s> From<Reduce<'s>> for Expr<'s> {
+    fn from(value: Reduce<'s>) -> Self {
+        Expr::Reduce(value)
+    }
+}
+
 // impl From<...> for Lit
 
 impl<'s> From<Num> for Lit {
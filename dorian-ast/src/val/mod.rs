@@ -2,6 +2,9 @@ mod convert;
 pub mod util;
 
 use std::borrow::Cow;
+use crate::function::Signature;
+use crate::span::Spanned;
+use crate::ty::{BoolType, FloatType, IntType, IntWidth, NumType, ScalarType, Type};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value<'s> {
@@ -9,6 +12,13 @@ pub enum Value<'s> {
     Expr(Box<Expr<'s>>),
     Lit(Lit),
     Call(Call<'s>),
+    FuncAddr(FuncAddr<'s>),
+    CallIndirect(Box<CallIndirect<'s>>),
+    Intrinsic(Intrinsic<'s>),
+    AtomicLoad(Box<AtomicLoad<'s>>),
+    AtomicRmw(Box<AtomicRmw<'s>>),
+    CmpXchg(Box<CmpXchg<'s>>),
+    Spanned(Box<Spanned<'s, Value<'s>>>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -31,6 +41,14 @@ pub struct Var<'s> {
 pub enum Expr<'s> {
     Bin(Bin<'s>),
     Una(Una<'s>),
+    Checked(Checked<'s>),
+    FloatCmp(FloatCmp<'s>),
+    Splat(Splat<'s>),
+    Vector(Vector<'s>),
+    ExtractElement(ExtractElement<'s>),
+    InsertElement(InsertElement<'s>),
+    Shuffle(Shuffle<'s>),
+    Reduce(Reduce<'s>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -58,7 +76,8 @@ pub enum BinOp {
     BitXor,
     Shl,
     Shr,
-    // Comparison operators
+    // Comparison operators, which on floats are false if either operand is NaN, except for `Ne` which is true, as in
+    // IEEE 754. A `FloatCmp` chooses the result for NaN explicitly.
     Eq,
     Ne,
     Lt,
@@ -67,6 +86,115 @@ pub enum BinOp {
     Ge,
 }
 
+/// An arithmetic operation on integers that detects overflow rather than wrapping or assuming it cannot happen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checked<'s> {
+    pub lhs: Value<'s>,
+    pub rhs: Value<'s>,
+    pub op: CheckedOp,
+    pub on_overflow: OnOverflow,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum CheckedOp {
+    Add,
+    Sub,
+    Mul,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum OnOverflow {
+    /// The operation produces two values, the wrapped result and a `bool` of whether it overflowed, which are
+    /// returned together or bound with [`BindTupleStmt`](crate::block::stmt::BindTupleStmt).
+    Flag,
+    /// The operation produces the result, and traps if it overflowed.
+    Trap,
+}
+
+/// A comparison of floats that chooses its result when either operand is NaN, i.e. when the operands are unordered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatCmp<'s> {
+    pub lhs: Value<'s>,
+    pub rhs: Value<'s>,
+    pub op: FloatCmpOp,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum FloatCmpOp {
+    /// Neither operand is NaN.
+    Ordered,
+    /// Either operand is NaN.
+    Unordered,
+    // Comparisons that are false if either operand is NaN
+    OrderedEq,
+    OrderedNe,
+    OrderedLt,
+    OrderedGt,
+    OrderedLe,
+    OrderedGe,
+    // Comparisons that are true if either operand is NaN
+    UnorderedEq,
+    UnorderedNe,
+    UnorderedLt,
+    UnorderedGt,
+    UnorderedLe,
+    UnorderedGe,
+}
+
+/// A vector with every lane set to `value`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Splat<'s> {
+    pub value: Value<'s>,
+    pub len: u32,
+}
+
+/// A vector of `elems`, which is a constant if they all are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vector<'s> {
+    pub elems: Vec<Value<'s>>,
+}
+
+/// The lane of `vector` at `index`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractElement<'s> {
+    pub vector: Value<'s>,
+    pub index: u32,
+}
+
+/// `vector` with the lane at `index` replaced by `value`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InsertElement<'s> {
+    pub vector: Value<'s>,
+    pub value: Value<'s>,
+    pub index: u32,
+}
+
+/// A vector of the lanes of `lhs` and `rhs` chosen by `mask`, where the lanes of `rhs` follow those of `lhs`. The mask
+/// has a lane for each lane of the operands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shuffle<'s> {
+    pub lhs: Value<'s>,
+    pub rhs: Value<'s>,
+    pub mask: Vec<u32>,
+}
+
+/// The lanes of `vector` combined into a single value from the first to the last.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reduce<'s> {
+    pub vector: Value<'s>,
+    pub op: ReduceOp,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ReduceOp {
+    /// The wrapping sum of integers, or the sum of floats added in order.
+    Sum,
+    /// The smallest lane, like [`IntrinsicOp::Min`].
+    Min,
+    /// The largest lane, like [`IntrinsicOp::Max`].
+    Max,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Una<'s> {
     pub operand: Value<'s>,
@@ -78,6 +206,8 @@ pub struct Una<'s> {
 pub enum UnaOp {
     Neg,
     Not,
+    /// Whether a float is NaN, producing a `bool`.
+    IsNan,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -86,6 +216,34 @@ pub enum Lit {
     Bool(bool),
 }
 
+impl Lit {
+    /// The type of the literal, or `None` if it is untyped.
+    pub fn ty(&self) -> Option<Type> {
+        let ty = match self {
+            Lit::Num(Num::Int(Int::Signed(x))) => match x {
+                SignedInt::B8(_) => IntType::S8,
+                SignedInt::B16(_) => IntType::S16,
+                SignedInt::B32(_) => IntType::S32,
+                SignedInt::B64(_) => IntType::S64,
+                SignedInt::B128(_) => IntType::S128,
+            }.into(),
+            Lit::Num(Num::Int(Int::Unsigned(x))) => match x {
+                UnsignedInt::U8(_) => IntType::U8,
+                UnsignedInt::U16(_) => IntType::U16,
+                UnsignedInt::U32(_) => IntType::U32,
+                UnsignedInt::U64(_) => IntType::U64,
+                UnsignedInt::U128(_) => IntType::U128,
+            }.into(),
+            Lit::Num(Num::Float(Float::F32(_))) => FloatType::F32.into(),
+            Lit::Num(Num::Float(Float::F64(_))) => FloatType::F64.into(),
+            Lit::Num(Num::Int(Int::Untyped(_)) | Num::Float(Float::Untyped(_))) => return None,
+            Lit::Bool(_) => BoolType.into(),
+        };
+
+        Some(ty)
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Num {
     Int(Int),
@@ -96,6 +254,8 @@ pub enum Num {
 pub enum Int {
     Signed(SignedInt),
     Unsigned(UnsignedInt),
+    /// An integer whose width and signage are inferred from where it is used, see [`infer`](crate::infer).
+    Untyped(i128),
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -132,6 +292,8 @@ impl UnsignedInt {
 pub enum Float {
     F32(f32),
     F64(f64),
+    /// A float whose width is inferred from where it is used, see [`infer`](crate::infer).
+    Untyped(f64),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -139,3 +301,172 @@ pub struct Call<'s> {
     pub function_name: Cow<'s, str>,
     pub args: Vec<Value<'s>>,
 }
+
+/// The address of the function named `function_name`, a `ptr` that can be called with [`CallIndirect`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuncAddr<'s> {
+    pub function_name: Cow<'s, str>,
+}
+
+/// A call to the function at the address `callee`, which must have been defined with `signature`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallIndirect<'s> {
+    pub callee: Value<'s>,
+    pub signature: Signature,
+    pub args: Vec<Value<'s>>,
+}
+
+/// A math or bit-manipulation operation that is not a [`BinOp`] or [`UnaOp`], lowered to a native instruction where
+/// the backend has one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Intrinsic<'s> {
+    pub op: IntrinsicOp,
+    pub args: Vec<Value<'s>>,
+}
+
+/// The operation of an [`Intrinsic`], whose arguments and result all have the same type.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum IntrinsicOp {
+    // Float operations
+    Sqrt,
+    /// `a * b + c` with a single rounding.
+    Fma,
+    Floor,
+    Ceil,
+    Trunc,
+    /// The magnitude of the first argument with the sign of the second.
+    CopySign,
+    // Operations on both integers and floats
+    /// The absolute value, which wraps for the minimum signed integer.
+    Abs,
+    /// The smaller argument, which is NaN if either float is NaN, and `-0.0` for `-0.0` and `0.0`.
+    Min,
+    /// The larger argument, which is NaN if either float is NaN, and `0.0` for `-0.0` and `0.0`.
+    Max,
+    // Integer operations
+    /// The number of set bits.
+    CtPop,
+    /// The number of leading zero bits, which is the width of the integer for zero.
+    Clz,
+    /// The number of trailing zero bits, which is the width of the integer for zero.
+    Ctz,
+    /// The integer with its bytes in reverse order.
+    BSwap,
+}
+
+impl IntrinsicOp {
+    /// The number of arguments the operation takes.
+    pub fn arity(self) -> usize {
+        match self {
+            IntrinsicOp::Fma => 3,
+            IntrinsicOp::CopySign | IntrinsicOp::Min | IntrinsicOp::Max => 2,
+            _ => 1,
+        }
+    }
+
+    /// Whether the operation is defined for arguments of type `ty`.
+    pub fn supports(self, ty: Type) -> bool {
+        let Type::Scalar(ScalarType::Num(ty)) = ty else {
+            return false;
+        };
+
+        match (self, ty) {
+            (
+                IntrinsicOp::Sqrt | IntrinsicOp::Fma | IntrinsicOp::Floor | IntrinsicOp::Ceil | IntrinsicOp::Trunc
+                | IntrinsicOp::CopySign,
+                NumType::Float(_),
+            ) => true,
+            // The absolute value of an unsigned integer is always itself
+            (IntrinsicOp::Abs, NumType::Int(x)) => x.signed,
+            (IntrinsicOp::Abs | IntrinsicOp::Min | IntrinsicOp::Max, _) => true,
+            (IntrinsicOp::CtPop | IntrinsicOp::Clz | IntrinsicOp::Ctz, NumType::Int(_)) => true,
+            // A single byte has nothing to swap
+            (IntrinsicOp::BSwap, NumType::Int(x)) => x.width != IntWidth::I8,
+            _ => false,
+        }
+    }
+}
+
+/// The ordering of an atomic operation relative to the memory operations of other threads, as in C++11.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AtomicOrdering {
+    /// Only the operation itself is atomic.
+    Relaxed,
+    /// Later memory operations cannot be reordered before the operation.
+    Acquire,
+    /// Earlier memory operations cannot be reordered after the operation.
+    Release,
+    AcqRel,
+    /// Like [`AtomicOrdering::AcqRel`], and all sequentially consistent operations have a single total order.
+    SeqCst,
+}
+
+impl AtomicOrdering {
+    /// Whether a load can have the ordering, which does not write and so cannot release.
+    pub fn supports_load(self) -> bool {
+        !matches!(self, AtomicOrdering::Release | AtomicOrdering::AcqRel)
+    }
+
+    /// Whether a store can have the ordering, which does not read and so cannot acquire.
+    pub fn supports_store(self) -> bool {
+        !matches!(self, AtomicOrdering::Acquire | AtomicOrdering::AcqRel)
+    }
+
+    /// Whether a fence can have the ordering, which orders nothing if it is relaxed.
+    pub fn supports_fence(self) -> bool {
+        self != AtomicOrdering::Relaxed
+    }
+}
+
+/// Atomically loads a value of type `ty` from the address `ptr`, which must be aligned to the size of the type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtomicLoad<'s> {
+    pub ptr: Value<'s>,
+    pub ty: Type,
+    pub ordering: AtomicOrdering,
+}
+
+/// Atomically replaces the value at the address `ptr` with the result of `op` on it and `value`, producing the
+/// value it replaced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtomicRmw<'s> {
+    pub ptr: Value<'s>,
+    pub value: Value<'s>,
+    pub op: AtomicRmwOp,
+    pub ordering: AtomicOrdering,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AtomicRmwOp {
+    Add,
+    Sub,
+    And,
+    Or,
+    Xor,
+    /// Replaces the value with `value`.
+    Xchg,
+    Min,
+    Max,
+}
+
+impl AtomicRmwOp {
+    /// Whether the operation is defined for values of type `ty`.
+    pub fn supports(self, ty: Type) -> bool {
+        match self {
+            AtomicRmwOp::Xchg => ty.is_atomic(),
+            _ => ty.is_atomic() && ty.get_signage().is_some(),
+        }
+    }
+}
+
+/// Atomically replaces the value at the address `ptr` with `replacement` if it is equal to `expected`, producing the
+/// value that was there, which is `expected` if and only if it was replaced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CmpXchg<'s> {
+    pub ptr: Value<'s>,
+    pub expected: Value<'s>,
+    pub replacement: Value<'s>,
+    /// The ordering of the operation when it replaces the value, while a failed comparison is only a load with the
+    /// strongest ordering a load supports, which is at most this one.
+    pub ordering: AtomicOrdering,
+}
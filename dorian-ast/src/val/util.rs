@@ -1,5 +1,12 @@
 use std::borrow::Cow;
-use crate::val::{Arg, Bin, BinOp, Call, Lit, Una, UnaOp, Value, Var};
+use crate::function::Signature;
+use crate::span::{Span, Spanned};
+use crate::ty::Type;
+use crate::val::{
+    Arg, AtomicLoad, AtomicOrdering, AtomicRmw, AtomicRmwOp, Bin, BinOp, Call, CallIndirect, Checked, CheckedOp,
+    CmpXchg, ExtractElement, Float, FloatCmp, FloatCmpOp, FuncAddr, InsertElement, Int, Intrinsic, IntrinsicOp, Lit,
+    OnOverflow, Reduce, ReduceOp, Shuffle, Splat, Una, UnaOp, Value, Var, Vector,
+};
 
 macro_rules! bin_op {
     ($($function:ident => $variant:ident),+ $(,)?) => {
@@ -37,6 +44,135 @@ bin_op! {
     ge => Ge,
 }
 
+macro_rules! checked_op {
+    ($($function:ident => $variant:ident, $on_overflow:ident),+ $(,)?) => {
+        $(
+        pub fn $function<'s, T: From<Checked<'s>>>(lhs: Value<'s>, rhs: Value<'s>) -> T {
+                T::from(Checked {
+                    lhs,
+                    rhs,
+                    op: CheckedOp::$variant,
+                    on_overflow: OnOverflow::$on_overflow,
+                })
+            }
+        )+
+    };
+}
+
+checked_op! {
+    checked_add => Add, Flag,
+    checked_sub => Sub, Flag,
+    checked_mul => Mul, Flag,
+    trapping_add => Add, Trap,
+    trapping_sub => Sub, Trap,
+    trapping_mul => Mul, Trap,
+}
+
+macro_rules! float_cmp_op {
+    ($($function:ident => $variant:ident),+ $(,)?) => {
+        $(
+        pub fn $function<'s, T: From<FloatCmp<'s>>>(lhs: Value<'s>, rhs: Value<'s>) -> T {
+                T::from(FloatCmp {
+                    lhs,
+                    rhs,
+                    op: FloatCmpOp::$variant,
+                })
+            }
+        )+
+    };
+}
+
+float_cmp_op! {
+    ordered => Ordered,
+    unordered => Unordered,
+    ordered_eq => OrderedEq,
+    ordered_ne => OrderedNe,
+    ordered_lt => OrderedLt,
+    ordered_gt => OrderedGt,
+    ordered_le => OrderedLe,
+    ordered_ge => OrderedGe,
+    unordered_eq => UnorderedEq,
+    unordered_ne => UnorderedNe,
+    unordered_lt => UnorderedLt,
+    unordered_gt => UnorderedGt,
+    unordered_le => UnorderedLe,
+    unordered_ge => UnorderedGe,
+}
+
+macro_rules! intrinsic_op {
+    ($($function:ident($($arg:ident),+) => $variant:ident),+ $(,)?) => {
+        $(
+        pub fn $function<'s, T: From<Intrinsic<'s>>>($($arg: Value<'s>),+) -> T {
+                T::from(Intrinsic {
+                    op: IntrinsicOp::$variant,
+                    args: vec![$($arg),+],
+                })
+            }
+        )+
+    };
+}
+
+intrinsic_op! {
+    sqrt(value) => Sqrt,
+    fma(a, b, c) => Fma,
+    floor(value) => Floor,
+    ceil(value) => Ceil,
+    trunc(value) => Trunc,
+    copysign(magnitude, sign) => CopySign,
+    abs(value) => Abs,
+    min(lhs, rhs) => Min,
+    max(lhs, rhs) => Max,
+    ctpop(value) => CtPop,
+    clz(value) => Clz,
+    ctz(value) => Ctz,
+    bswap(value) => BSwap,
+}
+
+pub fn splat<'s, T: From<Splat<'s>>>(value: Value<'s>, len: u32) -> T {
+    T::from(Splat { value, len })
+}
+
+pub fn vector<'s, T: From<Vector<'s>>>(elems: impl Into<Vec<Value<'s>>>) -> T {
+    T::from(Vector {
+        elems: elems.into(),
+    })
+}
+
+pub fn extract_element<'s, T: From<ExtractElement<'s>>>(vector: Value<'s>, index: u32) -> T {
+    T::from(ExtractElement { vector, index })
+}
+
+pub fn insert_element<'s, T: From<InsertElement<'s>>>(vector: Value<'s>, value: Value<'s>, index: u32) -> T {
+    T::from(InsertElement { vector, value, index })
+}
+
+pub fn shuffle<'s, T: From<Shuffle<'s>>>(lhs: Value<'s>, rhs: Value<'s>, mask: impl Into<Vec<u32>>) -> T {
+    T::from(Shuffle {
+        lhs,
+        rhs,
+        mask: mask.into(),
+    })
+}
+
+macro_rules! reduce_op {
+    ($($function:ident => $variant:ident),+ $(,)?) => {
+        $(
+        pub fn $function<'s, T: From<Reduce<'s>>>(vector: Value<'s>) -> T {
+                T::from(Reduce {
+                    vector,
+                    op: ReduceOp::$variant,
+                })
+            }
+        )+
+    };
+}
+
+reduce_op! {
+    reduce_sum => Sum,
+    reduce_min => Min,
+    reduce_max => Max,
+}
+
 pub fn neg<'s, T: From<Una<'s>>>(value: Value<'s>) -> T {
     T::from(Una {
         operand: value,
@@ -53,6 +189,14 @@ pub fn not<'s, T: From<Una<'s>>>(value: Value<'s>) -> T {
     })
 }
 
+pub fn is_nan<'s, T: From<Una<'s>>>(value: Value<'s>) -> T {
+    T::from(Una {
+        operand: value,
+        op: UnaOp::IsNan,
+        no_wrap: false,
+    })
+}
+
 pub fn arg<T: From<Arg>>(param_index: u32) -> T {
     T::from(Arg { param_index })
 }
@@ -67,9 +211,84 @@ pub fn lit<T: Into<Lit>, U: From<Lit>>(value: T) -> U {
     U::from(value.into())
 }
 
+/// An integer literal whose type is inferred from the other operand, the variable it is assigned to, or the parameter
+/// or output it is passed as.
+pub fn lit_int<T: From<Lit>>(value: i128) -> T {
+    T::from(Int::Untyped(value).into())
+}
+
+/// A float literal whose type is inferred like that of [`lit_int`].
+pub fn lit_float<T: From<Lit>>(value: f64) -> T {
+    T::from(Float::Untyped(value).into())
+}
+
 pub fn call<'s, T: From<Call<'s>>>(function_name: impl Into<Cow<'s, str>>, args: impl Into<Vec<Value<'s>>>) -> T {
     T::from(Call {
         function_name: function_name.into(),
         args: args.into(),
     })
 }
+
+pub fn func_addr<'s, T: From<FuncAddr<'s>>>(function_name: impl Into<Cow<'s, str>>) -> T {
+    T::from(FuncAddr { function_name: function_name.into() })
+}
+
+pub fn call_indirect<'s, T: From<CallIndirect<'s>>>(
+    callee: Value<'s>,
+    signature: Signature,
+    args: impl Into<Vec<Value<'s>>>,
+) -> T {
+    T::from(CallIndirect {
+        callee,
+        signature,
+        args: args.into(),
+    })
+}
+
+pub fn atomic_load<'s, T: From<AtomicLoad<'s>>>(ptr: Value<'s>, ty: Type, ordering: AtomicOrdering) -> T {
+    T::from(AtomicLoad { ptr, ty, ordering })
+}
+
+macro_rules! atomic_rmw_op {
+    ($($function:ident => $variant:ident),+ $(,)?) => {
+        $(
+        pub fn $function<'s, T: From<AtomicRmw<'s>>>(ptr: Value<'s>, value: Value<'s>, ordering: AtomicOrdering) -> T {
+                T::from(AtomicRmw {
+                    ptr,
+                    value,
+                    op: AtomicRmwOp::$variant,
+                    ordering,
+                })
+            }
+        )+
+    };
+}
+
+atomic_rmw_op! {
+    atomic_add => Add,
+    atomic_sub => Sub,
+    atomic_and => And,
+    atomic_or => Or,
+    atomic_xor => Xor,
+    atomic_xchg => Xchg,
+    atomic_min => Min,
+    atomic_max => Max,
+}
+
+pub fn cmpxchg<'s, T: From<CmpXchg<'s>>>(
+    ptr: Value<'s>,
+    expected: Value<'s>,
+    replacement: Value<'s>,
+    ordering: AtomicOrdering,
+) -> T {
+    T::from(CmpXchg {
+        ptr,
+        expected,
+        replacement,
+        ordering,
+    })
+}
+
+pub fn spanned<'s, T: From<Spanned<'s, Value<'s>>>>(span: Span<'s>, value: Value<'s>) -> T {
+    T::from(Spanned::new(span, value))
+}
@@ -0,0 +1,594 @@
+//! Traversal of a borrowed AST.
+//!
+//! Every method of [`Visit`] defaults to the `walk_*` function of the same node, which visits its children. An
+//! implementation only overrides the nodes it cares about, and calls the `walk_*` function itself to keep descending.
+
+/// Defines a visitor trait and its `walk_*` functions, which take nodes by `&'a` reference for [`Visit`], or by `&mut`
+/// reference with names suffixed with `_mut` for [`VisitMut`](crate::visit_mut::VisitMut). Both are generated from
+/// this one definition so that they always traverse the same nodes.
+macro_rules! visitor {
+    ($Visit:ident $(<$a:lifetime>)? $(, $suffix:ident, $mut:tt)?) => {
+        use crate::block::Block;
+        use crate::block::stmt::{
+            AssertStmt, AssignStmt, AtomicStoreStmt, BindStmt, BindTupleStmt, Handler, IfElse, IfStmt, MemCopyStmt,
+            MemSetStmt, ReturnStmt, Stmt, TryStmt, WhileStmt,
+        };
+        use crate::function::Function;
+        use crate::global::Global;
+        use crate::module::Module;
+        use crate::span::Span;
+        use crate::structure::Struct;
+        use crate::val::{
+            Arg, AtomicLoad, AtomicRmw, Bin, Call, CallIndirect, Checked, CmpXchg, ContextValue, Expr, ExtractElement,
+            FloatCmp, FuncAddr, InsertElement, Intrinsic, Lit, Reduce, Shuffle, Splat, Una, Value, Var, Vector,
+        };
+
+        paste::paste! {
+            pub trait $Visit $(<$a>)? {
+                fn [<visit_module $($suffix)?>](&mut self, module: & $($a)? $($mut)? Module) {
+                    [<walk_module $($suffix)?>](self, module);
+                }
+
+                fn [<visit_struct $($suffix)?>](&mut self, _: & $($a)? $($mut)? Struct) {}
+
+                fn [<visit_global $($suffix)?>](&mut self, global: & $($a)? $($mut)? Global) {
+                    [<walk_global $($suffix)?>](self, global);
+                }
+
+                fn [<visit_function $($suffix)?>](&mut self, function: & $($a)? $($mut)? Function) {
+                    [<walk_function $($suffix)?>](self, function);
+                }
+
+                fn [<visit_span $($suffix)?>](&mut self, _: & $($a)? $($mut)? Span) {}
+
+                fn [<visit_block $($suffix)?>](&mut self, block: & $($a)? $($mut)? Block) {
+                    [<walk_block $($suffix)?>](self, block);
+                }
+
+                fn [<visit_stmt $($suffix)?>](&mut self, stmt: & $($a)? $($mut)? Stmt) {
+                    [<walk_stmt $($suffix)?>](self, stmt);
+                }
+
+                fn [<visit_if_stmt $($suffix)?>](&mut self, stmt: & $($a)? $($mut)? IfStmt) {
+                    [<walk_if_stmt $($suffix)?>](self, stmt);
+                }
+
+                fn [<visit_while_stmt $($suffix)?>](&mut self, stmt: & $($a)? $($mut)? WhileStmt) {
+                    [<walk_while_stmt $($suffix)?>](self, stmt);
+                }
+
+                fn [<visit_return_stmt $($suffix)?>](&mut self, stmt: & $($a)? $($mut)? ReturnStmt) {
+                    [<walk_return_stmt $($suffix)?>](self, stmt);
+                }
+
+                fn [<visit_bind_stmt $($suffix)?>](&mut self, stmt: & $($a)? $($mut)? BindStmt) {
+                    [<walk_bind_stmt $($suffix)?>](self, stmt);
+                }
+
+                fn [<visit_bind_tuple_stmt $($suffix)?>](&mut self, stmt: & $($a)? $($mut)? BindTupleStmt) {
+                    [<walk_bind_tuple_stmt $($suffix)?>](self, stmt);
+                }
+
+                fn [<visit_assign_stmt $($suffix)?>](&mut self, stmt: & $($a)? $($mut)? AssignStmt) {
+                    [<walk_assign_stmt $($suffix)?>](self, stmt);
+                }
+
+                fn [<visit_assert_stmt $($suffix)?>](&mut self, stmt: & $($a)? $($mut)? AssertStmt) {
+                    [<walk_assert_stmt $($suffix)?>](self, stmt);
+                }
+
+                fn [<visit_atomic_store_stmt $($suffix)?>](&mut self, stmt: & $($a)? $($mut)? AtomicStoreStmt) {
+                    [<walk_atomic_store_stmt $($suffix)?>](self, stmt);
+                }
+
+                /// Visits both [`Stmt::MemCopy`] and [`Stmt::MemMove`].
+                fn [<visit_mem_copy_stmt $($suffix)?>](&mut self, stmt: & $($a)? $($mut)? MemCopyStmt) {
+                    [<walk_mem_copy_stmt $($suffix)?>](self, stmt);
+                }
+
+                fn [<visit_mem_set_stmt $($suffix)?>](&mut self, stmt: & $($a)? $($mut)? MemSetStmt) {
+                    [<walk_mem_set_stmt $($suffix)?>](self, stmt);
+                }
+
+                fn [<visit_try_stmt $($suffix)?>](&mut self, stmt: & $($a)? $($mut)? TryStmt) {
+                    [<walk_try_stmt $($suffix)?>](self, stmt);
+                }
+
+                fn [<visit_value $($suffix)?>](&mut self, value: & $($a)? $($mut)? Value) {
+                    [<walk_value $($suffix)?>](self, value);
+                }
+
+                fn [<visit_arg $($suffix)?>](&mut self, _: & $($a)? $($mut)? Arg) {}
+
+                fn [<visit_var $($suffix)?>](&mut self, _: & $($a)? $($mut)? Var) {}
+
+                fn [<visit_expr $($suffix)?>](&mut self, expr: & $($a)? $($mut)? Expr) {
+                    [<walk_expr $($suffix)?>](self, expr);
+                }
+
+                fn [<visit_bin $($suffix)?>](&mut self, bin: & $($a)? $($mut)? Bin) {
+                    [<walk_bin $($suffix)?>](self, bin);
+                }
+
+                fn [<visit_una $($suffix)?>](&mut self, una: & $($a)? $($mut)? Una) {
+                    [<walk_una $($suffix)?>](self, una);
+                }
+
+                fn [<visit_checked $($suffix)?>](&mut self, checked: & $($a)? $($mut)? Checked) {
+                    [<walk_checked $($suffix)?>](self, checked);
+                }
+
+                fn [<visit_float_cmp $($suffix)?>](&mut self, float_cmp: & $($a)? $($mut)? FloatCmp) {
+                    [<walk_float_cmp $($suffix)?>](self, float_cmp);
+                }
+
+                fn [<visit_splat $($suffix)?>](&mut self, splat: & $($a)? $($mut)? Splat) {
+                    [<walk_splat $($suffix)?>](self, splat);
+                }
+
+                fn [<visit_vector $($suffix)?>](&mut self, vector: & $($a)? $($mut)? Vector) {
+                    [<walk_vector $($suffix)?>](self, vector);
+                }
+
+                fn [<visit_extract_element $($suffix)?>](&mut self, extract: & $($a)? $($mut)? ExtractElement) {
+                    [<walk_extract_element $($suffix)?>](self, extract);
+                }
+
+                fn [<visit_insert_element $($suffix)?>](&mut self, insert: & $($a)? $($mut)? InsertElement) {
+                    [<walk_insert_element $($suffix)?>](self, insert);
+                }
+
+                fn [<visit_shuffle $($suffix)?>](&mut self, shuffle: & $($a)? $($mut)? Shuffle) {
+                    [<walk_shuffle $($suffix)?>](self, shuffle);
+                }
+
+                fn [<visit_reduce $($suffix)?>](&mut self, reduce: & $($a)? $($mut)? Reduce) {
+                    [<walk_reduce $($suffix)?>](self, reduce);
+                }
+
+                fn [<visit_lit $($suffix)?>](&mut self, _: & $($a)? $($mut)? Lit) {}
+
+                fn [<visit_call $($suffix)?>](&mut self, call: & $($a)? $($mut)? Call) {
+                    [<walk_call $($suffix)?>](self, call);
+                }
+
+                fn [<visit_func_addr $($suffix)?>](&mut self, _: & $($a)? $($mut)? FuncAddr) {}
+
+                fn [<visit_call_indirect $($suffix)?>](&mut self, call: & $($a)? $($mut)? CallIndirect) {
+                    [<walk_call_indirect $($suffix)?>](self, call);
+                }
+
+                fn [<visit_intrinsic $($suffix)?>](&mut self, intrinsic: & $($a)? $($mut)? Intrinsic) {
+                    [<walk_intrinsic $($suffix)?>](self, intrinsic);
+                }
+
+                fn [<visit_atomic_load $($suffix)?>](&mut self, load: & $($a)? $($mut)? AtomicLoad) {
+                    [<walk_atomic_load $($suffix)?>](self, load);
+                }
+
+                fn [<visit_atomic_rmw $($suffix)?>](&mut self, rmw: & $($a)? $($mut)? AtomicRmw) {
+                    [<walk_atomic_rmw $($suffix)?>](self, rmw);
+                }
+
+                fn [<visit_cmpxchg $($suffix)?>](&mut self, cmpxchg: & $($a)? $($mut)? CmpXchg) {
+                    [<walk_cmpxchg $($suffix)?>](self, cmpxchg);
+                }
+            }
+
+            pub fn [<walk_module $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                module: & $($a)? $($mut)? Module,
+            ) {
+                for ast_struct in & $($mut)? module.structs {
+                    visitor.[<visit_struct $($suffix)?>](ast_struct);
+                }
+
+                for global in & $($mut)? module.globals {
+                    visitor.[<visit_global $($suffix)?>](global);
+                }
+
+                for function in & $($mut)? module.functions {
+                    visitor.[<visit_function $($suffix)?>](function);
+                }
+            }
+
+            pub fn [<walk_global $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                global: & $($a)? $($mut)? Global,
+            ) {
+                if let Some(value) = & $($mut)? global.value {
+                    visitor.[<visit_value $($suffix)?>](value);
+                }
+            }
+
+            pub fn [<walk_function $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                function: & $($a)? $($mut)? Function,
+            ) {
+                if let Some(span) = & $($mut)? function.span {
+                    visitor.[<visit_span $($suffix)?>](span);
+                }
+
+                visitor.[<visit_block $($suffix)?>](& $($mut)? function.body);
+            }
+
+            pub fn [<walk_block $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                block: & $($a)? $($mut)? Block,
+            ) {
+                for stmt in & $($mut)? block.stmts {
+                    visitor.[<visit_stmt $($suffix)?>](stmt);
+                }
+            }
+
+            pub fn [<walk_stmt $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                stmt: & $($a)? $($mut)? Stmt,
+            ) {
+                match stmt {
+                    Stmt::If(x) => visitor.[<visit_if_stmt $($suffix)?>](x),
+                    Stmt::While(x) => visitor.[<visit_while_stmt $($suffix)?>](x),
+                    Stmt::Return(x) => visitor.[<visit_return_stmt $($suffix)?>](x),
+                    Stmt::Bind(x) => visitor.[<visit_bind_stmt $($suffix)?>](x),
+                    Stmt::BindTuple(x) => visitor.[<visit_bind_tuple_stmt $($suffix)?>](x),
+                    Stmt::Assign(x) => visitor.[<visit_assign_stmt $($suffix)?>](x),
+                    Stmt::Trap(_) | Stmt::Unreachable | Stmt::Fence(_) => {}
+                    Stmt::Assert(x) => visitor.[<visit_assert_stmt $($suffix)?>](x),
+                    Stmt::AtomicStore(x) => visitor.[<visit_atomic_store_stmt $($suffix)?>](x),
+                    Stmt::MemCopy(x) | Stmt::MemMove(x) => visitor.[<visit_mem_copy_stmt $($suffix)?>](x),
+                    Stmt::MemSet(x) => visitor.[<visit_mem_set_stmt $($suffix)?>](x),
+                    Stmt::Call(x) | Stmt::TailCall(x) => visitor.[<visit_call $($suffix)?>](x),
+                    Stmt::CallIndirect(x) | Stmt::TailCallIndirect(x) => visitor.[<visit_call_indirect $($suffix)?>](x),
+                    Stmt::Try(x) => visitor.[<visit_try_stmt $($suffix)?>](x),
+                    Stmt::Spanned(x) => {
+                        visitor.[<visit_span $($suffix)?>](& $($mut)? x.span);
+                        visitor.[<visit_stmt $($suffix)?>](& $($mut)? x.node);
+                    }
+                }
+            }
+
+            pub fn [<walk_if_stmt $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                stmt: & $($a)? $($mut)? IfStmt,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? stmt.condition);
+                visitor.[<visit_block $($suffix)?>](& $($mut)? stmt.then_block);
+
+                match & $($mut)? stmt.if_else {
+                    Some(IfElse::If(x)) => visitor.[<visit_if_stmt $($suffix)?>](x),
+                    Some(IfElse::Else(x)) => visitor.[<visit_block $($suffix)?>](x),
+                    None => {}
+                }
+            }
+
+            pub fn [<walk_while_stmt $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                stmt: & $($a)? $($mut)? WhileStmt,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? stmt.condition);
+                visitor.[<visit_block $($suffix)?>](& $($mut)? stmt.loop_block);
+            }
+
+            pub fn [<walk_return_stmt $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                stmt: & $($a)? $($mut)? ReturnStmt,
+            ) {
+                for value in & $($mut)? stmt.values {
+                    visitor.[<visit_value $($suffix)?>](value);
+                }
+            }
+
+            pub fn [<walk_bind_stmt $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                stmt: & $($a)? $($mut)? BindStmt,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? stmt.value);
+            }
+
+            pub fn [<walk_bind_tuple_stmt $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                stmt: & $($a)? $($mut)? BindTupleStmt,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? stmt.value);
+            }
+
+            pub fn [<walk_assign_stmt $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                stmt: & $($a)? $($mut)? AssignStmt,
+            ) {
+                visitor.[<visit_var $($suffix)?>](& $($mut)? stmt.var);
+                visitor.[<visit_value $($suffix)?>](& $($mut)? stmt.value);
+            }
+
+            pub fn [<walk_assert_stmt $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                stmt: & $($a)? $($mut)? AssertStmt,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? stmt.condition);
+            }
+
+            pub fn [<walk_atomic_store_stmt $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                stmt: & $($a)? $($mut)? AtomicStoreStmt,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? stmt.ptr);
+                visitor.[<visit_value $($suffix)?>](& $($mut)? stmt.value);
+            }
+
+            pub fn [<walk_mem_copy_stmt $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                stmt: & $($a)? $($mut)? MemCopyStmt,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? stmt.dest);
+                visitor.[<visit_value $($suffix)?>](& $($mut)? stmt.src);
+                visitor.[<visit_value $($suffix)?>](& $($mut)? stmt.len);
+            }
+
+            pub fn [<walk_mem_set_stmt $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                stmt: & $($a)? $($mut)? MemSetStmt,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? stmt.dest);
+                visitor.[<visit_value $($suffix)?>](& $($mut)? stmt.value);
+                visitor.[<visit_value $($suffix)?>](& $($mut)? stmt.len);
+            }
+
+            pub fn [<walk_try_stmt $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                stmt: & $($a)? $($mut)? TryStmt,
+            ) {
+                visitor.[<visit_block $($suffix)?>](& $($mut)? stmt.body);
+
+                match & $($mut)? stmt.handler {
+                    Handler::Cleanup(x) => visitor.[<visit_block $($suffix)?>](x),
+                    Handler::Catch { block, .. } => visitor.[<visit_block $($suffix)?>](block),
+                }
+            }
+
+            pub fn [<walk_value $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                value: & $($a)? $($mut)? Value,
+            ) {
+                match value {
+                    Value::Context(ContextValue::Arg(x)) => visitor.[<visit_arg $($suffix)?>](x),
+                    Value::Context(ContextValue::Var(x)) => visitor.[<visit_var $($suffix)?>](x),
+                    Value::Expr(x) => visitor.[<visit_expr $($suffix)?>](x),
+                    Value::Lit(x) => visitor.[<visit_lit $($suffix)?>](x),
+                    Value::Call(x) => visitor.[<visit_call $($suffix)?>](x),
+                    Value::FuncAddr(x) => visitor.[<visit_func_addr $($suffix)?>](x),
+                    Value::CallIndirect(x) => visitor.[<visit_call_indirect $($suffix)?>](x),
+                    Value::Intrinsic(x) => visitor.[<visit_intrinsic $($suffix)?>](x),
+                    Value::AtomicLoad(x) => visitor.[<visit_atomic_load $($suffix)?>](x),
+                    Value::AtomicRmw(x) => visitor.[<visit_atomic_rmw $($suffix)?>](x),
+                    Value::CmpXchg(x) => visitor.[<visit_cmpxchg $($suffix)?>](x),
+                    Value::Spanned(x) => {
+                        visitor.[<visit_span $($suffix)?>](& $($mut)? x.span);
+                        visitor.[<visit_value $($suffix)?>](& $($mut)? x.node);
+                    }
+                }
+            }
+
+            pub fn [<walk_expr $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                expr: & $($a)? $($mut)? Expr,
+            ) {
+                match expr {
+                    Expr::Bin(x) => visitor.[<visit_bin $($suffix)?>](x),
+                    Expr::Una(x) => visitor.[<visit_una $($suffix)?>](x),
+                    Expr::Checked(x) => visitor.[<visit_checked $($suffix)?>](x),
+                    Expr::FloatCmp(x) => visitor.[<visit_float_cmp $($suffix)?>](x),
+                    Expr::Splat(x) => visitor.[<visit_splat $($suffix)?>](x),
+                    Expr::Vector(x) => visitor.[<visit_vector $($suffix)?>](x),
+                    Expr::ExtractElement(x) => visitor.[<visit_extract_element $($suffix)?>](x),
+                    Expr::InsertElement(x) => visitor.[<visit_insert_element $($suffix)?>](x),
+                    Expr::Shuffle(x) => visitor.[<visit_shuffle $($suffix)?>](x),
+                    Expr::Reduce(x) => visitor.[<visit_reduce $($suffix)?>](x),
+                }
+            }
+
+            pub fn [<walk_bin $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                bin: & $($a)? $($mut)? Bin,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? bin.lhs);
+                visitor.[<visit_value $($suffix)?>](& $($mut)? bin.rhs);
+            }
+
+            pub fn [<walk_una $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                una: & $($a)? $($mut)? Una,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? una.operand);
+            }
+
+            pub fn [<walk_checked $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                checked: & $($a)? $($mut)? Checked,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? checked.lhs);
+                visitor.[<visit_value $($suffix)?>](& $($mut)? checked.rhs);
+            }
+
+            pub fn [<walk_float_cmp $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                float_cmp: & $($a)? $($mut)? FloatCmp,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? float_cmp.lhs);
+                visitor.[<visit_value $($suffix)?>](& $($mut)? float_cmp.rhs);
+            }
+
+            pub fn [<walk_splat $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                splat: & $($a)? $($mut)? Splat,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? splat.value);
+            }
+
+            pub fn [<walk_vector $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                vector: & $($a)? $($mut)? Vector,
+            ) {
+                for elem in & $($mut)? vector.elems {
+                    visitor.[<visit_value $($suffix)?>](elem);
+                }
+            }
+
+            pub fn [<walk_extract_element $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                extract: & $($a)? $($mut)? ExtractElement,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? extract.vector);
+            }
+
+            pub fn [<walk_insert_element $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                insert: & $($a)? $($mut)? InsertElement,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? insert.vector);
+                visitor.[<visit_value $($suffix)?>](& $($mut)? insert.value);
+            }
+
+            pub fn [<walk_shuffle $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                shuffle: & $($a)? $($mut)? Shuffle,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? shuffle.lhs);
+                visitor.[<visit_value $($suffix)?>](& $($mut)? shuffle.rhs);
+            }
+
+            pub fn [<walk_reduce $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                reduce: & $($a)? $($mut)? Reduce,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? reduce.vector);
+            }
+
+            pub fn [<walk_call $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                call: & $($a)? $($mut)? Call,
+            ) {
+                for arg in & $($mut)? call.args {
+                    visitor.[<visit_value $($suffix)?>](arg);
+                }
+            }
+
+            pub fn [<walk_call_indirect $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                call: & $($a)? $($mut)? CallIndirect,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? call.callee);
+                for arg in & $($mut)? call.args {
+                    visitor.[<visit_value $($suffix)?>](arg);
+                }
+            }
+
+            pub fn [<walk_intrinsic $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                intrinsic: & $($a)? $($mut)? Intrinsic,
+            ) {
+                for arg in & $($mut)? intrinsic.args {
+                    visitor.[<visit_value $($suffix)?>](arg);
+                }
+            }
+
+            pub fn [<walk_atomic_load $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                load: & $($a)? $($mut)? AtomicLoad,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? load.ptr);
+            }
+
+            pub fn [<walk_atomic_rmw $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                rmw: & $($a)? $($mut)? AtomicRmw,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? rmw.ptr);
+                visitor.[<visit_value $($suffix)?>](& $($mut)? rmw.value);
+            }
+
+            pub fn [<walk_cmpxchg $($suffix)?>]<$($a,)? V: $Visit $(<$a>)? + ?Sized>(
+                visitor: &mut V,
+                cmpxchg: & $($a)? $($mut)? CmpXchg,
+            ) {
+                visitor.[<visit_value $($suffix)?>](& $($mut)? cmpxchg.ptr);
+                visitor.[<visit_value $($suffix)?>](& $($mut)? cmpxchg.expected);
+                visitor.[<visit_value $($suffix)?>](& $($mut)? cmpxchg.replacement);
+            }
+        }
+    };
+}
+
+pub(crate) use visitor;
+
+visitor!(Visit<'a>);
+
+#[cfg(test)]
+mod tests {
+    use super::{walk_call, Visit};
+    use crate::function::Function;
+    use crate::span::Span;
+    use crate::val::util::*;
+    use crate::val::{Call, Var};
+    use crate::visit_mut::VisitMut;
+
+    /// Records the variables and calls it visits, in order.
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl<'a> Visit<'a> for Recorder {
+        fn visit_var(&mut self, var: &'a Var) {
+            self.0.push(var.name.to_string());
+        }
+
+        fn visit_call(&mut self, call: &'a Call) {
+            self.0.push(format!("{}()", call.function_name));
+            walk_call(self, call);
+        }
+    }
+
+    struct Uppercase;
+
+    impl VisitMut for Uppercase {
+        fn visit_var_mut(&mut self, var: &mut Var) {
+            var.name = var.name.to_uppercase().into();
+        }
+    }
+
+    fn function() -> Function<'static> {
+        Function::new("f").build_block(|scope| {
+            scope.bind("a", call("g", vec![var("b")]));
+            scope.if_then(var("c"), |scope| {
+                scope.bind("x", call("h", vec![var("d")]));
+            });
+            scope.loop_while(var("e"), |scope| {
+                scope.assign(var("f"), add(var("g"), var("h")));
+            });
+            scope.spanned(Span::new("f.dn", 1, 1), |scope| {
+                scope.ret([var("i")]);
+            });
+        })
+    }
+
+    fn record(function: &Function) -> Vec<String> {
+        let mut recorder = Recorder::default();
+        recorder.visit_function(function);
+        recorder.0
+    }
+
+    #[test]
+    fn visitors_reach_every_node_in_order() {
+        assert_eq!(record(&function()), vec![
+            "g()", "b", "c", "h()", "d", "e", "f", "g", "h", "i",
+        ]);
+    }
+
+    #[test]
+    fn mutable_visitors_reach_the_same_nodes() {
+        let mut function = function();
+        Uppercase.visit_function_mut(&mut function);
+
+        assert_eq!(record(&function), vec![
+            "g()", "B", "C", "h()", "D", "E", "F", "G", "H", "I",
+        ]);
+    }
+}
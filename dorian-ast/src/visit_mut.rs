@@ -0,0 +1,5 @@
+//! Traversal of a mutably borrowed AST, for passes that rewrite nodes in place.
+//!
+//! This mirrors [`Visit`](crate::visit::Visit), with every method and `walk_*` function suffixed with `_mut`.
+
+crate::visit::visitor!(VisitMut, _mut, mut);
@@ -1,8 +1,13 @@
 pub use cranelift::{
     codegen::{
+        binemit::Reloc,
         ir::{
             types::*,
             AbiParam,
+            AtomicRmwOp,
+            ConstantData,
+            Endianness,
+            MemFlags,
             Value as Scalar,
             Inst,
             Function,
@@ -12,6 +17,7 @@ pub use cranelift::{
             ExtFuncData,
             ExternalName,
             Signature,
+            TrapCode,
             UserFuncName,
             condcodes::{
                 FloatCC as FloatCmpOp,
@@ -20,35 +26,38 @@ pub use cranelift::{
         },
         isa::{
             CallConv,
-        }
+        },
+        CodegenError,
     },
     frontend::{
         FunctionBuilder,
         FunctionBuilderContext,
+        Variable,
+    },
+    jit::{
+        JITBuilder as JitBuilder,
+        JITModule as Module,
     },
     module::{
-        ModuleDeclarations as Module,
+        default_libcall_names,
+        Module as ModuleTrait,
         Linkage,
         FuncId,
         FuncOrDataId,
+        ModuleError,
+        ModuleReloc,
+        ModuleRelocTarget,
     },
 };
 
+#[derive(Copy, Clone)]
 pub struct Value {
     pub raw: ValueItem,
     pub signage: Option<bool>,
 }
 
+#[derive(Copy, Clone)]
 pub enum ValueItem {
     Scalar(Scalar),
     Variable(Inst)
 }
-
-impl ValueItem {
-    pub(crate) fn to_scalar(&self) -> Option<Scalar> {
-        match self {
-            Self::Scalar(scalar) => Some(*scalar),
-            _ => None,
-        }
-    }
-}
\ No newline at end of file
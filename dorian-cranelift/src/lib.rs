@@ -3,30 +3,87 @@ mod ty;
 mod cl;
 mod scope;
 
-use ast::backend::Backend;
-use ast::function::Function;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use ast::backend::{Backend, CompileError};
+use ast::block::stmt::{TrapCode, TryStmt};
+use ast::function::{Function, Linkage};
 use ast::global::Global;
 use ast::module::Module;
+use ast::infer;
+use ast::resolve::SymbolTable;
 use ast::structure::Struct;
+use ast::visit::Visit;
 use target_lexicon::Triple;
+use crate::cl::ModuleTrait;
+
+unsafe extern "C" {
+    fn fmod(x: f64, y: f64) -> f64;
+    fn fmodf(x: f32, y: f32) -> f32;
+}
 
 pub struct Cranelift {
     module: cl::Module,
     triple: Triple,
     context: cl::FunctionBuilderContext,
     function: Option<cl::Function>,
-    namespace: u32,
+    trampolines: HashMap<(cl::FuncId, u8), cl::FuncId>,
+    // Trap sites by function and offset until the function is finalized, and then by address
+    pending_traps: Vec<(cl::FuncId, u32, TrapCode)>,
+    traps: HashMap<usize, TrapCode>,
+    // The sizes of the compiled functions until they are finalized, and then the addresses of their code
+    pending_code: Vec<(cl::FuncId, usize)>,
+    code: Vec<Range<usize>>,
 }
 
 impl Cranelift {
     pub fn new() -> Self {
         Self {
-            module: cl::Module::default(),
+            module: Self::create_module(),
             triple: Triple::host(),
             context: cl::FunctionBuilderContext::new(),
             function: None,
-            namespace: 0,
+            trampolines: HashMap::new(),
+            pending_traps: Vec::new(),
+            traps: HashMap::new(),
+            pending_code: Vec::new(),
+            code: Vec::new(),
+        }
+    }
+
+    fn create_module() -> cl::Module {
+        // Tail calls in the tail calling convention rely on frame pointers
+        let flags = [("preserve_frame_pointers", "true")];
+        let mut builder = cl::JitBuilder::with_flags(&flags, cl::default_libcall_names())
+            .expect("Failed to create a JIT builder for the host machine");
+
+        // Float remainders call libm, which is only loaded into the process if something links against it
+        builder.symbol("fmod", fmod as *const u8);
+        builder.symbol("fmodf", fmodf as *const u8);
+
+        cl::Module::new(builder)
+    }
+
+    /// Checks that this backend can compile every feature that `ast_modules` use, which
+    /// [`compile_modules`](Backend::compile_modules) reports as [`CompileError::Unsupported`] otherwise.
+    pub fn check_supported(ast_modules: &[Module]) -> Result<(), Unsupported> {
+        #[derive(Default)]
+        struct Finder(Option<Unsupported>);
+
+        impl Visit<'_> for Finder {
+            fn visit_try_stmt(&mut self, _: &TryStmt) {
+                self.0.get_or_insert(Unsupported::TryStmt);
+            }
+        }
+
+        let mut finder = Finder::default();
+        for ast_module in ast_modules {
+            finder.visit_module(ast_module);
         }
+
+        finder.0.map_or(Ok(()), Err)
     }
 
     fn compile_struct<'ctx>(&'ctx self, _: &Struct) {
@@ -37,32 +94,167 @@ impl Cranelift {
         todo!("Globals are not implemented in the Cranelift backend yet");
     }
 
-    fn compile_function<'ctx>(&'ctx mut self, ast_function: &Function) {
+    fn declare_function(&mut self, ast_function: &Function, linkage: Linkage) -> cl::FuncId {
+        if ast_function.signature.variadic && linkage != Linkage::Import {
+            panic!("Variadic function '{}' must be imported", ast_function.name);
+        }
+
         let signature = self.compile_signature(&ast_function.signature);
-        let (func_id, _) = self.module.declare_function(&ast_function.name, cl::Linkage::Export, &signature).unwrap();
-        
-        let mut scope = self.create_scope(func_id, signature);
-        scope.compile_body(&ast_function.body);
+        let linkage = match linkage {
+            Linkage::Export => cl::Linkage::Export,
+            // Internal functions are only referred to by id, so that modules compiled together can reuse their names
+            Linkage::Internal => {
+                return self.module
+                    .declare_anonymous_function(&signature)
+                    .expect("Failed to declare function");
+            }
+            Linkage::Weak => cl::Linkage::Preemptible,
+            Linkage::Import => cl::Linkage::Import,
+        };
+
+        self.module
+            .declare_function(&ast_function.name, linkage, &signature)
+            .expect("Failed to declare function")
+    }
+
+    fn compile_function(
+        &mut self,
+        symbols: &SymbolTable,
+        func_ids: &[cl::FuncId],
+        ast_function: &Function,
+        func_id: cl::FuncId,
+    ) -> Result<(), CompileError> {
+        let signature = self.module.declarations().get_function_decl(func_id).signature.clone();
+
+        let mut scope = self.create_scope(symbols, func_ids, func_id, signature);
+        scope.compile_body(ast_function);
         scope.finish();
+
+        let mut context = self.module.make_context();
+        context.func = self.function.take().unwrap();
+        // Cranelift does not lower every instruction on every target, such as vectors that are not 128 bits wide
+        self.module.define_function(func_id, &mut context).map_err(|e| match e {
+            cl::ModuleError::Compilation(cl::CodegenError::Unsupported(message)) => {
+                let message = format!("Cranelift cannot compile function '{}': {}", ast_function.name, message);
+                CompileError::Unsupported(message)
+            }
+            e => panic!("Failed to define function '{}': {:?}", ast_function.name, e),
+        })?;
+
+        let compiled_code = context.compiled_code().unwrap();
+        self.pending_code.push((func_id, compiled_code.code_buffer().len()));
+        for trap in compiled_code.buffer.traps() {
+            let code = TrapCode::from_raw(trap.code.as_raw().get()).unwrap();
+            self.pending_traps.push((func_id, trap.offset, code));
+        }
+
+        Ok(())
+    }
+}
+
+/// A feature of the AST that [`Cranelift`] cannot compile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unsupported {
+    /// Try statements, as the JIT registers no unwind tables, so nothing can unwind through the compiled code.
+    TryStmt,
+}
+
+impl Display for Unsupported {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Unsupported::TryStmt => write!(f, "Cranelift does not support try statements"),
+        }
+    }
+}
+
+impl Error for Unsupported {}
+
+/// Functions compiled by [`Cranelift`] that have been finalized in its JIT module and are ready to be called.
+pub struct CraneliftModule<'ctx> {
+    module: &'ctx cl::Module,
+    traps: &'ctx HashMap<usize, TrapCode>,
+    code: &'ctx [Range<usize>],
+}
+
+impl CraneliftModule<'_> {
+    pub fn get_function_address(&self, name: &str) -> Option<*const u8> {
+        match self.module.get_name(name)? {
+            cl::FuncOrDataId::Func(x) => Some(self.module.get_finalized_function(x)),
+            cl::FuncOrDataId::Data(_) => None,
+        }
+    }
+
+    /// The code of the trap raised by the instruction at `address`, if it is a trap site of a compiled function.
+    pub fn trap_code(&self, address: *const u8) -> Option<TrapCode> {
+        self.traps.get(&(address as usize)).copied()
+    }
+
+    /// The addresses of the code of the compiled functions.
+    pub fn code(&self) -> &[Range<usize>] {
+        self.code
     }
 }
 
 impl Backend for Cranelift {
-    type CompiledModule<'ctx> = ();
+    type CompiledModule<'ctx> = CraneliftModule<'ctx>;
+
+    fn compile_modules<'ctx>(
+        &'ctx mut self,
+        ast_modules: &[Module],
+    ) -> Result<Self::CompiledModule<'ctx>, CompileError> {
+        // Every compilation gets its own JIT module, so that the names it exports do not clash with earlier ones. The
+        // previous module can be freed, as the module it was compiled into borrowed the backend mutably until now
+        let previous = std::mem::replace(&mut self.module, Self::create_module());
+        unsafe { previous.free_memory() };
+        self.trampolines.clear();
+        self.pending_traps.clear();
+        self.traps.clear();
+        self.pending_code.clear();
+        self.code.clear();
+
+        Cranelift::check_supported(ast_modules).map_err(|e| CompileError::Unsupported(e.to_string()))?;
 
-    fn compile_module<'ctx>(&'ctx mut self, ast_module: &Module) -> Self::CompiledModule<'ctx> {
-        for ast_struct in &ast_module.structs {
-            self.compile_struct(ast_struct);
+        let ast_modules = ast_modules
+            .iter()
+            .map(infer::type_literals)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(CompileError::Resolve)?;
+        let symbols = SymbolTable::resolve_linked(ast_modules.iter().map(AsRef::as_ref))
+            .map_err(CompileError::Resolve)?;
+
+        for symbols in &symbols {
+            for ast_struct in &symbols.module().structs {
+                self.compile_struct(ast_struct);
+            }
+
+            for ast_global in &symbols.module().globals {
+                self.compile_global(ast_global);
+            }
         }
 
-        for ast_global in &ast_module.globals {
-            self.compile_global(ast_global);
+        for symbols in &symbols {
+            // Indexed by `FunctionId`, where functions declared by name in several modules share the same id
+            let func_ids = symbols.functions()
+                .map(|(id, x)| self.declare_function(x, symbols.linkage(id)))
+                .collect::<Vec<_>>();
+
+            let defined = symbols.functions().filter(|(id, _)| symbols.linkage(*id) != Linkage::Import);
+            for (id, ast_function) in defined {
+                self.compile_function(symbols, &func_ids, ast_function, func_ids[id.0 as usize])?;
+            }
         }
 
-        for ast_function in &ast_module.functions {
-            self.compile_function(ast_function);
+        self.module.finalize_definitions().expect("Failed to finalize module definitions");
+
+        for (func_id, offset, code) in self.pending_traps.drain(..) {
+            let address = self.module.get_finalized_function(func_id) as usize + offset as usize;
+            self.traps.insert(address, code);
+        }
+        for (func_id, size) in self.pending_code.drain(..) {
+            let address = self.module.get_finalized_function(func_id) as usize;
+            self.code.push(address..address + size);
         }
-        
-        self.namespace += 1;
+
+        Ok(CraneliftModule { module: &self.module, traps: &self.traps, code: &self.code })
     }
 }
@@ -1,41 +1,333 @@
-use crate::cl::{FuncOrDataId, InstBuilder};
-use crate::{cl, Cranelift};
-use ast::block::stmt::Stmt;
+use crate::cl::{InstBuilder, ModuleTrait};
+use crate::{cl, ty, Cranelift};
+use ast::block::stmt::{
+    AssertStmt, AssignStmt, AtomicStoreStmt, BindStmt, BindTupleStmt, IfElse, IfStmt, MemCopyStmt, MemSetStmt,
+    ReturnStmt, Stmt, TrapCode, WhileStmt,
+};
 use ast::block::Block;
-use ast::val::{Bin, BinOp, Call, ContextValue, Expr, Float, Int, Lit, Num, SignedInt, Una, UnaOp, Value};
+use ast::function::{Function, Signature};
+use ast::resolve::SymbolTable;
+use ast::ty::{IntType, NumType, ScalarType, Type};
+use ast::val::{
+    Arg, AtomicLoad, AtomicRmw, AtomicRmwOp, Bin, BinOp, Call, CallIndirect, Checked, CheckedOp, CmpXchg, ContextValue,
+    Expr, ExtractElement, Float, FloatCmp, FloatCmpOp, FuncAddr, InsertElement, Int, Intrinsic, IntrinsicOp, Lit, Num,
+    OnOverflow, Reduce, ReduceOp, Shuffle, SignedInt, Splat, Una, UnaOp, UnsignedInt, Value, Var, Vector,
+};
 use std::collections::HashMap;
+use std::num::NonZeroU8;
+use target_lexicon::{Architecture, CallingConvention, OperatingSystem, Triple};
 
-pub(crate) struct Scope<'ctx> {
-    namespace: u32,
-    module: &'ctx cl::Module,
+pub(crate) struct Scope<'ctx, 'm> {
+    module: &'ctx mut cl::Module,
+    // Cranelift types do not distinguish signed and unsigned integers, so their signage is looked up in the AST
+    symbols: &'m SymbolTable<'m, 'm>,
+    func_ids: &'m [cl::FuncId],
+    // The trampolines that call variadic functions by function and number of vector registers, see
+    // `get_variadic_trampoline`
+    trampolines: &'ctx mut HashMap<(cl::FuncId, u8), cl::FuncId>,
     builder: cl::FunctionBuilder<'ctx>,
     imported_functions: HashMap<cl::FuncId, cl::FuncRef>,
+    params: Vec<cl::Value>,
+    levels: Vec<Level>,
+    variable_count: u32,
 }
 
-impl Scope<'_> {
-    pub(crate) fn compile_body(&mut self, body: &Block) {
+impl Scope<'_, '_> {
+    pub(crate) fn compile_body(&mut self, Function { signature, body, .. }: &Function) {
         let block = self.builder.create_block();
         self.builder.switch_to_block(block);
+        self.builder.seal_block(block);
         self.builder.append_block_params_for_function_params(block);
 
-        for stmt in &body.stmts {
-            self.compile_stmt(stmt);
-        }
+        self.params = self.builder.block_params(block).iter()
+            .zip(&signature.input)
+            .map(|(x, ty)| cl::Value {
+                raw: cl::ValueItem::Scalar(*x),
+                signage: ty.get_signage(),
+            })
+            .collect();
+
+        self.compile_block(body);
+    }
+
+    fn compile_block(&mut self, block: &Block) -> bool {
+        self.levels.push(Level::new());
+        let terminates = block.stmts.iter().any(|stmt| self.compile_stmt(stmt));
+        self.levels.pop();
+
+        terminates
     }
 
-    fn compile_stmt(&mut self, stmt: &Stmt) {
+    fn compile_stmt(&mut self, stmt: &Stmt) -> bool {
         match stmt {
-            Stmt::If(_) => todo!("If statements are not yet implemented"),
-            Stmt::While(_) => todo!("While statements are not yet implemented"),
+            Stmt::If(x) => self.compile_if_stmt(x),
+            Stmt::While(x) => {
+                self.compile_while_stmt(x);
+                false
+            }
             Stmt::Return(x) => {
-                let values = x.values.iter()
-                    .map(|x| self.compile_value(x).raw.to_scalar().unwrap())
-                    .collect::<Vec<_>>();
-                self.builder.ins().return_(&values);
+                self.compile_return_stmt(x);
+                true
+            }
+            Stmt::Bind(x) => {
+                self.compile_bind_stmt(x);
+                false
+            }
+            Stmt::BindTuple(x) => {
+                self.compile_bind_tuple_stmt(x);
+                false
+            }
+            Stmt::Assign(x) => {
+                self.compile_assign_stmt(x);
+                false
+            }
+            Stmt::Trap(x) => {
+                self.builder.ins().trap(compile_trap_code(*x));
+                true
+            }
+            Stmt::Unreachable => {
+                self.builder.ins().trap(compile_trap_code(TrapCode::UNREACHABLE));
+                true
+            }
+            Stmt::Assert(x) => {
+                self.compile_assert_stmt(x);
+                false
             }
-            Stmt::Bind(_) => todo!("Bind statements are not yet implemented"),
-            Stmt::Assign(_) => todo!("Assign statements are not yet implemented"),
+            Stmt::AtomicStore(x) => {
+                self.compile_atomic_store_stmt(x);
+                false
+            }
+            Stmt::Fence(x) => {
+                if !x.supports_fence() {
+                    panic!("Fence does not support {:?} ordering", x);
+                }
+
+                // Cranelift only has sequentially consistent fences, which are at least as strong as any ordering
+                self.builder.ins().fence();
+                false
+            }
+            Stmt::MemCopy(x) => {
+                self.compile_mem_copy_stmt(x, true);
+                false
+            }
+            Stmt::MemMove(x) => {
+                self.compile_mem_copy_stmt(x, false);
+                false
+            }
+            Stmt::MemSet(x) => {
+                self.compile_mem_set_stmt(x);
+                false
+            }
+            Stmt::Call(x) => {
+                self.compile_call(x);
+                false
+            }
+            Stmt::CallIndirect(x) => {
+                self.compile_call_indirect(x);
+                false
+            }
+            Stmt::TailCall(x) => {
+                self.compile_tail_call(x);
+                true
+            }
+            Stmt::TailCallIndirect(x) => {
+                self.compile_tail_call_indirect(x);
+                true
+            }
+            Stmt::Try(_) => unreachable!("Try statements are rejected by `Cranelift::check_supported`"),
+            // Source locations are only used for debug info, which this backend does not emit
+            Stmt::Spanned(x) => self.compile_stmt(&x.node),
+        }
+    }
+
+    fn compile_if_stmt(&mut self, stmt: &IfStmt) -> bool {
+        let condition = self.compile_value(&stmt.condition);
+        let condition = self.to_scalar(condition);
+
+        let then_block = self.builder.create_block();
+        let else_block = self.builder.create_block();
+        let merge_block = self.builder.create_block();
+
+        self.builder.ins().brif(condition, then_block, &[], else_block, &[]);
+        self.builder.seal_block(then_block);
+        self.builder.seal_block(else_block);
+        self.builder.switch_to_block(then_block);
+
+        let then_terminates = self.compile_block(&stmt.then_block);
+        if !then_terminates {
+            self.builder.ins().jump(merge_block, &[]);
+        }
+
+        self.builder.switch_to_block(else_block);
+        let else_terminates = match &stmt.if_else {
+            Some(IfElse::If(x)) => self.compile_if_stmt(x),
+            Some(IfElse::Else(x)) => self.compile_block(x),
+            None => false,
+        };
+
+        if !else_terminates {
+            self.builder.ins().jump(merge_block, &[]);
+        }
+
+        if !then_terminates || !else_terminates {
+            self.builder.seal_block(merge_block);
+            self.builder.switch_to_block(merge_block);
+        }
+
+        then_terminates && else_terminates
+    }
+
+    fn compile_while_stmt(&mut self, stmt: &WhileStmt) {
+        let condition = self.compile_value(&stmt.condition);
+        let condition = self.to_scalar(condition);
+
+        let loop_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+        self.builder.ins().brif(condition, loop_block, &[], exit_block, &[]);
+
+        self.builder.switch_to_block(loop_block);
+        if !self.compile_block(&stmt.loop_block) {
+            let condition = self.compile_value(&stmt.condition);
+            let condition = self.to_scalar(condition);
+            self.builder.ins().brif(condition, loop_block, &[], exit_block, &[]);
+        }
+        self.builder.seal_block(loop_block);
+        self.builder.seal_block(exit_block);
+
+        self.builder.switch_to_block(exit_block);
+    }
+
+    fn compile_return_stmt(&mut self, stmt: &ReturnStmt) {
+        let mut values = vec![];
+        for value in &stmt.values {
+            let value = self.compile_value(value);
+            self.flatten_value(value, &mut values);
         }
+        self.builder.ins().return_(&values);
+    }
+
+    fn compile_bind_stmt(&mut self, stmt: &BindStmt) {
+        let value = self.compile_value(&stmt.value);
+        self.bind(&stmt.name, value);
+    }
+
+    fn compile_bind_tuple_stmt(&mut self, stmt: &BindTupleStmt) {
+        let values = self.compile_values(&stmt.value);
+        if values.len() != stmt.names.len() {
+            panic!("Expected {} values to bind but found {} values", stmt.names.len(), values.len());
+        }
+
+        for (name, value) in stmt.names.iter().zip(values) {
+            self.bind(name, value);
+        }
+    }
+
+    fn bind(&mut self, name: &str, value: cl::Value) {
+        let scalar = self.to_scalar(value);
+        let ty = self.builder.func.dfg.value_type(scalar);
+
+        // Every binding is a new variable, so a shadowed binding keeps its own value once the inner block ends
+        let variable = cl::Variable::from_u32(self.variable_count);
+        self.variable_count += 1;
+        self.builder.declare_var(variable, ty);
+        self.builder.def_var(variable, scalar);
+
+        let stored_value = StoredValue { variable, signage: value.signage };
+        self.levels.last_mut().unwrap().values.insert(name.to_string(), stored_value);
+    }
+
+    fn compile_assign_stmt(&mut self, stmt: &AssignStmt) {
+        let stored_value = self.get_var(&stmt.var).expect("Failed to get variable from scope");
+        let value = self.compile_value(&stmt.value);
+        let scalar = self.to_scalar(value);
+        self.builder.def_var(stored_value.variable, scalar);
+    }
+
+    fn compile_assert_stmt(&mut self, stmt: &AssertStmt) {
+        let condition = self.compile_value(&stmt.condition);
+        let condition = self.to_scalar(condition);
+        self.builder.ins().trapz(condition, compile_trap_code(stmt.code));
+    }
+
+    fn compile_atomic_store_stmt(&mut self, stmt: &AtomicStoreStmt) {
+        if !stmt.ordering.supports_store() {
+            panic!("Atomic store does not support {:?} ordering", stmt.ordering);
+        }
+
+        let ptr = self.compile_address(&stmt.ptr);
+        let value = self.compile_value(&stmt.value);
+        let signage = value.signage;
+        let value = self.to_scalar(value);
+        self.atomic_type(value, signage);
+
+        self.builder.ins().atomic_store(cl::MemFlags::new(), value, ptr);
+    }
+
+    fn compile_mem_copy_stmt(&mut self, stmt: &MemCopyStmt, non_overlapping: bool) {
+        let dest = self.compile_address(&stmt.dest);
+        let src = self.compile_address(&stmt.src);
+        let config = self.module.target_config();
+
+        // Copies of a known length are inlined as loads and stores when they are short enough
+        if let Some(len) = literal_length(&stmt.len) {
+            let align = small_memory_align(stmt.align, len);
+            self.builder.emit_small_memory_copy(
+                config, dest, src, len, align, align, non_overlapping, cl::MemFlags::new(),
+            );
+            return;
+        }
+
+        let len = self.compile_length(&stmt.len);
+        if non_overlapping {
+            self.builder.call_memcpy(config, dest, src, len);
+        } else {
+            self.builder.call_memmove(config, dest, src, len);
+        }
+    }
+
+    fn compile_mem_set_stmt(&mut self, stmt: &MemSetStmt) {
+        let dest = self.compile_address(&stmt.dest);
+        let config = self.module.target_config();
+
+        if let (Some(len), Value::Lit(Lit::Num(Num::Int(Int::Unsigned(UnsignedInt::U8(value)))))) =
+            (literal_length(&stmt.len), strip_spans(&stmt.value))
+        {
+            let align = small_memory_align(stmt.align, len);
+            self.builder.emit_small_memset(config, dest, *value, len, align, cl::MemFlags::new());
+            return;
+        }
+
+        let value = self.compile_value(&stmt.value);
+        let signage = value.signage;
+        let value = self.to_scalar(value);
+        let ty = ty::ast_type(self.builder.func.dfg.value_type(value), signage);
+        if ty != Some(IntType::U8.into()) {
+            panic!("Value of memset must be a u8 but found {:?}", ty);
+        }
+
+        let len = self.compile_length(&stmt.len);
+        self.builder.call_memset(config, dest, value, len);
+    }
+
+    fn compile_length(&mut self, len: &Value) -> cl::Scalar {
+        let len = self.compile_value(len);
+        let signage = len.signage;
+        let len = self.to_scalar(len);
+
+        let ty = ty::ast_type(self.builder.func.dfg.value_type(len), signage);
+        if ty != Some(IntType::U64.into()) {
+            panic!("Length of memory operation must be a u64 but found {:?}", ty);
+        }
+
+        len
+    }
+
+    fn get_var(&self, var: &Var) -> Option<StoredValue> {
+        // Iterate in reverse to prefer the most recent scope and allow for shadowing
+        self.levels.iter()
+            .rev()
+            .find_map(|level| level.values.get(var.name.as_ref()))
+            .copied()
     }
 
     fn compile_value(&mut self, value: &Value) -> cl::Value { 
@@ -44,30 +336,88 @@ impl Scope<'_> {
             Value::Expr(x) => self.compile_expr(x),
             Value::Lit(x) => self.compile_lit(x),
             Value::Call(x) => self.compile_call(x),
+            Value::FuncAddr(x) => self.compile_func_addr(x),
+            Value::CallIndirect(x) => self.compile_call_indirect(x),
+            Value::Intrinsic(x) => self.compile_intrinsic(x),
+            Value::AtomicLoad(x) => self.compile_atomic_load(x),
+            Value::AtomicRmw(x) => self.compile_atomic_rmw(x),
+            Value::CmpXchg(x) => self.compile_cmpxchg(x),
+            Value::Spanned(x) => self.compile_value(&x.node),
         }
     }
+
+    /// Compiles a value that may produce more than one value, i.e. a call with multiple outputs or a checked operation
+    /// that flags overflow, into each of its values.
+    fn compile_values(&mut self, value: &Value) -> Vec<cl::Value> {
+        let compiled = self.compile_value(value);
+        let signages = match strip_spans(value) {
+            Value::Call(x) => {
+                let id = self.symbols.callee(x);
+                self.symbols.function(id).signature.output.iter().map(|x| x.get_signage()).collect()
+            }
+            Value::CallIndirect(x) => x.signature.output.iter().map(|x| x.get_signage()).collect(),
+            // The overflow flag of a checked operation is a boolean
+            _ => vec![compiled.signage, None],
+        };
+
+        let mut scalars = vec![];
+        self.flatten_value(compiled, &mut scalars);
+
+        scalars.into_iter()
+            .zip(signages)
+            .map(|(x, signage)| cl::Value { raw: cl::ValueItem::Scalar(x), signage })
+            .collect()
+    }
     
     fn compile_context_value(&mut self, value: &ContextValue) -> cl::Value {
-        todo!("Context values are not yet implemented: {:?}", value);
+        match value {
+            ContextValue::Arg(x) => self.compile_arg_value(x),
+            ContextValue::Var(x) => self.compile_var_value(x),
+        }
+    }
+
+    fn compile_var_value(&mut self, var: &Var) -> cl::Value {
+        let stored_value = self.get_var(var).expect("Failed to get variable from scope");
+
+        cl::Value {
+            raw: cl::ValueItem::Scalar(self.builder.use_var(stored_value.variable)),
+            signage: stored_value.signage,
+        }
+    }
+
+    fn compile_arg_value(&mut self, arg: &Arg) -> cl::Value {
+        *self.params
+            .get(arg.param_index as usize)
+            .expect("Failed to get function parameter")
     }
 
     fn compile_expr(&mut self, value: &Expr) -> cl::Value {
         match value {
             Expr::Bin(x) => self.compile_bin(x),
             Expr::Una(x) => self.compile_una(x),
+            Expr::Checked(x) => self.compile_checked(x),
+            Expr::FloatCmp(x) => self.compile_float_cmp(x),
+            Expr::Splat(x) => self.compile_splat(x),
+            Expr::Vector(x) => self.compile_vector(x),
+            Expr::ExtractElement(x) => self.compile_extract_element(x),
+            Expr::InsertElement(x) => self.compile_insert_element(x),
+            Expr::Shuffle(x) => self.compile_shuffle(x),
+            Expr::Reduce(x) => self.compile_reduce(x),
         }
     }
 
     fn compile_bin(&mut self, value: &Bin) -> cl::Value {
         let (lhs, rhs) = (self.compile_value(&value.lhs), self.compile_value(&value.rhs));
-        let signed = lhs.signage.unwrap_or(false);
-        let (lhs, rhs) = (lhs.raw.to_scalar().unwrap(), rhs.raw.to_scalar().unwrap());
+        let signage = lhs.signage;
+        let (lhs, rhs) = (self.to_scalar(lhs), self.to_scalar(rhs));
 
         let ty = self.builder.func.dfg.value_type(lhs);
         if ty.is_int() {
-            self.compile_int_bin(value.op, signed, lhs, rhs)
+            self.compile_int_bin(value.op, signage, lhs, rhs)
         } else if ty.is_float() {
             self.compile_float_bin(value.op, lhs, rhs)
+        } else if ty.is_vector() {
+            self.compile_vector_bin(value.op, signage, lhs, rhs)
         } else {
             panic!("Type does not support binary operations: {:?}", ty);
         }
@@ -76,10 +426,11 @@ impl Scope<'_> {
     fn compile_int_bin(
         &mut self,
         op: BinOp,
-        signed: bool,
+        signage: Option<bool>,
         lhs: cl::Scalar,
         rhs: cl::Scalar,
     ) -> cl::Value {
+        let signed = signage.unwrap_or(false);
         let ty = self.builder.func.dfg.value_type(rhs);
         if !ty.is_int() {
             panic!(
@@ -112,10 +463,16 @@ impl Scope<'_> {
             BinOp::Ge if signed => self.builder.ins().icmp(cl::IntCmpOp::SignedGreaterThanOrEqual, lhs, rhs),
             BinOp::Ge => self.builder.ins().icmp(cl::IntCmpOp::UnsignedGreaterThanOrEqual, lhs, rhs),
         };
-        
+
+        // Comparisons produce booleans, which have no signage
+        let signage = match op {
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => None,
+            _ => signage,
+        };
+
         cl::Value {
             raw: cl::ValueItem::Scalar(scalar),
-            signage: Some(signed),
+            signage,
         }
     }
 
@@ -137,6 +494,9 @@ impl Scope<'_> {
             BinOp::Sub => self.builder.ins().fsub(lhs, rhs),
             BinOp::Mul => self.builder.ins().fmul(lhs, rhs),
             BinOp::Div => self.builder.ins().fdiv(lhs, rhs),
+            // Cranelift has no float remainder instruction, so it is computed by libm like LLVM's `frem`
+            BinOp::Rem => self.compile_float_rem(lhs, rhs),
+            // `Ne` is the only comparison that is true for NaN, as `NotEqual` is unordered unlike the others
             BinOp::Eq => self.builder.ins().fcmp(cl::FloatCmpOp::Equal, lhs, rhs),
             BinOp::Ne => self.builder.ins().fcmp(cl::FloatCmpOp::NotEqual, lhs, rhs),
             BinOp::Lt => self.builder.ins().fcmp(cl::FloatCmpOp::LessThan, lhs, rhs),
@@ -152,15 +512,150 @@ impl Scope<'_> {
         }
     }
 
+    fn compile_float_rem(&mut self, lhs: cl::Scalar, rhs: cl::Scalar) -> cl::Scalar {
+        let ty = self.builder.func.dfg.value_type(lhs);
+        match ty {
+            cl::F32 => self.compile_libm_call("fmodf", &[lhs, rhs]),
+            cl::F64 => self.compile_libm_call("fmod", &[lhs, rhs]),
+            cl::F128 => self.compile_libm_call("fmodf128", &[lhs, rhs]),
+            // libm has no half precision functions, while the remainder is exact and so fits a half again
+            cl::F16 => {
+                let (lhs, rhs) = (self.builder.ins().fpromote(cl::F32, lhs), self.builder.ins().fpromote(cl::F32, rhs));
+                let rem = self.compile_libm_call("fmodf", &[lhs, rhs]);
+                self.builder.ins().fdemote(cl::F16, rem)
+            }
+            _ => panic!("Type does not support float remainders: {:?}", ty),
+        }
+    }
+
+    fn compile_vector_bin(
+        &mut self,
+        op: BinOp,
+        signage: Option<bool>,
+        lhs: cl::Scalar,
+        rhs: cl::Scalar,
+    ) -> cl::Value {
+        let ty = self.builder.func.dfg.value_type(lhs);
+        if self.builder.func.dfg.value_type(rhs) != ty {
+            panic!("Right-hand value type of binary operation does not match left-hand type which is {:?}", ty);
+        }
+
+        let float = ty.lane_type().is_float();
+        let scalar = match op {
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => {
+                panic!("Comparison operation is not supported for vector values: {:?}", op)
+            }
+            BinOp::Add if float => self.builder.ins().fadd(lhs, rhs),
+            BinOp::Sub if float => self.builder.ins().fsub(lhs, rhs),
+            BinOp::Mul if float => self.builder.ins().fmul(lhs, rhs),
+            BinOp::Div if float => self.builder.ins().fdiv(lhs, rhs),
+            BinOp::Add => self.builder.ins().iadd(lhs, rhs),
+            BinOp::Sub => self.builder.ins().isub(lhs, rhs),
+            // x64 cannot multiply vectors of bytes
+            BinOp::Mul if ty.lane_type() != cl::I8 => self.builder.ins().imul(lhs, rhs),
+            BinOp::And | BinOp::BitAnd if !float => self.builder.ins().band(lhs, rhs),
+            BinOp::Or | BinOp::BitOr if !float => self.builder.ins().bor(lhs, rhs),
+            BinOp::BitXor if !float => self.builder.ins().bxor(lhs, rhs),
+            // Division and float remainders have no vector instructions and vector shifts take a single amount, so they
+            // are lane by lane
+            _ => {
+                let mut vector = lhs;
+                for i in 0..ty.lane_count() as u8 {
+                    let (lhs, rhs) = (self.builder.ins().extractlane(lhs, i), self.builder.ins().extractlane(rhs, i));
+                    let lane = if float {
+                        self.compile_float_bin(op, lhs, rhs)
+                    } else {
+                        self.compile_int_bin(op, signage, lhs, rhs)
+                    };
+                    let lane = self.to_scalar(lane);
+                    vector = self.builder.ins().insertlane(vector, lane, i);
+                }
+                vector
+            }
+        };
+
+        cl::Value {
+            raw: cl::ValueItem::Scalar(scalar),
+            signage,
+        }
+    }
+
+    fn compile_float_cmp(&mut self, value: &FloatCmp) -> cl::Value {
+        let (lhs, rhs) = (self.compile_value(&value.lhs), self.compile_value(&value.rhs));
+        let (lhs, rhs) = (self.to_scalar(lhs), self.to_scalar(rhs));
+
+        let ty = self.builder.func.dfg.value_type(lhs);
+        if !ty.is_float() {
+            panic!("Type does not support float comparisons: {:?}", ty);
+        }
+
+        let op = match value.op {
+            FloatCmpOp::Ordered => cl::FloatCmpOp::Ordered,
+            FloatCmpOp::Unordered => cl::FloatCmpOp::Unordered,
+            FloatCmpOp::OrderedEq => cl::FloatCmpOp::Equal,
+            FloatCmpOp::OrderedNe => cl::FloatCmpOp::OrderedNotEqual,
+            FloatCmpOp::OrderedLt => cl::FloatCmpOp::LessThan,
+            FloatCmpOp::OrderedGt => cl::FloatCmpOp::GreaterThan,
+            FloatCmpOp::OrderedLe => cl::FloatCmpOp::LessThanOrEqual,
+            FloatCmpOp::OrderedGe => cl::FloatCmpOp::GreaterThanOrEqual,
+            FloatCmpOp::UnorderedEq => cl::FloatCmpOp::UnorderedOrEqual,
+            FloatCmpOp::UnorderedNe => cl::FloatCmpOp::NotEqual,
+            FloatCmpOp::UnorderedLt => cl::FloatCmpOp::UnorderedOrLessThan,
+            FloatCmpOp::UnorderedGt => cl::FloatCmpOp::UnorderedOrGreaterThan,
+            FloatCmpOp::UnorderedLe => cl::FloatCmpOp::UnorderedOrLessThanOrEqual,
+            FloatCmpOp::UnorderedGe => cl::FloatCmpOp::UnorderedOrGreaterThanOrEqual,
+        };
+
+        cl::Value {
+            raw: cl::ValueItem::Scalar(self.builder.ins().fcmp(op, lhs, rhs)),
+            signage: None,
+        }
+    }
+
+    fn compile_checked(&mut self, value: &Checked) -> cl::Value {
+        let (lhs, rhs) = (self.compile_value(&value.lhs), self.compile_value(&value.rhs));
+        let signage = lhs.signage;
+        let signed = signage.unwrap_or(false);
+        let (lhs, rhs) = (self.to_scalar(lhs), self.to_scalar(rhs));
+
+        let ty = self.builder.func.dfg.value_type(lhs);
+        if !ty.is_int() || signage.is_none() {
+            panic!("Type does not support checked operations: {:?}", ty);
+        }
+
+        let (result, overflowed) = match value.op {
+            CheckedOp::Add if signed => self.builder.ins().sadd_overflow(lhs, rhs),
+            CheckedOp::Add => self.builder.ins().uadd_overflow(lhs, rhs),
+            CheckedOp::Sub if signed => self.builder.ins().ssub_overflow(lhs, rhs),
+            CheckedOp::Sub => self.builder.ins().usub_overflow(lhs, rhs),
+            CheckedOp::Mul if signed => self.builder.ins().smul_overflow(lhs, rhs),
+            CheckedOp::Mul => self.builder.ins().umul_overflow(lhs, rhs),
+        };
+
+        match value.on_overflow {
+            OnOverflow::Flag => {
+                let inst = self.builder.func.dfg.value_def(result).unwrap_inst();
+                cl::Value { raw: cl::ValueItem::Variable(inst), signage }
+            }
+            OnOverflow::Trap => {
+                self.builder.ins().trapnz(overflowed, compile_trap_code(TrapCode::INTEGER_OVERFLOW));
+                cl::Value { raw: cl::ValueItem::Scalar(result), signage }
+            }
+        }
+    }
+
     fn compile_una(&mut self, value: &Una) -> cl::Value {
         let operand = self.compile_value(&value.operand);
-        let signed = operand.signage.unwrap_or(false);
+        let signage = operand.signage;
+        let operand = self.to_scalar(operand);
         
-        let ty = self.builder.func.dfg.value_type(operand.raw.to_scalar().unwrap());
+        let ty = self.builder.func.dfg.value_type(operand);
         if ty.is_int() {
-            self.compile_int_una(value.op, signed, operand.raw.to_scalar().unwrap())
+            self.compile_int_una(value.op, signage, operand)
         } else if ty.is_float() {
-            self.compile_float_una(value.op, operand.raw.to_scalar().unwrap())
+            self.compile_float_una(value.op, operand)
+        } else if ty.is_vector() {
+            self.compile_vector_una(value.op, signage, operand)
         } else {
             panic!("Value does not support unary operations: {:?}", ty);
         }
@@ -169,17 +664,20 @@ impl Scope<'_> {
     fn compile_int_una(
         &mut self,
         op: UnaOp,
-        signed: bool,
+        signage: Option<bool>,
         operand: cl::Scalar,
     ) -> cl::Value { 
         let raw = match op {
             UnaOp::Neg => self.builder.ins().ineg(operand),
+            // Booleans are a byte that is either 0 or 1, so only the lowest bit is flipped
+            UnaOp::Not if signage.is_none() => self.builder.ins().bxor_imm(operand, 1),
             UnaOp::Not => self.builder.ins().bnot(operand),
+            UnaOp::IsNan => panic!("IsNan operation is not supported for integer values"),
         };
         
         cl::Value {
             raw: cl::ValueItem::Scalar(raw),
-            signage: Some(signed),
+            signage,
         }
     }
 
@@ -194,6 +692,279 @@ impl Scope<'_> {
                 signage: None,
             },
             UnaOp::Not => panic!("Not operation is not supported for float values"),
+            // Only NaN is unordered with itself
+            UnaOp::IsNan => cl::Value {
+                raw: cl::ValueItem::Scalar(self.builder.ins().fcmp(cl::FloatCmpOp::Unordered, operand, operand)),
+                signage: None,
+            },
+        }
+    }
+
+    fn compile_vector_una(
+        &mut self,
+        op: UnaOp,
+        signage: Option<bool>,
+        operand: cl::Scalar,
+    ) -> cl::Value {
+        let ty = self.builder.func.dfg.value_type(operand);
+        let float = ty.lane_type().is_float();
+
+        let raw = match op {
+            UnaOp::Neg if float => self.builder.ins().fneg(operand),
+            UnaOp::Neg => self.builder.ins().ineg(operand),
+            // Only the lowest bit of each boolean is flipped, like a single boolean
+            UnaOp::Not if !float && signage.is_none() => {
+                let one = self.builder.ins().iconst(ty.lane_type(), 1);
+                let ones = self.builder.ins().splat(ty, one);
+                self.builder.ins().bxor(operand, ones)
+            }
+            UnaOp::Not if !float => self.builder.ins().bnot(operand),
+            _ => panic!("Unsupported vector operation: {:?}", op),
+        };
+
+        cl::Value {
+            raw: cl::ValueItem::Scalar(raw),
+            signage,
+        }
+    }
+
+    fn compile_splat(&mut self, value: &Splat) -> cl::Value {
+        let lane = self.compile_value(&value.value);
+        let signage = lane.signage;
+        let lane = self.to_scalar(lane);
+
+        let ty = self.vector_type(lane, value.len);
+        cl::Value {
+            raw: cl::ValueItem::Scalar(self.builder.ins().splat(ty, lane)),
+            signage,
+        }
+    }
+
+    fn compile_vector(&mut self, value: &Vector) -> cl::Value {
+        let lanes = value.elems.iter().map(|x| self.compile_value(x)).collect::<Vec<_>>();
+        let Some(signage) = lanes.first().map(|x| x.signage) else {
+            panic!("Vector has no lanes");
+        };
+        let lanes = lanes.into_iter().map(|x| self.to_scalar(x)).collect::<Vec<_>>();
+
+        // The other lanes are inserted into a splat of the first
+        let ty = self.vector_type(lanes[0], lanes.len() as u32);
+        let mut vector = self.builder.ins().splat(ty, lanes[0]);
+        for (i, lane) in lanes.iter().enumerate().skip(1) {
+            vector = self.builder.ins().insertlane(vector, *lane, i as u8);
+        }
+
+        cl::Value {
+            raw: cl::ValueItem::Scalar(vector),
+            signage,
+        }
+    }
+
+    fn compile_extract_element(&mut self, value: &ExtractElement) -> cl::Value {
+        let vector = self.compile_value(&value.vector);
+        let signage = vector.signage;
+        let vector = self.to_scalar(vector);
+        self.check_lane(vector, value.index);
+
+        cl::Value {
+            raw: cl::ValueItem::Scalar(self.builder.ins().extractlane(vector, value.index as u8)),
+            signage,
+        }
+    }
+
+    fn compile_insert_element(&mut self, value: &InsertElement) -> cl::Value {
+        let vector = self.compile_value(&value.vector);
+        let signage = vector.signage;
+        let (vector, lane) = (self.to_scalar(vector), self.compile_value(&value.value));
+        let lane = self.to_scalar(lane);
+        self.check_lane(vector, value.index);
+
+        cl::Value {
+            raw: cl::ValueItem::Scalar(self.builder.ins().insertlane(vector, lane, value.index as u8)),
+            signage,
+        }
+    }
+
+    fn compile_shuffle(&mut self, value: &Shuffle) -> cl::Value {
+        let (lhs, rhs) = (self.compile_value(&value.lhs), self.compile_value(&value.rhs));
+        let signage = lhs.signage;
+        let (lhs, rhs) = (self.to_scalar(lhs), self.to_scalar(rhs));
+
+        let ty = self.builder.func.dfg.value_type(lhs);
+        if !ty.is_vector() || value.mask.len() != ty.lane_count() as usize {
+            panic!("Shuffle mask {:?} does not have a lane for each lane of {:?}", value.mask, ty);
+        }
+        if value.mask.iter().any(|x| *x >= 2 * ty.lane_count()) {
+            panic!("Shuffle mask {:?} is out of bounds of two {:?}", value.mask, ty);
+        }
+
+        // Cranelift only shuffles 128-bit vectors, so narrower or wider ones are shuffled lane by lane
+        if ty.bits() != 128 {
+            let mut vector = lhs;
+            for (i, lane) in value.mask.iter().enumerate() {
+                let (source, index) = if *lane < ty.lane_count() {
+                    (lhs, *lane)
+                } else {
+                    (rhs, *lane - ty.lane_count())
+                };
+                let element = self.builder.ins().extractlane(source, index as u8);
+                vector = self.builder.ins().insertlane(vector, element, i as u8);
+            }
+
+            return cl::Value { raw: cl::ValueItem::Scalar(vector), signage };
+        }
+
+        // Cranelift only shuffles bytes, so every lane of the mask selects each byte of a lane
+        let lane_bytes = ty.lane_type().bytes();
+        let bytes = value.mask
+            .iter()
+            .flat_map(|lane| (0..lane_bytes).map(move |byte| (lane * lane_bytes + byte) as u8))
+            .collect::<Vec<_>>();
+        let mask = self.builder.func.dfg.immediates.push(cl::ConstantData::from(bytes));
+
+        let flags = cl::MemFlags::new().with_endianness(cl::Endianness::Little);
+        let lhs = self.builder.ins().bitcast(cl::I8X16, flags, lhs);
+        let rhs = self.builder.ins().bitcast(cl::I8X16, flags, rhs);
+        let shuffled = self.builder.ins().shuffle(lhs, rhs, mask);
+
+        cl::Value {
+            raw: cl::ValueItem::Scalar(self.builder.ins().bitcast(ty, flags, shuffled)),
+            signage,
+        }
+    }
+
+    fn compile_reduce(&mut self, value: &Reduce) -> cl::Value {
+        let vector = self.compile_value(&value.vector);
+        let signage = vector.signage;
+        let signed = signage.unwrap_or(false);
+        let vector = self.to_scalar(vector);
+
+        let ty = self.builder.func.dfg.value_type(vector);
+        if !ty.is_vector() {
+            panic!("Type does not support reductions: {:?}", ty);
+        }
+
+        // Cranelift has few horizontal instructions, so the lanes are combined in order
+        let float = ty.lane_type().is_float();
+        let mut result = self.builder.ins().extractlane(vector, 0);
+        for i in 1..ty.lane_count() as u8 {
+            let lane = self.builder.ins().extractlane(vector, i);
+            result = match value.op {
+                ReduceOp::Sum if float => self.builder.ins().fadd(result, lane),
+                ReduceOp::Sum => self.builder.ins().iadd(result, lane),
+                ReduceOp::Min if float => self.builder.ins().fmin(result, lane),
+                ReduceOp::Min if signed => self.builder.ins().smin(result, lane),
+                ReduceOp::Min => self.builder.ins().umin(result, lane),
+                ReduceOp::Max if float => self.builder.ins().fmax(result, lane),
+                ReduceOp::Max if signed => self.builder.ins().smax(result, lane),
+                ReduceOp::Max => self.builder.ins().umax(result, lane),
+            };
+        }
+
+        cl::Value {
+            raw: cl::ValueItem::Scalar(result),
+            signage,
+        }
+    }
+
+    fn compile_atomic_load(&mut self, value: &AtomicLoad) -> cl::Value {
+        if !value.ordering.supports_load() {
+            panic!("Atomic load does not support {:?} ordering", value.ordering);
+        }
+        if !value.ty.is_atomic() {
+            panic!("Type cannot be accessed atomically: {:?}", value.ty);
+        }
+
+        let ptr = self.compile_address(&value.ptr);
+        let ty = ty::compile_type(&value.ty, self.module.isa().triple());
+
+        cl::Value {
+            // Cranelift atomics are all sequentially consistent, which is at least as strong as any ordering
+            raw: cl::ValueItem::Scalar(self.builder.ins().atomic_load(ty, cl::MemFlags::new(), ptr)),
+            signage: value.ty.get_signage(),
+        }
+    }
+
+    fn compile_atomic_rmw(&mut self, value: &AtomicRmw) -> cl::Value {
+        let ptr = self.compile_address(&value.ptr);
+        let operand = self.compile_value(&value.value);
+        let signage = operand.signage;
+        let operand = self.to_scalar(operand);
+
+        let ty = self.atomic_type(operand, signage);
+        if !value.op.supports(ty) {
+            panic!("Atomic operation {:?} does not support values of type {:?}", value.op, ty);
+        }
+
+        let signed = signage.unwrap_or(false);
+        let op = match value.op {
+            AtomicRmwOp::Add => cl::AtomicRmwOp::Add,
+            AtomicRmwOp::Sub => cl::AtomicRmwOp::Sub,
+            AtomicRmwOp::And => cl::AtomicRmwOp::And,
+            AtomicRmwOp::Or => cl::AtomicRmwOp::Or,
+            AtomicRmwOp::Xor => cl::AtomicRmwOp::Xor,
+            AtomicRmwOp::Xchg => cl::AtomicRmwOp::Xchg,
+            AtomicRmwOp::Min if signed => cl::AtomicRmwOp::Smin,
+            AtomicRmwOp::Min => cl::AtomicRmwOp::Umin,
+            AtomicRmwOp::Max if signed => cl::AtomicRmwOp::Smax,
+            AtomicRmwOp::Max => cl::AtomicRmwOp::Umax,
+        };
+        let ty = self.builder.func.dfg.value_type(operand);
+
+        cl::Value {
+            raw: cl::ValueItem::Scalar(self.builder.ins().atomic_rmw(ty, cl::MemFlags::new(), op, ptr, operand)),
+            signage,
+        }
+    }
+
+    fn compile_cmpxchg(&mut self, value: &CmpXchg) -> cl::Value {
+        let ptr = self.compile_address(&value.ptr);
+        let expected = self.compile_value(&value.expected);
+        let signage = expected.signage;
+        let (expected, replacement) = (self.to_scalar(expected), self.compile_value(&value.replacement));
+        let replacement = self.to_scalar(replacement);
+
+        let ty = self.atomic_type(expected, signage);
+        if self.builder.func.dfg.value_type(replacement) != self.builder.func.dfg.value_type(expected) {
+            panic!("Replacement type of cmpxchg does not match expected type which is {:?}", ty);
+        }
+
+        cl::Value {
+            raw: cl::ValueItem::Scalar(self.builder.ins().atomic_cas(cl::MemFlags::new(), ptr, expected, replacement)),
+            signage,
+        }
+    }
+
+    fn compile_address(&mut self, ptr: &Value) -> cl::Scalar {
+        let ptr = self.compile_value(ptr);
+        let signage = ptr.signage;
+        let ptr = self.to_scalar(ptr);
+
+        let ty = ty::ast_type(self.builder.func.dfg.value_type(ptr), signage);
+        if !matches!(ty, Some(Type::Scalar(ScalarType::Ptr(_)))) {
+            panic!("Address must be a pointer but found {:?}", ty);
+        }
+
+        ptr
+    }
+
+    /// The AST type of a value accessed atomically, which must be an integer of up to 64 bits or a pointer.
+    fn atomic_type(&self, value: cl::Scalar, signage: Option<bool>) -> Type {
+        let ty = self.builder.func.dfg.value_type(value);
+        ty::ast_type(ty, signage)
+            .filter(|x| x.is_atomic())
+            .unwrap_or_else(|| panic!("Type cannot be accessed atomically: {:?}", ty))
+    }
+
+    fn vector_type(&self, lane: cl::Scalar, len: u32) -> cl::Type {
+        let ty = self.builder.func.dfg.value_type(lane);
+        ty.by(len).unwrap_or_else(|| panic!("Failed to create a vector of {} lanes of {:?}", len, ty))
+    }
+
+    fn check_lane(&self, vector: cl::Scalar, index: u32) {
+        let ty = self.builder.func.dfg.value_type(vector);
+        if !ty.is_vector() || index >= ty.lane_count() {
+            panic!("Lane {} is out of bounds of {:?}", index, ty);
         }
     }
 
@@ -204,6 +975,7 @@ impl Scope<'_> {
                     let (integer, signed) = match *x {
                         Int::Signed(x) => (x, true),
                         Int::Unsigned(x) => (x.cast_signed(), false),
+                        Int::Untyped(_) => panic!("Untyped literal {:?} was not typed before compilation", x),
                     };
 
                     let raw = match integer {
@@ -228,43 +1000,294 @@ impl Scope<'_> {
                     raw: cl::ValueItem::Scalar(match x { 
                         &Float::F32(x) => self.builder.ins().f32const(x), 
                         &Float::F64(x) => self.builder.ins().f64const(x), 
+                        Float::Untyped(_) => panic!("Untyped literal {:?} was not typed before compilation", x),
                     }),
                     signage: None,
                 }
             }
             &Lit::Bool(x) => cl::Value {
                 raw: cl::ValueItem::Scalar(self.builder.ins().iconst(cl::I8, x as i64)),
-                signage: Some(false),             
+                signage: None,
             },
         }
     }
     
     fn compile_call(&mut self, value: &Call) -> cl::Value {
-        let func_ref = self.get_func_ref(&value.function_name).unwrap();
-        
+        let id = self.symbols.callee(value);
+        let func_id = self.func_ids[id.0 as usize];
+        let signature = &self.symbols.function(id).signature;
+        let values = self.compile_args(&value.args, signature);
+
+        let func_ref = if signature.variadic {
+            let call_signature = self.compile_variadic_signature(signature, &values);
+            self.get_variadic_func_ref(func_id, call_signature)
+        } else {
+            self.get_func_ref(func_id)
+        };
+
+        let signage = match signature.output.as_slice() {
+            [output] => output.get_signage(),
+            _ => None,
+        };
+
+        cl::Value {
+            raw: cl::ValueItem::Variable(self.builder.ins().call(func_ref, &values)),
+            signage,
+        }
+    }
+
+    fn compile_func_addr(&mut self, value: &FuncAddr) -> cl::Value {
+        let id = self.symbols.addressed(value);
+        let func_ref = self.get_func_ref(self.func_ids[id.0 as usize]);
+        let ptr_type = self.module.target_config().pointer_type();
+
+        cl::Value {
+            raw: cl::ValueItem::Scalar(self.builder.ins().func_addr(ptr_type, func_ref)),
+            signage: None,
+        }
+    }
+
+    fn compile_call_indirect(&mut self, value: &CallIndirect) -> cl::Value {
+        let callee = self.compile_address(&value.callee);
+        let values = self.compile_args(&value.args, &value.signature);
+
+        let signature = if value.signature.variadic {
+            if passes_vector_count(self.module.isa().triple()) {
+                panic!("Cranelift cannot call variadic functions indirectly on {}", self.module.isa().triple());
+            }
+            self.compile_variadic_signature(&value.signature, &values)
+        } else {
+            ty::compile_signature(&value.signature, self.module.isa().triple())
+        };
+        let sig_ref = self.builder.import_signature(signature);
+
+        let signage = match value.signature.output.as_slice() {
+            [output] => output.get_signage(),
+            _ => None,
+        };
+
+        cl::Value {
+            raw: cl::ValueItem::Variable(self.builder.ins().call_indirect(sig_ref, callee, &values)),
+            signage,
+        }
+    }
+
+    fn compile_tail_call(&mut self, value: &Call) {
+        let id = self.symbols.callee(value);
+        let func_ref = self.get_func_ref(self.func_ids[id.0 as usize]);
+        let values = self.compile_args(&value.args, &self.symbols.function(id).signature);
+
+        self.builder.ins().return_call(func_ref, &values);
+    }
+
+    fn compile_tail_call_indirect(&mut self, value: &CallIndirect) {
+        let callee = self.compile_address(&value.callee);
+        let values = self.compile_args(&value.args, &value.signature);
+        let signature = ty::compile_signature(&value.signature, self.module.isa().triple());
+        let sig_ref = self.builder.import_signature(signature);
+
+        self.builder.ins().return_call_indirect(sig_ref, callee, &values);
+    }
+
+    /// Compiles the arguments of a call, applying the default argument promotions of C to those after the inputs of a
+    /// variadic function.
+    fn compile_args(&mut self, args: &[Value], signature: &Signature) -> Vec<cl::Scalar> {
+        if signature.variadic && args.len() < signature.input.len() {
+            panic!("Variadic call passes {} arguments but expected at least {}", args.len(), signature.input.len());
+        }
+
         let mut values = vec![];
-        for arg in &value.args {
+        for (i, arg) in args.iter().enumerate() {
             let value = self.compile_value(arg);
-            self.flatten_value(value, &mut values);
+            if signature.variadic && i >= signature.input.len() {
+                values.push(self.promote_variadic_arg(value));
+            } else {
+                self.flatten_value(value, &mut values);
+            }
         }
-        
+
+        values
+    }
+
+    fn promote_variadic_arg(&mut self, value: cl::Value) -> cl::Scalar {
+        let signage = value.signage;
+        let value = self.to_scalar(value);
+        let ty = ty::ast_type(self.builder.func.dfg.value_type(value), signage)
+            .unwrap_or_else(|| panic!("Type cannot be passed to a variadic function: {:?}", value));
+
+        let promoted = ty.c_promoted();
+        if promoted == ty {
+            return value;
+        }
+
+        let promoted_type = ty::compile_type(&promoted, self.module.isa().triple());
+        match promoted {
+            Type::Scalar(ScalarType::Num(NumType::Float(_))) => self.builder.ins().fpromote(promoted_type, value),
+            _ if signage == Some(true) => self.builder.ins().sextend(promoted_type, value),
+            _ => self.builder.ins().uextend(promoted_type, value),
+        }
+    }
+
+    /// The signature of a call to a variadic function with `signature` that passes `args`, since Cranelift only calls
+    /// functions with a parameter for every argument. The variadic arguments are passed like the inputs on the
+    /// supported targets.
+    fn compile_variadic_signature(&self, signature: &Signature, args: &[cl::Scalar]) -> cl::Signature {
+        let triple = self.module.isa().triple();
+        let supported = match triple.architecture {
+            Architecture::X86_64 => passes_vector_count(triple),
+            // Apple passes variadic arguments on the stack and Windows passes variadic floats in general purpose
+            // registers, while Linux follows the standard procedure call standard
+            Architecture::Aarch64(_) => triple.operating_system == OperatingSystem::Linux,
+            _ => false,
+        };
+        if !supported {
+            panic!("Cranelift cannot call variadic functions on {}", triple);
+        }
+
+        let mut call_signature = ty::compile_signature(signature, triple);
+        for arg in &args[call_signature.params.len()..] {
+            call_signature.params.push(cl::AbiParam::new(self.builder.func.dfg.value_type(*arg)));
+        }
+
+        call_signature
+    }
+
+    /// A reference to the variadic function `func_id` for calls with `signature`.
+    fn get_variadic_func_ref(&mut self, func_id: cl::FuncId, signature: cl::Signature) -> cl::FuncRef {
+        let callee = match passes_vector_count(self.module.isa().triple()) {
+            true => {
+                let vector_count = signature.params.iter()
+                    .filter(|x| x.value_type.is_float() || x.value_type.is_vector())
+                    .count()
+                    .min(8);
+                self.get_variadic_trampoline(func_id, vector_count as u8)
+            }
+            false => func_id,
+        };
+
+        // The callee is declared with the inputs of the variadic function, while every call passes its own arguments
+        let name = cl::UserExternalName::new(0, callee.as_u32());
+        let data = cl::ExtFuncData {
+            name: cl::ExternalName::User(self.builder.func.declare_imported_user_function(name)),
+            signature: self.builder.import_signature(signature),
+            colocated: false,
+        };
+        self.builder.import_function(data)
+    }
+
+    /// A function that calls the variadic function `func_id` with `vector_count` in `al`, shared by every call that
+    /// uses as many vector registers.
+    fn get_variadic_trampoline(&mut self, func_id: cl::FuncId, vector_count: u8) -> cl::FuncId {
+        if let Some(trampoline) = self.trampolines.get(&(func_id, vector_count)) {
+            return *trampoline;
+        }
+
+        // Cranelift cannot set `al` to the number of vector registers used by the call, so it calls a trampoline that
+        // sets it before jumping to the function: `mov al, count; movabs r11, function; jmp r11`
+        let mut code = vec![0xB0, vector_count, 0x49, 0xBB];
+        code.extend(0u64.to_le_bytes());
+        code.extend([0x41, 0xFF, 0xE3]);
+        let reloc = cl::ModuleReloc {
+            offset: 4,
+            kind: cl::Reloc::Abs8,
+            name: cl::ModuleRelocTarget::User { namespace: 0, index: func_id.as_u32() },
+            addend: 0,
+        };
+
+        let signature = self.module.declarations().get_function_decl(func_id).signature.clone();
+        let trampoline = self.module
+            .declare_anonymous_function(&signature)
+            .expect("Failed to declare variadic trampoline");
+        self.module
+            .define_function_bytes(trampoline, 16, &code, &[reloc])
+            .expect("Failed to define variadic trampoline");
+
+        self.trampolines.insert((func_id, vector_count), trampoline);
+        trampoline
+    }
+
+    fn compile_intrinsic(&mut self, value: &Intrinsic) -> cl::Value {
+        if value.args.len() != value.op.arity() {
+            panic!("Intrinsic {:?} takes {} arguments but found {}", value.op, value.op.arity(), value.args.len());
+        }
+
+        let args = value.args.iter().map(|x| self.compile_value(x)).collect::<Vec<_>>();
+        let signage = args[0].signage;
+        let args = args.into_iter().map(|x| self.to_scalar(x)).collect::<Vec<_>>();
+
+        let ty = self.builder.func.dfg.value_type(args[0]);
+        if !ty::ast_type(ty, signage).is_some_and(|x| value.op.supports(x)) {
+            panic!("Intrinsic {:?} does not support values of type {:?}", value.op, ty);
+        }
+
+        // Cranelift has no quadruple precision instructions, so they are computed by libm
+        if ty == cl::F128 {
+            let scalar = self.compile_libm_call(libm_f128_name(value.op), &args);
+            return cl::Value { raw: cl::ValueItem::Scalar(scalar), signage };
+        }
+
+        let signed = signage.unwrap_or(false);
+        let scalar = match (value.op, args.as_slice()) {
+            (IntrinsicOp::Sqrt, &[x]) => self.builder.ins().sqrt(x),
+            (IntrinsicOp::Fma, &[a, b, c]) => self.builder.ins().fma(a, b, c),
+            (IntrinsicOp::Floor, &[x]) => self.builder.ins().floor(x),
+            (IntrinsicOp::Ceil, &[x]) => self.builder.ins().ceil(x),
+            (IntrinsicOp::Trunc, &[x]) => self.builder.ins().trunc(x),
+            (IntrinsicOp::CopySign, &[x, y]) => self.builder.ins().fcopysign(x, y),
+            (IntrinsicOp::Abs, &[x]) if ty.is_float() => self.builder.ins().fabs(x),
+            (IntrinsicOp::Abs, &[x]) => self.builder.ins().iabs(x),
+            (IntrinsicOp::Min, &[x, y]) if ty.is_float() => self.builder.ins().fmin(x, y),
+            (IntrinsicOp::Max, &[x, y]) if ty.is_float() => self.builder.ins().fmax(x, y),
+            // Not every target has 128-bit `smin` and friends, while a select of a comparison becomes them where it can
+            (IntrinsicOp::Min | IntrinsicOp::Max, &[x, y]) => {
+                let op = match (value.op, signed) {
+                    (IntrinsicOp::Min, true) => cl::IntCmpOp::SignedLessThan,
+                    (IntrinsicOp::Min, false) => cl::IntCmpOp::UnsignedLessThan,
+                    (_, true) => cl::IntCmpOp::SignedGreaterThan,
+                    (_, false) => cl::IntCmpOp::UnsignedGreaterThan,
+                };
+                let condition = self.builder.ins().icmp(op, x, y);
+                self.builder.ins().select(condition, x, y)
+            }
+            (IntrinsicOp::CtPop, &[x]) => self.builder.ins().popcnt(x),
+            (IntrinsicOp::Clz, &[x]) => self.builder.ins().clz(x),
+            (IntrinsicOp::Ctz, &[x]) => self.builder.ins().ctz(x),
+            (IntrinsicOp::BSwap, &[x]) => self.builder.ins().bswap(x),
+            _ => unreachable!(),
+        };
+
         cl::Value {
-            raw: cl::ValueItem::Variable(self.builder.ins().call(func_ref, &values)),
-            // TODO: look up function signature to determine signage
-            signage: None,
+            raw: cl::ValueItem::Scalar(scalar),
+            signage,
         }
     }
 
-    fn get_func_ref(&mut self, name: &str) -> Option<cl::FuncRef> {
-        let func_id = self.get_func_id(name)?;
+    fn compile_libm_call(&mut self, name: &str, args: &[cl::Scalar]) -> cl::Scalar {
+        let ty = self.builder.func.dfg.value_type(args[0]);
+        let mut signature = self.module.make_signature();
+        signature.params = args.iter().map(|_| cl::AbiParam::new(ty)).collect();
+        signature.returns.push(cl::AbiParam::new(ty));
+
+        // The JIT module resolves imported functions among the symbols of the process, which libm is linked into
+        let func_id = self.module
+            .declare_function(name, cl::Linkage::Import, &signature)
+            .unwrap_or_else(|e| panic!("Failed to declare libm function '{}': {:?}", name, e));
+        let func_ref = self.get_func_ref(func_id);
+
+        let call = self.builder.ins().call(func_ref, args);
+        self.builder.inst_results(call)[0]
+    }
+
+    fn get_func_ref(&mut self, func_id: cl::FuncId) -> cl::FuncRef {
         if let Some(func_ref) = self.imported_functions.get(&func_id) {
-            return Some(*func_ref);
+            return *func_ref;
         }
 
-        let name = cl::UserExternalName::new(self.namespace, func_id.as_u32());
+        // Cranelift modules reserve namespace 0 for functions when resolving relocations
+        let name = cl::UserExternalName::new(0, func_id.as_u32());
         let name_ref = self.builder.func.declare_imported_user_function(name);
 
-        let signature = &self.module.get_function_decl(func_id).signature;
+        let signature = &self.module.declarations().get_function_decl(func_id).signature;
         let sig_ref = self.builder.func.import_signature(signature.clone());
 
         let data = cl::ExtFuncData {
@@ -276,18 +1299,23 @@ impl Scope<'_> {
         let func_ref = self.builder.func.import_function(data);
         self.imported_functions.insert(func_id, func_ref);
 
-        Some(func_ref)
+        func_ref
     }
 
-    fn get_func_id(&self, name: &str) -> Option<cl::FuncId> {
-        match self.module.get_name(name)? {
-            FuncOrDataId::Func(x) => Some(x),
-            FuncOrDataId::Data(_) => None,
-        }
+    pub fn finish(mut self) {
+        // Merge blocks that nothing branches to are never sealed while compiling
+        self.builder.seal_all_blocks();
+        self.builder.finalize();
     }
 
-    pub fn finish(self) {
-        self.builder.finalize();
+    fn to_scalar(&self, value: cl::Value) -> cl::Scalar {
+        match value.raw {
+            cl::ValueItem::Scalar(x) => x,
+            cl::ValueItem::Variable(x) => match self.builder.inst_results(x) {
+                [x] => *x,
+                _ => panic!("Expected a single value but found {} values", self.builder.inst_results(x).len()),
+            },
+        }
     }
 
     fn flatten_value(&self, value: cl::Value, values: &mut Vec<cl::Scalar>) {
@@ -305,15 +1333,90 @@ impl Scope<'_> {
 }
         
 impl Cranelift {
-    pub(crate) fn create_scope(&mut self, func_id: cl::FuncId, signature: cl::Signature) -> Scope {
-        let name = cl::UserFuncName::user(self.namespace, func_id.as_u32());
+    pub(crate) fn create_scope<'m>(
+        &'m mut self,
+        symbols: &'m SymbolTable,
+        func_ids: &'m [cl::FuncId],
+        func_id: cl::FuncId,
+        signature: cl::Signature,
+    ) -> Scope<'m, 'm> {
+        let name = cl::UserFuncName::user(0, func_id.as_u32());
         self.function = Some(cl::Function::with_name_signature(name, signature));
 
         Scope {
-            module: &self.module,
+            module: &mut self.module,
+            symbols,
+            func_ids,
+            trampolines: &mut self.trampolines,
             builder: cl::FunctionBuilder::new(self.function.as_mut().unwrap(), &mut self.context),
-            namespace: self.namespace,
             imported_functions: HashMap::new(),
+            params: Vec::new(),
+            levels: Vec::new(),
+            variable_count: 0,
         }
     }
+}
+
+// The codes reserved by the AST have the same values as the codes reserved by Cranelift
+fn compile_trap_code(code: TrapCode) -> cl::TrapCode {
+    cl::TrapCode::from_raw(NonZeroU8::new(code.get()).unwrap())
+}
+
+fn libm_f128_name(op: IntrinsicOp) -> &'static str {
+    match op {
+        IntrinsicOp::Sqrt => "sqrtf128",
+        IntrinsicOp::Fma => "fmaf128",
+        IntrinsicOp::Floor => "floorf128",
+        IntrinsicOp::Ceil => "ceilf128",
+        IntrinsicOp::Trunc => "truncf128",
+        IntrinsicOp::CopySign => "copysignf128",
+        IntrinsicOp::Abs => "fabsf128",
+        IntrinsicOp::Min => "fminimumf128",
+        IntrinsicOp::Max => "fmaximumf128",
+        IntrinsicOp::CtPop | IntrinsicOp::Clz | IntrinsicOp::Ctz | IntrinsicOp::BSwap => {
+            unreachable!("Integer intrinsic {:?} has no libm function", op)
+        }
+    }
+}
+
+fn literal_length(len: &Value) -> Option<u64> {
+    match strip_spans(len) {
+        Value::Lit(Lit::Num(Num::Int(Int::Unsigned(UnsignedInt::U64(x))))) => Some(*x),
+        _ => None,
+    }
+}
+
+/// The alignment to pass to Cranelift's small memory helpers, which reject lengths not a multiple of the alignment.
+fn small_memory_align(align: u32, len: u64) -> u8 {
+    let len_align = 1 << len.trailing_zeros().min(7);
+    align.min(len_align) as u8
+}
+
+/// Whether callers of variadic functions pass an upper bound on the number of vector registers used in `al`, as the
+/// System V ABI does on x86-64.
+fn passes_vector_count(triple: &Triple) -> bool {
+    triple.architecture == Architecture::X86_64 && triple.default_calling_convention() == Ok(CallingConvention::SystemV)
+}
+
+fn strip_spans<'v, 's>(value: &'v Value<'s>) -> &'v Value<'s> {
+    match value {
+        Value::Spanned(x) => strip_spans(&x.node),
+        _ => value,
+    }
+}
+
+struct Level {
+    values: HashMap<String, StoredValue>,
+}
+
+impl Level {
+    fn new() -> Self {
+        Level { values: HashMap::new() }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct StoredValue {
+    variable: cl::Variable,
+    signage: Option<bool>,
 }
\ No newline at end of file
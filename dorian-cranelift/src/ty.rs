@@ -1,53 +1,100 @@
-use ast::function::Signature;
+use ast::function::{CallConv, Signature};
 use crate::{cl, Cranelift};
-use ast::ty::{Type, FloatType, IntWidth, NumType, ScalarType};
+use ast::ty::{Type, BoolType, FloatType, IntType, IntWidth, NumType, PtrType, ScalarType};
+use target_lexicon::Triple;
 
 impl Cranelift {
-    pub(crate) fn compile_type(&self, ty: &Type) -> cl::Type {
-        match ty {
-            Type::Scalar(x) => self.compile_scalar_type(x),
-            Type::Vector(x) => self.compile_scalar_type(&x.elem).by(x.len).unwrap(),
-        }
+    pub(crate) fn compile_signature(&self, signature: &Signature) -> cl::Signature {
+        compile_signature(signature, &self.triple)
     }
-    
-    fn compile_scalar_type(&self, ty: &ScalarType) -> cl::Type {
-        match ty {
-            ScalarType::Num(x) => self.compile_num_type(x),
-            ScalarType::Bool(_) => cl::I8,
-            ScalarType::Ptr(_) => cl::Type::triple_pointer_type(&self.triple),
-        }
+}
+
+/// The Cranelift signature of `signature` on `triple`, which decides the C calling convention and pointer width.
+pub(crate) fn compile_signature(signature: &Signature, triple: &Triple) -> cl::Signature {
+    if signature.variadic && signature.call_conv != CallConv::C {
+        panic!("Variadic functions must use the C calling convention but found {:?}", signature.call_conv);
     }
-    
-    fn compile_num_type(&self, num: &NumType) -> cl::Type {
-        match num {
-            NumType::Int(x) => match x.width {
-                IntWidth::I8 => cl::I8,
-                IntWidth::I16 => cl::I16,
-                IntWidth::I32 => cl::I32,
-                IntWidth::I64 => cl::I64,
-                IntWidth::I128 => cl::I128,
-            }
-            NumType::Float(x) => match x {
-                FloatType::F16 => cl::F16,
-                FloatType::F32 => cl::F32,
-                FloatType::F64 => cl::F64,
-                FloatType::F128 => cl::F128,
-            }
-        }
+
+    let params = signature.input.iter()
+        .map(|x| cl::AbiParam::new(compile_type(x, triple)))
+        .collect();
+    let returns = signature.output.iter()
+        .map(|x| cl::AbiParam::new(compile_type(x, triple)))
+        .collect();
+
+    cl::Signature {
+        params,
+        returns,
+        call_conv: compile_call_conv(signature.call_conv, triple),
     }
-    
-    pub(crate) fn compile_signature(&self, signature: &Signature) -> cl::Signature {
-        let params = signature.input.iter()
-            .map(|x| cl::AbiParam::new(self.compile_type(x)))
-            .collect();
-        let returns = signature.output.iter()
-            .map(|x| cl::AbiParam::new(self.compile_type(x)))
-            .collect();
-        
-        cl::Signature {
-            params,
-            returns,
-            call_conv: cl::CallConv::Fast,
+}
+
+fn compile_call_conv(call_conv: CallConv, triple: &Triple) -> cl::CallConv {
+    match call_conv {
+        CallConv::C => cl::CallConv::triple_default(triple),
+        CallConv::Fast => cl::CallConv::Fast,
+        CallConv::Cold => cl::CallConv::Cold,
+        CallConv::Tail => cl::CallConv::Tail,
+    }
+}
+
+/// The Cranelift type of `ty` on `triple`, which only matters for the width of pointers.
+pub(crate) fn compile_type(ty: &Type, triple: &Triple) -> cl::Type {
+    match ty {
+        Type::Scalar(x) => compile_scalar_type(x, triple),
+        Type::Vector(x) => compile_scalar_type(&x.elem, triple).by(x.len).unwrap(),
+    }
+}
+
+fn compile_scalar_type(ty: &ScalarType, triple: &Triple) -> cl::Type {
+    match ty {
+        ScalarType::Num(x) => compile_num_type(x),
+        ScalarType::Bool(_) => cl::I8,
+        ScalarType::Ptr(_) => cl::Type::triple_pointer_type(triple),
+    }
+}
+
+fn compile_num_type(num: &NumType) -> cl::Type {
+    match num {
+        NumType::Int(x) => match x.width {
+            IntWidth::I8 => cl::I8,
+            IntWidth::I16 => cl::I16,
+            IntWidth::I32 => cl::I32,
+            IntWidth::I64 => cl::I64,
+            IntWidth::I128 => cl::I128,
+        }
+        NumType::Float(x) => match x {
+            FloatType::F16 => cl::F16,
+            FloatType::F32 => cl::F32,
+            FloatType::F64 => cl::F64,
+            FloatType::F128 => cl::F128,
         }
     }
 }
+
+/// The AST type of a value of type `ty` with `signage`, where integers without a signage are booleans or pointers.
+pub(crate) fn ast_type(ty: cl::Type, signage: Option<bool>) -> Option<Type> {
+    let scalar: ScalarType = match (ty, signage) {
+        (cl::I8, None) => BoolType.into(),
+        // Pointers are the only wider integers without signage
+        (cl::I32 | cl::I64, None) => PtrType.into(),
+        (cl::F16, _) => FloatType::F16.into(),
+        (cl::F32, _) => FloatType::F32.into(),
+        (cl::F64, _) => FloatType::F64.into(),
+        (cl::F128, _) => FloatType::F128.into(),
+        (_, Some(signed)) => {
+            let width = match ty {
+                cl::I8 => IntWidth::I8,
+                cl::I16 => IntWidth::I16,
+                cl::I32 => IntWidth::I32,
+                cl::I64 => IntWidth::I64,
+                cl::I128 => IntWidth::I128,
+                _ => return None,
+            };
+            IntType { width, signed }.into()
+        }
+        _ => return None,
+    };
+
+    Some(scalar.into())
+}
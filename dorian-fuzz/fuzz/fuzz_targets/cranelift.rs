@@ -0,0 +1,10 @@
+#![no_main]
+
+use dorian::prelude::*;
+use dorian_fuzz::ArbitraryModule;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: ArbitraryModule| {
+    let mut cranelift = Cranelift::new();
+    cranelift.compile_module(&input.0).expect("Generated modules should compile");
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use dorian::prelude::*;
+use dorian_fuzz::ArbitraryModule;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: ArbitraryModule| {
+    let mut llvm = Llvm::new();
+    let module = llvm.compile_module(&input.0).expect("Generated modules should compile");
+
+    if let Err(error) = module.verify() {
+        panic!("LLVM module failed verification: {}", error.to_string());
+    }
+});
@@ -0,0 +1,567 @@
+use arbitrary::{Result, Unstructured};
+use ast::block::Block;
+use ast::block::stmt::{AssignStmt, BindStmt, BindTupleStmt, IfElse, IfStmt, ReturnStmt, Stmt, WhileStmt};
+use ast::function::{Function, Signature};
+use ast::module::Module;
+use ast::ty::{BoolType, FloatType, IntType, IntWidth, NumType, ScalarType, Type};
+use ast::val::{
+    Bin, BinOp, Call, Checked, CheckedOp, ExtractElement, Float, FloatCmp, FloatCmpOp, InsertElement, Int, Intrinsic,
+    IntrinsicOp, Lit, OnOverflow, Reduce, ReduceOp, Shuffle, Splat, Una, UnaOp, Value, Vector,
+};
+use ast::val::util::{arg, var};
+
+/// Bounds on the size of generated modules.
+///
+/// Generated functions must run the same on every backend, so these are never generated:
+/// - `no_wrap` arithmetic, whose overflow is undefined in LLVM
+/// - vectors that are not 128 bits wide, which Cranelift cannot lower, and vector division, shifts and comparisons
+/// - traps, unreachable statements, asserts and trapping arithmetic, which end the run
+/// - pointers, memory and atomic operations, function addresses, imports and try statements, which need memory or
+///   functions from outside the module
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Limits {
+    pub functions: usize,
+    pub params: usize,
+    pub stmts: usize,
+    pub block_depth: usize,
+    pub value_depth: usize,
+    pub loop_iterations: u32,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            functions: 4,
+            params: 4,
+            stmts: 8,
+            block_depth: 3,
+            value_depth: 4,
+            loop_iterations: 8,
+        }
+    }
+}
+
+// 128-bit integers and 16/128-bit floats are left out as neither backend supports them fully on every host.
+// The integer types must stay first, followed by the float types and then `BoolType`, so each kind can be sliced off.
+const TYPES: [Type; 11] = [
+    Type::Scalar(ScalarType::Num(NumType::Int(IntType::S8))),
+    Type::Scalar(ScalarType::Num(NumType::Int(IntType::S16))),
+    Type::Scalar(ScalarType::Num(NumType::Int(IntType::S32))),
+    Type::Scalar(ScalarType::Num(NumType::Int(IntType::S64))),
+    Type::Scalar(ScalarType::Num(NumType::Int(IntType::U8))),
+    Type::Scalar(ScalarType::Num(NumType::Int(IntType::U16))),
+    Type::Scalar(ScalarType::Num(NumType::Int(IntType::U32))),
+    Type::Scalar(ScalarType::Num(NumType::Int(IntType::U64))),
+    Type::Scalar(ScalarType::Num(NumType::Float(FloatType::F32))),
+    Type::Scalar(ScalarType::Num(NumType::Float(FloatType::F64))),
+    Type::Scalar(ScalarType::Bool(BoolType)),
+];
+
+const INTRINSIC_OPS: [IntrinsicOp; 13] = [
+    IntrinsicOp::Sqrt,
+    IntrinsicOp::Fma,
+    IntrinsicOp::Floor,
+    IntrinsicOp::Ceil,
+    IntrinsicOp::Trunc,
+    IntrinsicOp::CopySign,
+    IntrinsicOp::Abs,
+    IntrinsicOp::Min,
+    IntrinsicOp::Max,
+    IntrinsicOp::CtPop,
+    IntrinsicOp::Clz,
+    IntrinsicOp::Ctz,
+    IntrinsicOp::BSwap,
+];
+
+const FLOAT_CMP_OPS: [FloatCmpOp; 14] = [
+    FloatCmpOp::Ordered,
+    FloatCmpOp::Unordered,
+    FloatCmpOp::OrderedEq,
+    FloatCmpOp::OrderedNe,
+    FloatCmpOp::OrderedLt,
+    FloatCmpOp::OrderedGt,
+    FloatCmpOp::OrderedLe,
+    FloatCmpOp::OrderedGe,
+    FloatCmpOp::UnorderedEq,
+    FloatCmpOp::UnorderedNe,
+    FloatCmpOp::UnorderedLt,
+    FloatCmpOp::UnorderedGt,
+    FloatCmpOp::UnorderedLe,
+    FloatCmpOp::UnorderedGe,
+];
+
+const COUNTER_TYPE: IntType = IntType::U32;
+
+pub(crate) struct Generator<'u, 'a> {
+    u: &'u mut Unstructured<'a>,
+    limits: Limits,
+    functions: Vec<(String, Signature)>,
+    params: Vec<Type>,
+    output: Type,
+    scopes: Vec<Vec<Binding>>,
+    next_name: usize,
+}
+
+struct Binding {
+    name: String,
+    ty: Type,
+    mutable: bool,
+}
+
+impl<'u, 'a> Generator<'u, 'a> {
+    pub(crate) fn new(u: &'u mut Unstructured<'a>, limits: Limits) -> Self {
+        Generator {
+            u,
+            limits,
+            functions: Vec::new(),
+            params: Vec::new(),
+            output: TYPES[0],
+            scopes: Vec::new(),
+            next_name: 0,
+        }
+    }
+
+    pub(crate) fn module(mut self) -> Result<Module<'static>> {
+        let mut module = Module::new("fuzz");
+
+        let count = self.u.int_in_range(1..=self.limits.functions.max(1))?;
+        for i in 0..count {
+            module.add_function(self.function(format!("f{}", i))?);
+        }
+
+        Ok(module)
+    }
+
+    fn function(&mut self, name: String) -> Result<Function<'static>> {
+        let param_count = self.u.int_in_range(0..=self.limits.params)?;
+        self.params = (0..param_count).map(|_| self.ty()).collect::<Result<_>>()?;
+        self.output = self.ty()?;
+        self.scopes.clear();
+
+        let mut body = self.block(0)?;
+        if !matches!(body.stmts.last(), Some(Stmt::Return(_))) {
+            body.stmts.push(self.return_stmt()?);
+        }
+
+        let mut function = Function::new(name.clone()).with_block(body);
+        for param in &self.params {
+            function = function.add_input(*param);
+        }
+        function = function.add_output(self.output);
+
+        // Functions may only call the functions generated before them, so every call graph is acyclic
+        self.functions.push((name, function.signature.clone()));
+
+        Ok(function)
+    }
+
+    fn ty(&mut self) -> Result<Type> {
+        self.u.choose(&TYPES).copied()
+    }
+
+    fn fresh_name(&mut self, prefix: &str) -> String {
+        self.next_name += 1;
+        format!("{}{}", prefix, self.next_name)
+    }
+
+    fn block(&mut self, depth: usize) -> Result<Block<'static>> {
+        self.scopes.push(Vec::new());
+        let stmts = self.stmts(depth, Vec::new());
+        self.scopes.pop();
+
+        Ok(Block { stmts: stmts? })
+    }
+
+    fn stmts(&mut self, depth: usize, mut stmts: Vec<Stmt<'static>>) -> Result<Vec<Stmt<'static>>> {
+        let count = self.u.int_in_range(0..=self.limits.stmts)?;
+        for _ in 0..count {
+            let nested = depth < self.limits.block_depth;
+            match self.u.int_in_range(0..=10)? {
+                0..=2 => stmts.push(self.bind_stmt()?),
+                10 => stmts.push(self.bind_checked_stmt()?),
+                3..=4 if self.has_mutable_binding() => stmts.push(self.assign_stmt()?),
+                5..=6 if nested => stmts.push(self.if_stmt(depth)?),
+                7..=8 if nested => {
+                    let (counter, loop_stmt) = self.while_stmt(depth)?;
+                    stmts.push(counter);
+                    stmts.push(loop_stmt);
+                }
+                9 => {
+                    stmts.push(self.return_stmt()?);
+                    break;
+                }
+                _ => stmts.push(self.bind_stmt()?),
+            }
+        }
+
+        Ok(stmts)
+    }
+
+    fn bind_stmt(&mut self) -> Result<Stmt<'static>> {
+        let ty = self.ty()?;
+        let value = self.value(ty, 0)?;
+
+        // Occasionally rebind a visible name so shadowing is exercised as well
+        let name = match self.visible_bindings(|_| true).last() {
+            Some(x) if self.u.ratio(1, 4)? => x.clone(),
+            _ => self.fresh_name("v"),
+        };
+
+        self.bind(name.clone(), ty, true);
+        Ok(Stmt::Bind(BindStmt { name: name.into(), value }))
+    }
+
+    /// Binds the wrapped result of checked arithmetic on integers and whether it overflowed.
+    fn bind_checked_stmt(&mut self) -> Result<Stmt<'static>> {
+        let ty = *self.u.choose(&TYPES[..8])?;
+        let op = *self.u.choose(&[CheckedOp::Add, CheckedOp::Sub, CheckedOp::Mul])?;
+        let value = Checked {
+            lhs: self.value(ty, 0)?,
+            rhs: self.value(ty, 0)?,
+            op,
+            on_overflow: OnOverflow::Flag,
+        };
+
+        let names = [self.fresh_name("v"), self.fresh_name("o")];
+        self.bind(names[0].clone(), ty, true);
+        self.bind(names[1].clone(), BoolType.into(), true);
+
+        Ok(Stmt::BindTuple(BindTupleStmt { names: names.map(Into::into).into(), value: value.into() }))
+    }
+
+    fn assign_stmt(&mut self) -> Result<Stmt<'static>> {
+        let candidates = self.visible_bindings(|x| x.mutable);
+        let name = self.u.choose(&candidates)?.clone();
+        let ty = self.binding_type(&name);
+
+        Ok(Stmt::Assign(AssignStmt {
+            var: var(name),
+            value: self.value(ty, 0)?,
+        }))
+    }
+
+    fn if_stmt(&mut self, depth: usize) -> Result<Stmt<'static>> {
+        let condition = self.value(BoolType.into(), 0)?;
+        let then_block = self.block(depth + 1)?;
+        let if_else = match self.u.int_in_range(0..=2)? {
+            0 => None,
+            1 => Some(IfElse::Else(self.block(depth + 1)?)),
+            _ if depth + 1 >= self.limits.block_depth => Some(IfElse::Else(self.block(depth + 1)?)),
+            _ => match self.if_stmt(depth + 1)? {
+                Stmt::If(x) => Some(IfElse::If(Box::new(x))),
+                _ => unreachable!(),
+            },
+        };
+
+        Ok(Stmt::If(IfStmt { condition, then_block, if_else }))
+    }
+
+    /// Generates a loop guarded by a fresh counter, which is incremented before anything else in the loop body so
+    /// the loop runs at most `Limits::loop_iterations` times.
+    fn while_stmt(&mut self, depth: usize) -> Result<(Stmt<'static>, Stmt<'static>)> {
+        let counter = self.fresh_name("i");
+        let bound = self.u.int_in_range(0..=self.limits.loop_iterations)?;
+
+        let counter_stmt = Stmt::Bind(BindStmt {
+            name: counter.clone().into(),
+            value: int_lit(COUNTER_TYPE, 0).into(),
+        });
+        self.bind(counter.clone(), COUNTER_TYPE.into(), false);
+
+        let increment = Stmt::Assign(AssignStmt {
+            var: var(counter.clone()),
+            value: bin(BinOp::Add, var(counter.clone()), int_lit(COUNTER_TYPE, 1).into()),
+        });
+
+        self.scopes.push(Vec::new());
+        let stmts = self.stmts(depth + 1, vec![increment]);
+        self.scopes.pop();
+
+        let loop_stmt = Stmt::While(WhileStmt {
+            condition: bin(BinOp::Lt, var(counter), int_lit(COUNTER_TYPE, bound as i64).into()),
+            loop_block: Block { stmts: stmts? },
+        });
+
+        Ok((counter_stmt, loop_stmt))
+    }
+
+    fn return_stmt(&mut self) -> Result<Stmt<'static>> {
+        Ok(Stmt::Return(ReturnStmt {
+            values: vec![self.value(self.output, 0)?],
+        }))
+    }
+
+    fn bind(&mut self, name: String, ty: Type, mutable: bool) {
+        if self.scopes.is_empty() {
+            self.scopes.push(Vec::new());
+        }
+
+        self.scopes.last_mut().unwrap().push(Binding { name, ty, mutable });
+    }
+
+    /// Returns the names of bindings that are not shadowed and satisfy `predicate`.
+    fn visible_bindings(&self, predicate: impl Fn(&Binding) -> bool) -> Vec<String> {
+        let mut seen = Vec::<&str>::new();
+        let mut names = Vec::new();
+        for binding in self.scopes.iter().rev().flat_map(|x| x.iter().rev()) {
+            if seen.contains(&binding.name.as_str()) {
+                continue;
+            }
+            seen.push(&binding.name);
+
+            if predicate(binding) {
+                names.push(binding.name.clone());
+            }
+        }
+
+        names
+    }
+
+    fn has_mutable_binding(&self) -> bool {
+        !self.visible_bindings(|x| x.mutable).is_empty()
+    }
+
+    fn binding_type(&self, name: &str) -> Type {
+        self.scopes.iter()
+            .rev()
+            .flat_map(|x| x.iter().rev())
+            .find(|x| x.name == name)
+            .map(|x| x.ty)
+            .unwrap()
+    }
+
+    fn value(&mut self, ty: Type, depth: usize) -> Result<Value<'static>> {
+        if depth >= self.limits.value_depth || self.u.ratio(1, 3)? {
+            return self.leaf(ty);
+        }
+
+        let callees = self.functions.iter()
+            .filter(|(_, signature)| signature.output == [ty])
+            .cloned()
+            .collect::<Vec<_>>();
+        if !callees.is_empty() && self.u.ratio(1, 4)? {
+            let (name, signature) = self.u.choose(&callees)?.clone();
+            let args = signature.input.iter()
+                .map(|x| self.value(*x, depth + 1))
+                .collect::<Result<Vec<_>>>()?;
+
+            return Ok(Call { function_name: name.into(), args }.into());
+        }
+
+        match ty {
+            Type::Scalar(ScalarType::Num(NumType::Int(x))) => self.int_expr(x, depth),
+            Type::Scalar(ScalarType::Num(NumType::Float(x))) => self.float_expr(x, depth),
+            Type::Scalar(ScalarType::Bool(_)) => self.bool_expr(depth),
+            _ => unreachable!("Only scalar number and bool types are generated"),
+        }
+    }
+
+    fn leaf(&mut self, ty: Type) -> Result<Value<'static>> {
+        let args = self.params.iter()
+            .enumerate()
+            .filter(|(_, x)| **x == ty)
+            .map(|(i, _)| i as u32)
+            .collect::<Vec<_>>();
+        let vars = self.visible_bindings(|x| x.ty == ty);
+
+        match self.u.int_in_range(0..=2)? {
+            0 if !args.is_empty() => Ok(arg(*self.u.choose(&args)?)),
+            1 if !vars.is_empty() => Ok(var(self.u.choose(&vars)?.clone())),
+            _ => self.lit(ty),
+        }
+    }
+
+    fn lit(&mut self, ty: Type) -> Result<Value<'static>> {
+        let lit = match ty {
+            Type::Scalar(ScalarType::Num(NumType::Int(x))) => int_lit(x, self.u.arbitrary()?),
+            Type::Scalar(ScalarType::Num(NumType::Float(FloatType::F32))) => Float::F32(self.u.arbitrary()?).into(),
+            Type::Scalar(ScalarType::Num(NumType::Float(_))) => Float::F64(self.u.arbitrary()?).into(),
+            Type::Scalar(ScalarType::Bool(_)) => Lit::Bool(self.u.arbitrary()?),
+            _ => unreachable!("Only scalar number and bool types are generated"),
+        };
+
+        Ok(lit.into())
+    }
+
+    fn int_expr(&mut self, ty: IntType, depth: usize) -> Result<Value<'static>> {
+        let bits = int_bits(ty.width);
+
+        match self.u.int_in_range(0..=5)? {
+            0 => {
+                let ops = [BinOp::Add, BinOp::Sub, BinOp::Mul, BinOp::BitAnd, BinOp::BitOr, BinOp::BitXor];
+                let op = *self.u.choose(&ops)?;
+                Ok(bin(op, self.value(ty.into(), depth + 1)?, self.value(ty.into(), depth + 1)?))
+            }
+            // Division by zero and signed `MIN / -1` are undefined, so the divisor is always a safe literal
+            1 => {
+                let op = *self.u.choose(&[BinOp::Div, BinOp::Rem])?;
+                let magnitude = (self.u.int_in_range(1..=i64::MAX)? >> (64 - bits)).max(1);
+                let divisor = if ty.signed && self.u.arbitrary()? {
+                    -magnitude.max(2)
+                } else {
+                    magnitude
+                };
+
+                Ok(bin(op, self.value(ty.into(), depth + 1)?, int_lit(ty, divisor).into()))
+            }
+            // Shifting by the bit width or more is poison in LLVM but masked in Cranelift
+            2 => {
+                let op = *self.u.choose(&[BinOp::Shl, BinOp::Shr])?;
+                let amount = self.u.int_in_range(0..=bits - 1)?;
+
+                Ok(bin(op, self.value(ty.into(), depth + 1)?, int_lit(ty, amount as i64).into()))
+            }
+            3 => self.intrinsic(ty.into(), depth),
+            4 => self.vector_expr(ty.into(), depth),
+            _ => {
+                let op = *self.u.choose(&[UnaOp::Neg, UnaOp::Not])?;
+                Ok(una(op, self.value(ty.into(), depth + 1)?))
+            }
+        }
+    }
+
+    fn float_expr(&mut self, ty: FloatType, depth: usize) -> Result<Value<'static>> {
+        if self.u.ratio(1, 4)? {
+            return Ok(una(UnaOp::Neg, self.value(ty.into(), depth + 1)?));
+        }
+        if self.u.ratio(1, 4)? {
+            return self.intrinsic(ty.into(), depth);
+        }
+        if self.u.ratio(1, 4)? {
+            return self.vector_expr(ty.into(), depth);
+        }
+
+        let op = *self.u.choose(&[BinOp::Add, BinOp::Sub, BinOp::Mul, BinOp::Div, BinOp::Rem])?;
+        Ok(bin(op, self.value(ty.into(), depth + 1)?, self.value(ty.into(), depth + 1)?))
+    }
+
+    fn intrinsic(&mut self, ty: Type, depth: usize) -> Result<Value<'static>> {
+        let ops = INTRINSIC_OPS.iter().filter(|x| x.supports(ty)).copied().collect::<Vec<_>>();
+        let op = *self.u.choose(&ops)?;
+        let args = (0..op.arity()).map(|_| self.value(ty, depth + 1)).collect::<Result<_>>()?;
+
+        Ok(Intrinsic { op, args }.into())
+    }
+
+    /// Generates a value of `ty` that reduces a vector or extracts one of its lanes.
+    fn vector_expr(&mut self, ty: Type, depth: usize) -> Result<Value<'static>> {
+        let vector = self.vector(ty, depth + 1)?;
+        if self.u.arbitrary()? {
+            let op = *self.u.choose(&[ReduceOp::Sum, ReduceOp::Min, ReduceOp::Max])?;
+            return Ok(Reduce { vector, op }.into());
+        }
+
+        let index = self.u.int_in_range(0..=lanes(ty) - 1)?;
+        Ok(ExtractElement { vector, index }.into())
+    }
+
+    /// Generates a 128-bit vector with lanes of `ty`.
+    fn vector(&mut self, ty: Type, depth: usize) -> Result<Value<'static>> {
+        let len = lanes(ty);
+        let nested = depth < self.limits.value_depth;
+
+        match self.u.int_in_range(0..=4)? {
+            0 if nested => {
+                let ops: &[BinOp] = match ty {
+                    Type::Scalar(ScalarType::Num(NumType::Float(_))) => {
+                        &[BinOp::Add, BinOp::Sub, BinOp::Mul, BinOp::Div, BinOp::Rem]
+                    }
+                    _ => &[BinOp::Add, BinOp::Sub, BinOp::Mul, BinOp::BitAnd, BinOp::BitOr, BinOp::BitXor],
+                };
+                let op = *self.u.choose(ops)?;
+
+                Ok(bin(op, self.vector(ty, depth + 1)?, self.vector(ty, depth + 1)?))
+            }
+            1 if nested => {
+                let (lhs, rhs) = (self.vector(ty, depth + 1)?, self.vector(ty, depth + 1)?);
+                let mask = (0..len).map(|_| self.u.int_in_range(0..=2 * len - 1)).collect::<Result<_>>()?;
+
+                Ok(Shuffle { lhs, rhs, mask }.into())
+            }
+            2 if nested => {
+                let vector = self.vector(ty, depth + 1)?;
+                let value = self.value(ty, depth + 1)?;
+                let index = self.u.int_in_range(0..=len - 1)?;
+
+                Ok(InsertElement { vector, value, index }.into())
+            }
+            3 => Ok(Splat { value: self.value(ty, depth + 1)?, len }.into()),
+            // The lanes are leaves, as there may be many of them
+            _ => {
+                let elems = (0..len).map(|_| self.leaf(ty)).collect::<Result<_>>()?;
+                Ok(Vector { elems }.into())
+            }
+        }
+    }
+
+    fn bool_expr(&mut self, depth: usize) -> Result<Value<'static>> {
+        match self.u.int_in_range(0..=3)? {
+            0 => {
+                let ty = *self.u.choose(&TYPES[..TYPES.len() - 1])?;
+                let op = *self.u.choose(&[BinOp::Eq, BinOp::Ne, BinOp::Lt, BinOp::Gt, BinOp::Le, BinOp::Ge])?;
+
+                Ok(bin(op, self.value(ty, depth + 1)?, self.value(ty, depth + 1)?))
+            }
+            1 => {
+                let op = *self.u.choose(&[BinOp::And, BinOp::Or])?;
+                Ok(bin(op, self.value(BoolType.into(), depth + 1)?, self.value(BoolType.into(), depth + 1)?))
+            }
+            2 => {
+                let ty = *self.u.choose(&TYPES[8..TYPES.len() - 1])?;
+                if self.u.ratio(1, 4)? {
+                    return Ok(una(UnaOp::IsNan, self.value(ty, depth + 1)?));
+                }
+
+                let op = *self.u.choose(&FLOAT_CMP_OPS)?;
+                let (lhs, rhs) = (self.value(ty, depth + 1)?, self.value(ty, depth + 1)?);
+                Ok(FloatCmp { lhs, rhs, op }.into())
+            }
+            _ => Ok(una(UnaOp::Not, self.value(BoolType.into(), depth + 1)?)),
+        }
+    }
+}
+
+fn bin(op: BinOp, lhs: Value<'static>, rhs: Value<'static>) -> Value<'static> {
+    Bin { lhs, rhs, op, no_wrap: false }.into()
+}
+
+fn una(op: UnaOp, operand: Value<'static>) -> Value<'static> {
+    Una { operand, op, no_wrap: false }.into()
+}
+
+/// The number of lanes of `ty` in a 128-bit vector.
+fn lanes(ty: Type) -> u32 {
+    match ty {
+        Type::Scalar(ScalarType::Num(NumType::Int(x))) => 128 / int_bits(x.width),
+        Type::Scalar(ScalarType::Num(NumType::Float(FloatType::F32))) => 4,
+        Type::Scalar(ScalarType::Num(NumType::Float(_))) => 2,
+        _ => unreachable!("Only vectors of generated number types are generated"),
+    }
+}
+
+fn int_bits(width: IntWidth) -> u32 {
+    match width {
+        IntWidth::I8 => 8,
+        IntWidth::I16 => 16,
+        IntWidth::I32 => 32,
+        IntWidth::I64 => 64,
+        IntWidth::I128 => 128,
+    }
+}
+
+fn int_lit(ty: IntType, x: i64) -> Lit {
+    let int: Int = match (ty.width, ty.signed) {
+        (IntWidth::I8, true) => (x as i8).into(),
+        (IntWidth::I16, true) => (x as i16).into(),
+        (IntWidth::I32, true) => (x as i32).into(),
+        (IntWidth::I64, true) => x.into(),
+        (IntWidth::I128, true) => (x as i128).into(),
+        (IntWidth::I8, false) => (x as u8).into(),
+        (IntWidth::I16, false) => (x as u16).into(),
+        (IntWidth::I32, false) => (x as u32).into(),
+        (IntWidth::I64, false) => (x as u64).into(),
+        (IntWidth::I128, false) => (x as u128).into(),
+    };
+
+    int.into()
+}
@@ -0,0 +1,30 @@
+//! Generation of random but well-typed Dorian modules for fuzzing the backends.
+//!
+//! The `cargo fuzz` targets live in `fuzz/` and are run from this crate's directory, e.g.
+//! `cargo +nightly fuzz run cranelift` or `cargo +nightly fuzz run llvm`.
+
+extern crate dorian_ast as ast;
+
+use arbitrary::{Arbitrary, Unstructured};
+use ast::module::Module;
+
+mod generator;
+
+pub use generator::Limits;
+use generator::Generator;
+
+/// A random module in which every value is well-typed and every loop terminates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArbitraryModule(pub Module<'static>);
+
+impl<'a> Arbitrary<'a> for ArbitraryModule {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Generator::new(u, Limits::default()).module().map(ArbitraryModule)
+    }
+}
+
+impl ArbitraryModule {
+    pub fn with_limits(u: &mut Unstructured, limits: Limits) -> arbitrary::Result<Self> {
+        Generator::new(u, limits).module().map(ArbitraryModule)
+    }
+}
@@ -0,0 +1,260 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use inkwell::debug_info::{AsDIScope, DIFlagsConstants};
+use inkwell::types::BasicType;
+use inkwell::values::AsValueRef;
+use llvm_sys::debuginfo::LLVMDIBuilderInsertDbgValueAtEnd;
+use ast::function::Function;
+use ast::module::Module;
+use ast::span::Span;
+use crate::{llvm, Llvm};
+
+// DWARF base type encodings (`DW_ATE_*`)
+const DW_ATE_ADDRESS: u32 = 0x01;
+const DW_ATE_BOOLEAN: u32 = 0x02;
+const DW_ATE_FLOAT: u32 = 0x04;
+const DW_ATE_SIGNED: u32 = 0x05;
+const DW_ATE_UNSIGNED: u32 = 0x08;
+
+/// The DWARF debug info of a module, which is only emitted if at least one of its functions has a span.
+pub(crate) struct DebugInfo<'ctx> {
+    builder: llvm::DebugInfoBuilder<'ctx>,
+    compile_unit: llvm::DICompileUnit<'ctx>,
+    files: RefCell<HashMap<String, llvm::DIFile<'ctx>>>,
+    is_optimized: bool,
+}
+
+impl<'ctx> DebugInfo<'ctx> {
+    fn file(&self, path: &str) -> llvm::DIFile<'ctx> {
+        *self.files
+            .borrow_mut()
+            .entry(path.to_string())
+            .or_insert_with(|| {
+                let (directory, file_name) = split_path(path);
+                self.builder.create_file(file_name, directory)
+            })
+    }
+
+    pub(crate) fn finalize(&self) {
+        self.builder.finalize();
+    }
+}
+
+/// The debug info of a single function, which is present if the function has a span.
+#[derive(Copy, Clone)]
+pub(crate) struct DebugScope<'ctx, 'm> {
+    info: &'m DebugInfo<'ctx>,
+    subprogram: llvm::DISubprogram<'ctx>,
+    file: llvm::DIFile<'ctx>,
+}
+
+#[derive(Copy, Clone)]
+pub(crate) struct DebugLocation<'ctx> {
+    pub(crate) raw: llvm::DILocation<'ctx>,
+    file: llvm::DIFile<'ctx>,
+}
+
+impl<'ctx> DebugScope<'ctx, '_> {
+    pub(crate) fn location(&self, llvm: &'ctx Llvm, span: &Span) -> DebugLocation<'ctx> {
+        let file = self.info.file(&span.file);
+
+        // Spans from other files (e.g. included or generated code) are placed in a lexical block of that file
+        let scope = if file == self.file {
+            self.subprogram.as_debug_info_scope()
+        } else {
+            self.info.builder
+                .create_lexical_block(self.subprogram.as_debug_info_scope(), file, span.line, span.column)
+                .as_debug_info_scope()
+        };
+
+        let raw = self.info.builder.create_debug_location(&llvm.context, span.line, span.column, scope, None);
+        DebugLocation { raw, file }
+    }
+
+    pub(crate) fn create_variable(
+        &self,
+        llvm: &'ctx Llvm,
+        name: &str,
+        value: llvm::Value<'ctx>,
+        location: DebugLocation<'ctx>,
+    ) -> llvm::DILocalVariable<'ctx> {
+        let ty = llvm.compile_debug_type(&self.info.builder, value.raw.get_type(), value.signage);
+
+        self.info.builder.create_auto_variable(
+            location.raw.get_scope(),
+            name,
+            location.file,
+            location.raw.get_line(),
+            ty,
+            true,
+            llvm::DIFlags::ZERO,
+            0,
+        )
+    }
+
+    /// Records that `variable` holds `value` from the end of `block` onwards.
+    pub(crate) fn set_variable_value(
+        &self,
+        variable: llvm::DILocalVariable<'ctx>,
+        value: llvm::RawValue<'ctx>,
+        location: DebugLocation<'ctx>,
+        block: llvm::Block<'ctx>,
+    ) {
+        let expression = self.info.builder.create_expression(Vec::new());
+
+        // Inkwell can only insert `llvm.dbg.value` before an existing instruction, but the value is assigned at the
+        // end of the block that is still being built
+        unsafe {
+            LLVMDIBuilderInsertDbgValueAtEnd(
+                self.info.builder.as_mut_ptr(),
+                value.as_value_ref(),
+                variable.as_mut_ptr(),
+                expression.as_mut_ptr(),
+                location.raw.as_mut_ptr(),
+                block.as_mut_ptr(),
+            );
+        }
+    }
+}
+
+impl Llvm {
+    pub(crate) fn create_debug_info<'ctx>(
+        &'ctx self,
+        ast_module: &Module,
+        module: &llvm::Module<'ctx>,
+    ) -> Option<DebugInfo<'ctx>> {
+        let span = ast_module.functions.iter().find_map(|x| x.span.as_ref())?;
+        let (directory, file_name) = split_path(&span.file);
+        let is_optimized = self.optimization.pipeline().is_some();
+
+        let version = self.context.i32_type().const_int(llvm::debug_metadata_version() as u64, false);
+        module.add_basic_value_flag("Debug Info Version", llvm::FlagBehavior::Warning, version);
+
+        let (builder, compile_unit) = module.create_debug_info_builder(
+            true,
+            llvm::DWARFSourceLanguage::C,
+            file_name,
+            directory,
+            "dorian",
+            is_optimized,
+            "",
+            0,
+            "",
+            llvm::DWARFEmissionKind::Full,
+            0,
+            false,
+            false,
+            "",
+            "",
+        );
+
+        Some(DebugInfo {
+            builder,
+            compile_unit,
+            files: RefCell::new(HashMap::new()),
+            is_optimized,
+        })
+    }
+
+    pub(crate) fn create_debug_scope<'ctx, 'm>(
+        &'ctx self,
+        info: &'m DebugInfo<'ctx>,
+        ast_function: &Function,
+        function: llvm::Function<'ctx>,
+    ) -> Option<DebugScope<'ctx, 'm>> {
+        let span = ast_function.span.as_ref()?;
+        let file = info.file(&span.file);
+
+        let function_type = function.get_type();
+        let param_types = ast_function.signature.input
+            .iter()
+            .zip(function_type.get_param_types())
+            .map(|(ast_type, ty)| {
+                self.compile_debug_type(&info.builder, ty.try_into().unwrap(), ast_type.get_signage())
+            })
+            .collect::<Vec<_>>();
+        // Multiple outputs are returned as an anonymous struct, which is left undescribed
+        let return_type = match ast_function.signature.output.as_slice() {
+            [ast_type] => function_type
+                .get_return_type()
+                .map(|ty| self.compile_debug_type(&info.builder, ty, ast_type.get_signage())),
+            _ => None,
+        };
+        let subroutine_type = info.builder.create_subroutine_type(file, return_type, &param_types, llvm::DIFlags::ZERO);
+
+        let subprogram = info.builder.create_function(
+            info.compile_unit.as_debug_info_scope(),
+            &ast_function.name,
+            None,
+            file,
+            span.line,
+            subroutine_type,
+            false,
+            true,
+            span.line,
+            llvm::DIFlags::ZERO,
+            info.is_optimized,
+        );
+        function.set_subprogram(subprogram);
+
+        Some(DebugScope { info, subprogram, file })
+    }
+
+    fn compile_debug_type<'ctx>(
+        &'ctx self,
+        builder: &llvm::DebugInfoBuilder<'ctx>,
+        ty: llvm::Type<'ctx>,
+        signage: Option<bool>,
+    ) -> llvm::DIType<'ctx> {
+        let (name, bits, encoding) = match ty {
+            llvm::Type::IntType(x) if x.get_bit_width() == 1 => ("bool".to_string(), 8, DW_ATE_BOOLEAN),
+            llvm::Type::IntType(x) => {
+                let bits = x.get_bit_width();
+                if signage.unwrap_or(false) {
+                    (format!("s{}", bits), bits, DW_ATE_SIGNED)
+                } else {
+                    (format!("u{}", bits), bits, DW_ATE_UNSIGNED)
+                }
+            }
+            llvm::Type::FloatType(x) => {
+                let bits = [
+                    (self.context.f16_type(), 16),
+                    (self.context.f32_type(), 32),
+                    (self.context.f64_type(), 64),
+                    (self.context.f128_type(), 128),
+                ]
+                .into_iter()
+                .find_map(|(float_type, bits)| (float_type == x).then_some(bits))
+                .expect("Unsupported float type");
+
+                (format!("f{}", bits), bits, DW_ATE_FLOAT)
+            }
+            // Modules are always compiled for the host, so pointers have the width of a host pointer
+            llvm::Type::PointerType(_) => ("ptr".to_string(), usize::BITS, DW_ATE_ADDRESS),
+            llvm::Type::VectorType(x) => {
+                let elem_type = self.compile_debug_type(builder, x.get_element_type(), signage);
+                let bits = elem_type.get_size_in_bits() * x.get_size() as u64;
+
+                return builder
+                    .create_array_type(elem_type, bits, 0, &[Range { start: 0, end: x.get_size() as i64 }])
+                    .as_type();
+            }
+            _ => panic!("Type does not have debug info: {:?}", ty.as_basic_type_enum()),
+        };
+
+        builder
+            .create_basic_type(&name, bits as u64, encoding, llvm::DIFlags::ZERO)
+            .unwrap()
+            .as_type()
+    }
+}
+
+fn split_path(path: &str) -> (&str, &str) {
+    let path = Path::new(path);
+    let directory = path.parent().and_then(|x| x.to_str()).unwrap_or("");
+    let file_name = path.file_name().and_then(|x| x.to_str()).unwrap_or("");
+
+    (directory, file_name)
+}
@@ -1,35 +1,54 @@
 extern crate dorian_ast as ast;
 
-use ast::backend::Backend;
+use ast::backend::{Backend, CompileError};
+use ast::function::Linkage;
 use ast::global::Global;
 use ast::module::Module;
+use ast::infer;
+use ast::resolve::SymbolTable;
 use ast::structure::Struct;
+use ast::ty::{ScalarType, Type};
+use crate::debug::DebugInfo;
 use crate::scope::Scope;
 
+pub use opt::Optimization;
+
+mod debug;
 mod scope;
+mod ssa;
 mod ty;
 mod val;
 mod llvm;
+mod opt;
 
 pub struct Llvm {
     context: llvm::Context,
-    signed_attribute: llvm::Attribute,
-    unsigned_attribute: llvm::Attribute,
+    zero_extend_attribute: llvm::Attribute,
+    optimization: Optimization,
 }
 
 impl Llvm {
     pub fn new() -> Self {
         let context = llvm::Context::create();
-        let signed_attribute = context.create_string_attribute("signage", "signed");
-        let unsigned_attribute = context.create_string_attribute("signage", "unsigned");
+        let zero_extend_kind = llvm::Attribute::get_named_enum_kind_id("zeroext");
+        let zero_extend_attribute = context.create_enum_attribute(zero_extend_kind, 0);
 
         Llvm {
             context,
-            signed_attribute,
-            unsigned_attribute,
+            zero_extend_attribute,
+            optimization: Optimization::None,
         }
     }
 
+    pub fn with_optimization(mut self, optimization: Optimization) -> Self {
+        self.optimization = optimization;
+        self
+    }
+
+    pub fn optimization(&self) -> &Optimization {
+        &self.optimization
+    }
+
     fn compile_struct<'ctx>(&'ctx self, ast_struct: &Struct) {
         let struct_type = self.context.opaque_struct_type(&ast_struct.name);
         let fields = ast_struct
@@ -56,42 +75,73 @@ impl Llvm {
         let _ = global;
     }
 
-    fn compile_functions<'ctx>(&'ctx self, ast_module: &Module, module: &llvm::Module<'ctx>) {
-        let mut pairs = Vec::with_capacity(ast_module.functions.len());
-        for ast_function in &ast_module.functions {
+    fn compile_resolved_module<'ctx>(&'ctx self, symbols: &SymbolTable) -> llvm::Module<'ctx> {
+        let ast_module = symbols.module();
+        let module = self.context.create_module(&ast_module.name);
+
+        for ast_struct in &ast_module.structs {
+            self.compile_struct(ast_struct);
+        }
+
+        for ast_global in &ast_module.globals {
+            self.compile_global(ast_global, &module);
+        }
+
+        let debug_info = self.create_debug_info(ast_module, &module);
+        self.compile_functions(symbols, &module, debug_info.as_ref());
+        if let Some(debug_info) = &debug_info {
+            debug_info.finalize();
+        }
+
+        module
+    }
+
+    fn compile_functions<'ctx>(
+        &'ctx self,
+        symbols: &SymbolTable,
+        module: &llvm::Module<'ctx>,
+        debug_info: Option<&DebugInfo<'ctx>>,
+    ) {
+        // Indexed by `FunctionId`
+        let mut functions = Vec::with_capacity(symbols.module().functions.len());
+        for (id, ast_function) in symbols.functions() {
+            if ast_function.signature.variadic && symbols.linkage(id) != Linkage::Import {
+                panic!("Variadic function '{}' must be imported", ast_function.name);
+            }
+
             let function_type = self.compile_signature(&ast_function.signature);
-            let function = module.add_function(&ast_function.name, function_type, None);
-
-            /*
-            TODO
-            if let Some(signed) = ast_function.ty.return_type.get_signage() {
-                if signed {
-                    function.add_attribute(llvm::AttributeLoc::Return, self.signed_attribute);
-                } else {
-                    function.add_attribute(llvm::AttributeLoc::Return, self.unsigned_attribute);
-                }
+            let linkage = match symbols.linkage(id) {
+                Linkage::Export | Linkage::Import => llvm::Linkage::External,
+                Linkage::Internal => llvm::Linkage::Internal,
+                Linkage::Weak => llvm::Linkage::WeakAny,
+            };
+            let function = module.add_function(&ast_function.name, function_type, Some(linkage));
+            function.set_call_conventions(self.compile_call_conv(ast_function.signature.call_conv));
+
+            // Booleans are `i1` in LLVM but a full byte in the C ABI and in the Cranelift backend
+            if let [Type::Scalar(ScalarType::Bool(_))] = ast_function.signature.output.as_slice() {
+                function.add_attribute(llvm::AttributeLoc::Return, self.zero_extend_attribute);
             }
-             */
 
             for (i, param) in ast_function.signature.input.iter().enumerate() {
-                let Some(signed) = param.get_signage() else {
-                    continue;
-                };
-
-                let attribute = if signed {
-                    self.signed_attribute
-                } else {
-                    self.unsigned_attribute
-                };
-
-                function.add_attribute(llvm::AttributeLoc::Param(i as u32), attribute);
+                if let Type::Scalar(ScalarType::Bool(_)) = param {
+                    function.add_attribute(llvm::AttributeLoc::Param(i as u32), self.zero_extend_attribute);
+                }
             }
 
-            pairs.push((ast_function, function));
+            functions.push(function);
         }
 
-        for (ast_function, function) in pairs {
-            self.create_scope(&module, function)
+        // Imported functions are only declared
+        let defined = symbols.functions().filter(|(id, _)| symbols.linkage(*id) != Linkage::Import);
+        for (id, ast_function) in defined {
+            let function = functions[id.0 as usize];
+            let debug_scope = debug_info.and_then(|x| self.create_debug_scope(x, ast_function, function));
+            if let Some(personality) = symbols.personality(id) {
+                function.set_personality_function(functions[personality.0 as usize]);
+            }
+
+            self.create_scope(symbols, module, &functions, function, &ast_function.signature, debug_scope)
                 .compile_body(ast_function);
         }
     }
@@ -100,19 +150,28 @@ impl Llvm {
 impl Backend for Llvm {
     type CompiledModule<'ctx> = llvm::Module<'ctx>;
 
-    fn compile_module<'ctx>(&'ctx mut self, ast_module: &Module) -> Self::CompiledModule<'ctx> {
-        let module = self.context.create_module(&ast_module.name);
-
-        for ast_struct in &ast_module.structs {
-            self.compile_struct(ast_struct);
-        }
-
-        for ast_global in &ast_module.globals {
-            self.compile_global(ast_global, &module);
+    fn compile_modules<'ctx>(
+        &'ctx mut self,
+        ast_modules: &[Module],
+    ) -> Result<Self::CompiledModule<'ctx>, CompileError> {
+        let ast_modules = ast_modules
+            .iter()
+            .map(infer::type_literals)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(CompileError::Resolve)?;
+        let symbols = SymbolTable::resolve_linked(ast_modules.iter().map(AsRef::as_ref))
+            .map_err(CompileError::Resolve)?;
+
+        // Every module is compiled on its own and then linked into the first, which renames clashing internal symbols
+        let mut modules = symbols.iter().map(|x| self.compile_resolved_module(x));
+        let module = modules.next().expect("Expected at least one module to compile");
+        for other in modules {
+            module.link_in_module(other).unwrap_or_else(|e| panic!("Failed to link modules: {}", e));
         }
 
-        self.compile_functions(ast_module, &module);
+        // Optimizing the linked module lets functions be inlined across modules
+        self.optimize_module(&module)?;
 
-        module
+        Ok(module)
     }
 }
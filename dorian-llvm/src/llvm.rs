@@ -3,7 +3,23 @@ pub(crate) use inkwell::{
     basic_block::BasicBlock as Block,
     builder::Builder,
     context::Context,
-    module::Module,
+    debug_info::{
+        debug_metadata_version,
+        DICompileUnit,
+        DIFile,
+        DIFlags,
+        DILocalVariable,
+        DILocation,
+        DISubprogram,
+        DIType,
+        DWARFEmissionKind,
+        DWARFSourceLanguage,
+        DebugInfoBuilder,
+    },
+    intrinsics::Intrinsic,
+    module::{FlagBehavior, Linkage, Module},
+    passes::PassBuilderOptions,
+    targets::{CodeModel, InitializationConfig, RelocMode, Target, TargetMachine},
     types::{
         BasicMetadataTypeEnum as MetadataType,
         BasicTypeEnum as Type,
@@ -12,20 +28,31 @@ pub(crate) use inkwell::{
         IntType,
         PointerType,
         StringRadix,
+        StructType,
         VectorType,
         VoidType,
     },
     values::{
+        BasicMetadataValueEnum as MetadataValue,
         BasicValueEnum as RawValue,
+        CallSiteValue as CallSite,
         FloatValue as Float,
         FunctionValue as Function,
-        IntValue as Int
+        InstructionOpcode,
+        IntValue as Int,
+        LLVMTailCallKind as TailCallKind,
+        PhiValue as Phi,
+        PointerValue as Pointer,
+        VectorValue as Vector,
     },
     AddressSpace,
+    AtomicOrdering,
+    AtomicRMWBinOp,
     FloatPredicate as FloatCmpOp,
     IntPredicate as IntCmpOp,
+    OptimizationLevel,
 };
-use inkwell::types::{BasicType, StructType};
+use inkwell::types::BasicType;
 
 pub(crate) enum ScalarType<'ctx> {
     Int(IntType<'ctx>),
@@ -0,0 +1,76 @@
+use std::borrow::Cow;
+use ast::backend::CompileError;
+use crate::{llvm, Llvm};
+
+/// The optimization pipeline that is run on every module compiled by [`Llvm`].
+///
+/// Pipelines are run by LLVM's new pass manager, so `Custom` accepts any pipeline string understood by `opt -passes`,
+/// e.g. `"mem2reg,instcombine,simplifycfg"`.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub enum Optimization {
+    #[default]
+    None,
+    O1,
+    O2,
+    O3,
+    Os,
+    Oz,
+    Custom(Cow<'static, str>),
+}
+
+impl Optimization {
+    pub fn pipeline(&self) -> Option<&str> {
+        match self {
+            Optimization::None => None,
+            Optimization::O1 => Some("default<O1>"),
+            Optimization::O2 => Some("default<O2>"),
+            Optimization::O3 => Some("default<O3>"),
+            Optimization::Os => Some("default<Os>"),
+            Optimization::Oz => Some("default<Oz>"),
+            Optimization::Custom(x) => Some(x),
+        }
+    }
+
+    pub fn codegen_level(&self) -> llvm::OptimizationLevel {
+        match self {
+            Optimization::None => llvm::OptimizationLevel::None,
+            Optimization::O1 => llvm::OptimizationLevel::Less,
+            Optimization::O2 | Optimization::Os | Optimization::Oz | Optimization::Custom(_) => {
+                llvm::OptimizationLevel::Default
+            }
+            Optimization::O3 => llvm::OptimizationLevel::Aggressive,
+        }
+    }
+}
+
+impl Llvm {
+    pub(crate) fn optimize_module(&self, module: &llvm::Module) -> Result<(), CompileError> {
+        let Some(pipeline) = self.optimization.pipeline() else {
+            return Ok(());
+        };
+
+        llvm::Target::initialize_native(&llvm::InitializationConfig::default())
+            .expect("Failed to initialize the native target");
+
+        let triple = llvm::TargetMachine::get_default_triple();
+        let target = llvm::Target::from_triple(&triple).expect("Failed to get the native target");
+        let machine = target
+            .create_target_machine(
+                &triple,
+                &llvm::TargetMachine::get_host_cpu_name().to_string(),
+                &llvm::TargetMachine::get_host_cpu_features().to_string(),
+                self.optimization.codegen_level(),
+                llvm::RelocMode::Default,
+                llvm::CodeModel::JITDefault,
+            )
+            .expect("Failed to create a target machine for the native target");
+
+        // LLVM checks a custom pipeline only when parsing it here, so an invalid one is reported as an error
+        module
+            .run_passes(pipeline, &machine, llvm::PassBuilderOptions::create())
+            .map_err(|error| CompileError::Optimization {
+                pipeline: pipeline.to_string(),
+                message: error.to_string(),
+            })
+    }
+}
@@ -1,24 +1,43 @@
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
-use inkwell::values::BasicValue;
 use ast::block::Block;
-use ast::block::stmt::{AssignStmt, BindStmt, IfElse, IfStmt, ReturnStmt, Stmt, WhileStmt};
-use ast::function::Function;
-use ast::val::Var;
+use ast::block::stmt::{
+    AssertStmt, AssignStmt, AtomicStoreStmt, BindStmt, BindTupleStmt, Handler, IfElse, IfStmt, MemCopyStmt,
+    MemSetStmt, ReturnStmt, Stmt, TrapCode, TryStmt, WhileStmt,
+};
+use ast::function::{Function, Signature};
+use ast::resolve::SymbolTable;
+use ast::span::{Span, Spanned};
+use ast::ty::IntType;
+use ast::val::{Value, Var};
 use crate::{llvm, Llvm};
+use crate::debug::{DebugLocation, DebugScope};
+use crate::ssa::{Ssa, VarId};
 
 impl Llvm {
     pub(crate) fn create_scope<'ctx, 'm>(
         &'ctx self,
+        symbols: &'m SymbolTable<'m, 'm>,
         module: &'m llvm::Module<'ctx>,
+        functions: &'m [llvm::Function<'ctx>],
         function: llvm::Function<'ctx>,
+        signature: &'m Signature,
+        debug: Option<DebugScope<'ctx, 'm>>,
     ) -> LocalScope<'ctx, 'm> {
         LocalScope {
             llvm: self,
+            symbols,
             module,
+            functions,
             function,
+            signature,
             builder: self.context.create_builder(),
-            levels: vec![Level::new()],
-            depth: 0,
+            phi_builder: self.context.create_builder(),
+            debug,
+            location: Cell::new(None),
+            levels: Vec::new(),
+            tries: Vec::new(),
+            ssa: RefCell::new(Ssa::default()),
         }
     }
 }
@@ -40,62 +59,81 @@ impl<'ctx, 'l, 'm> Scope<'ctx, 'l, 'm> {
 
 pub(crate) struct LocalScope<'ctx, 'm> {
     pub(crate) llvm: &'ctx Llvm,
+    // LLVM types do not distinguish signed and unsigned integers, so their signage is looked up in the AST
+    pub(crate) symbols: &'m SymbolTable<'m, 'm>,
     pub(crate) module: &'m llvm::Module<'ctx>,
+    /// The compiled functions of the module, indexed by `FunctionId`.
+    pub(crate) functions: &'m [llvm::Function<'ctx>],
     pub(crate) function: llvm::Function<'ctx>,
+    pub(crate) signature: &'m Signature,
     pub(crate) builder: llvm::Builder<'ctx>,
-    levels: Vec<Level<'ctx>>,
-    depth: usize,
+    pub(crate) phi_builder: llvm::Builder<'ctx>,
+    pub(crate) debug: Option<DebugScope<'ctx, 'm>>,
+    pub(crate) location: Cell<Option<DebugLocation<'ctx>>>,
+    levels: Vec<Level>,
+    // The try statements whose body is being compiled, innermost last
+    tries: Vec<TryScope<'ctx>>,
+    pub(crate) ssa: RefCell<Ssa<'ctx>>,
 }
 
 impl<'ctx> LocalScope<'ctx, '_> {
-    pub(crate) fn get_var(&self, var: &Var, load: bool) -> Option<llvm::Value<'ctx>> {
-        // Iterate in reverse to prefer the most recent scope and allow for shadowing
-        let mut stored_value = None;
-        for level in self.levels.iter().rev() {
-            if let Some(x) = level.values.get(var.name.as_ref()) {
-                stored_value = Some(*x);
-                break;
-            }
-        }
+    pub(crate) fn get_var(&self, var: &Var) -> Option<llvm::Value<'ctx>> {
+        let id = self.resolve_var(var)?;
+        let block = self.builder.get_insert_block().unwrap();
 
-        let stored_value = stored_value?;
-        if load {
-            let pointer_value = stored_value.value.raw.into_pointer_value();
-            let loaded_value = self.builder.build_load(stored_value.base_type, pointer_value, &var.name).unwrap();
-            Some(llvm::Value::new(loaded_value, stored_value.value.signage))
-        } else {
-            Some(stored_value.value)
-        }
+        Some(llvm::Value::new(self.read_variable(id, block), self.variable_signage(id)))
     }
 
-    pub(crate) fn compile_body(&mut self, Function { body, .. }: &Function) {
+    fn resolve_var(&self, var: &Var) -> Option<VarId> {
+        // Iterate in reverse to prefer the most recent scope and allow for shadowing
+        self.levels
+            .iter()
+            .rev()
+            .find_map(|level| level.vars.get(var.name.as_ref()))
+            .copied()
+    }
+
+    pub(crate) fn compile_body(&mut self, Function { body, span, .. }: &Function) {
         let block = self.append_block();
         self.builder.position_at_end(block);
+        self.seal_block(block);
 
-        for stmt in &body.stmts {
-            self.compile_stmt(stmt);
+        // Instructions that are not inside a spanned node are attributed to the function itself
+        if let Some(span) = span {
+            self.enter_span(span);
         }
+
+        self.compile_block(body);
     }
 
-    fn push_level(&mut self) {
-        self.levels.push(Level::new());
-        self.depth += 1;
+    /// Attributes the instructions built from now on to `span`, returning the previous location to be restored with
+    /// [`LocalScope::exit_span`].
+    pub(crate) fn enter_span(&self, span: &Span) -> Option<DebugLocation<'ctx>> {
+        let debug = self.debug?;
+        let location = debug.location(self.llvm, span);
+
+        self.builder.set_current_debug_location(location.raw);
+        self.location.replace(Some(location))
     }
 
-    fn pop_level(&mut self) {
-        self.levels.pop();
-        self.depth -= 1;
+    pub(crate) fn exit_span(&self, previous: Option<DebugLocation<'ctx>>) {
+        if self.debug.is_none() {
+            return;
+        }
+
+        match previous {
+            Some(location) => self.builder.set_current_debug_location(location.raw),
+            None => self.builder.unset_current_debug_location(),
+        }
+        self.location.set(previous);
     }
 
     fn compile_block(&mut self, block: &Block) -> bool {
-        self.push_level();
-        for stmt in &block.stmts {
-            if self.compile_stmt(stmt) {
-                return true;
-            }
-        }
-        self.pop_level();
-        false
+        self.levels.push(Level::new());
+        let terminates = block.stmts.iter().any(|stmt| self.compile_stmt(stmt));
+        self.levels.pop();
+
+        terminates
     }
 
     fn compile_stmt(&mut self, stmt: &Stmt) -> bool {
@@ -113,13 +151,81 @@ impl<'ctx> LocalScope<'ctx, '_> {
                 self.compile_bind_stmt(x);
                 false
             },
+            Stmt::BindTuple(x) => {
+                self.compile_bind_tuple_stmt(x);
+                false
+            },
             Stmt::Assign(x) => {
                 self.compile_assign_stmt(x);
                 false
             },
+            Stmt::Trap(x) => {
+                self.build_trap(*x);
+                true
+            },
+            Stmt::Unreachable => {
+                self.build_trap(TrapCode::UNREACHABLE);
+                true
+            },
+            Stmt::Assert(x) => {
+                self.compile_assert_stmt(x);
+                false
+            },
+            Stmt::AtomicStore(x) => {
+                self.compile_atomic_store_stmt(x);
+                false
+            },
+            Stmt::Fence(x) => {
+                if !x.supports_fence() {
+                    panic!("Fence does not support {:?} ordering", x);
+                }
+
+                self.builder.build_fence(Llvm::compile_atomic_ordering(*x), 0, "").unwrap();
+                false
+            },
+            Stmt::MemCopy(x) => {
+                self.compile_mem_copy_stmt(x, true);
+                false
+            },
+            Stmt::MemMove(x) => {
+                self.compile_mem_copy_stmt(x, false);
+                false
+            },
+            Stmt::MemSet(x) => {
+                self.compile_mem_set_stmt(x);
+                false
+            },
+            Stmt::Call(x) => {
+                self.llvm.build_call(x, self).unwrap();
+                false
+            },
+            Stmt::CallIndirect(x) => {
+                self.llvm.build_call_indirect(x, self).unwrap();
+                false
+            },
+            Stmt::TailCall(x) => {
+                let call_site = self.llvm.build_call(x, self).unwrap();
+                self.build_tail_return(call_site);
+                true
+            },
+            Stmt::TailCallIndirect(x) => {
+                let call_site = self.llvm.build_call_indirect(x, self).unwrap();
+                self.build_tail_return(call_site);
+                true
+            },
+            Stmt::Try(x) => self.compile_try_stmt(x),
+            Stmt::Spanned(x) => self.compile_spanned_stmt(x),
         }
     }
 
+    fn compile_spanned_stmt(&mut self, stmt: &Spanned<Stmt>) -> bool {
+        let previous = self.enter_span(&stmt.span);
+        let terminates = self.compile_stmt(&stmt.node);
+        self.exit_span(previous);
+
+        terminates
+    }
+
     fn compile_if_stmt(&mut self, stmt: &IfStmt) -> bool {
         let condition = self.llvm.compile_value(&stmt.condition, Scope::Local(self)).unwrap();
 
@@ -127,12 +233,14 @@ impl<'ctx> LocalScope<'ctx, '_> {
         let else_block = self.append_block();
         let merge_block = self.append_block();
 
-        self.builder.build_conditional_branch(condition.raw.into_int_value(), then_block, else_block).unwrap();
-        self.builder.position_at_end(then_block);
+        self.build_conditional_branch(condition.raw.into_int_value(), then_block, else_block);
+        self.seal_block(then_block);
+        self.seal_block(else_block);
 
+        self.builder.position_at_end(then_block);
         let then_terminates = self.compile_block(&stmt.then_block);
         if !then_terminates {
-            self.builder.build_unconditional_branch(merge_block).unwrap();
+            self.build_unconditional_branch(merge_block);
         }
 
         self.builder.position_at_end(else_block);
@@ -143,14 +251,18 @@ impl<'ctx> LocalScope<'ctx, '_> {
         };
 
         if !else_terminates {
-            self.builder.build_unconditional_branch(merge_block).unwrap();
+            self.build_unconditional_branch(merge_block);
         }
 
-        if !then_terminates || !else_terminates {
-            self.builder.position_at_end(merge_block);
+        if then_terminates && else_terminates {
+            // Nothing branches to the merge block, so it would be left without a terminator
+            unsafe { merge_block.delete() }.unwrap();
+            return true;
         }
 
-        then_terminates && else_terminates
+        self.seal_block(merge_block);
+        self.builder.position_at_end(merge_block);
+        false
     }
 
     fn compile_if_else(&mut self, if_else: &IfElse) -> bool {
@@ -179,59 +291,334 @@ impl<'ctx> LocalScope<'ctx, '_> {
         }
     }
 
+    /// Returns the result of `call_site`, which is guaranteed to reuse the stack frame of the function.
+    fn build_tail_return(&self, call_site: llvm::CallSite<'ctx>) {
+        call_site.set_tail_call_kind(llvm::TailCallKind::LLVMTailCallKindMustTail);
+        match call_site.try_as_basic_value().left() {
+            Some(value) => self.builder.build_return(Some(&value)).unwrap(),
+            None => self.builder.build_return(None).unwrap(),
+        };
+    }
+
+    /// Builds a call to `callee`, which is an invoke that unwinds to the innermost try statement if there is one.
+    pub(crate) fn build_call_site(
+        &self,
+        function_type: llvm::FunctionType<'ctx>,
+        callee: llvm::Pointer<'ctx>,
+        args: &[llvm::RawValue<'ctx>],
+    ) -> llvm::CallSite<'ctx> {
+        let Some(try_scope) = self.tries.last() else {
+            let args = args.iter().map(|&x| x.into()).collect::<Vec<_>>();
+            return self.builder.build_indirect_call(function_type, callee, &args, "").unwrap();
+        };
+
+        let block = self.builder.get_insert_block().unwrap();
+        let continue_block = self.append_block();
+        self.add_predecessor(continue_block, block);
+        self.add_predecessor(try_scope.unwind_block, block);
+
+        let call_site = self.builder
+            .build_indirect_invoke(function_type, callee, args, continue_block, try_scope.unwind_block, "")
+            .unwrap();
+        self.seal_block(continue_block);
+        self.builder.position_at_end(continue_block);
+
+        call_site
+    }
+
     fn append_block(&self) -> llvm::Block<'ctx> {
         self.llvm.context.append_basic_block(self.function, "")
     }
 
+    fn build_unconditional_branch(&self, destination: llvm::Block<'ctx>) {
+        let block = self.builder.get_insert_block().unwrap();
+        self.add_predecessor(destination, block);
+
+        self.builder.build_unconditional_branch(destination).unwrap();
+    }
+
+    /// Branches to a block that traps with `code` if `condition` is true, and continues in a new block otherwise.
+    pub(crate) fn build_trap_if(&self, condition: llvm::Int<'ctx>, code: TrapCode) {
+        let trap_block = self.append_block();
+        let continue_block = self.append_block();
+        self.build_conditional_branch(condition, trap_block, continue_block);
+        self.seal_block(trap_block);
+        self.seal_block(continue_block);
+
+        self.builder.position_at_end(trap_block);
+        self.build_trap(code);
+
+        self.builder.position_at_end(continue_block);
+    }
+
+    fn build_trap(&self, code: TrapCode) {
+        // Unlike `llvm.trap`, the code is encoded in the trapping instruction, where the JIT can find it
+        let trap = self.intrinsic("llvm.ubsantrap", &[]);
+        let code = self.llvm.context.i8_type().const_int(code.get() as u64, false);
+        self.builder.build_call(trap, &[code.into()], "").unwrap();
+        self.builder.build_unreachable().unwrap();
+    }
+
+    /// Declares the overload of the LLVM intrinsic `name` for `types`.
+    pub(crate) fn intrinsic(&self, name: &str, types: &[llvm::Type<'ctx>]) -> llvm::Function<'ctx> {
+        llvm::Intrinsic::find(name)
+            .and_then(|x| x.get_declaration(self.module, types))
+            .unwrap_or_else(|| panic!("Failed to declare intrinsic {}", name))
+    }
+
+    fn build_conditional_branch(
+        &self,
+        condition: llvm::Int<'ctx>,
+        then: llvm::Block<'ctx>,
+        otherwise: llvm::Block<'ctx>,
+    ) {
+        let block = self.builder.get_insert_block().unwrap();
+        self.add_predecessor(then, block);
+        self.add_predecessor(otherwise, block);
+
+        self.builder.build_conditional_branch(condition, then, otherwise).unwrap();
+    }
+
     fn compile_while_stmt(&mut self, stmt: &WhileStmt) {
         let condition = self.llvm.compile_value(&stmt.condition, Scope::Local(self)).unwrap();
 
         let exit_block = self.append_block();
         let loop_block = self.append_block();
-        self.builder.build_conditional_branch(condition.raw.into_int_value(), loop_block, exit_block).unwrap();
+        self.build_conditional_branch(condition.raw.into_int_value(), loop_block, exit_block);
 
+        // The loop block cannot be sealed until the back-edge from the end of its body is known
         self.builder.position_at_end(loop_block);
-        self.compile_block(&stmt.loop_block);
-
-        let condition = self.llvm.compile_value(&stmt.condition, Scope::Local(self)).unwrap();
-        self.builder.build_conditional_branch(condition.raw.into_int_value(), loop_block, exit_block).unwrap();
+        if !self.compile_block(&stmt.loop_block) {
+            let condition = self.llvm.compile_value(&stmt.condition, Scope::Local(self)).unwrap();
+            self.build_conditional_branch(condition.raw.into_int_value(), loop_block, exit_block);
+        }
+        self.seal_block(loop_block);
+        self.seal_block(exit_block);
 
         self.builder.position_at_end(exit_block);
     }
 
+    fn compile_try_stmt(&mut self, stmt: &TryStmt) -> bool {
+        let catches = matches!(stmt.handler, Handler::Catch { .. });
+        // Where unwinding lands, and where the handler starts with the landing pad value in `exception`
+        let unwind_block = self.append_block();
+        let handler_block = self.append_block();
+        let exception = self.declare_internal_variable(self.exception_type().into());
+
+        self.tries.push(TryScope {
+            unwind_block,
+            handler_block,
+            exception,
+            // An enclosing catch handler is reached from the landing pad of this statement after its handler has run
+            catches: catches || self.tries.last().is_some_and(|x| x.catches),
+        });
+        let body_terminates = self.compile_block(&stmt.body);
+        let try_scope = self.tries.pop().unwrap();
+
+        let merge_block = self.append_block();
+        if !body_terminates {
+            self.build_unconditional_branch(merge_block);
+        }
+
+        // Calls in nested try statements unwind to their own landing pads, and only reach this handler after theirs
+        if unwind_block.get_first_use().is_some() {
+            self.seal_block(unwind_block);
+            self.builder.position_at_end(unwind_block);
+            self.build_landing_pad(exception, try_scope.catches, !catches);
+            self.build_unconditional_branch(handler_block);
+        } else {
+            unsafe { unwind_block.delete() }.unwrap();
+        }
+
+        let handler_terminates = if handler_block.get_first_use().is_some() {
+            self.seal_block(handler_block);
+            self.builder.position_at_end(handler_block);
+            self.compile_handler(&stmt.handler, exception, merge_block)
+        } else {
+            // Nothing in the body can unwind, so the handler is never run
+            unsafe { handler_block.delete() }.unwrap();
+            true
+        };
+
+        if body_terminates && handler_terminates {
+            // Nothing branches to the merge block, so it would be left without a terminator
+            unsafe { merge_block.delete() }.unwrap();
+            return true;
+        }
+
+        self.seal_block(merge_block);
+        self.builder.position_at_end(merge_block);
+        false
+    }
+
+    fn build_landing_pad(&self, exception: VarId, catches: bool, cleanup: bool) {
+        let personality = self.function
+            .get_personality_function()
+            .expect("Function with a try statement has no personality");
+        // A null type info catches every exception
+        let clauses = match catches {
+            true => vec![self.llvm.context.ptr_type(llvm::AddressSpace::default()).const_null().into()],
+            false => vec![],
+        };
+        let landing_pad = self.builder
+            .build_landing_pad(self.exception_type(), personality, &clauses, cleanup, "")
+            .unwrap();
+        self.assign_variable(exception, landing_pad);
+    }
+
+    /// The type of a landing pad value, which is the exception object and a selector for the personality.
+    fn exception_type(&self) -> llvm::StructType<'ctx> {
+        let ptr_type = self.llvm.context.ptr_type(llvm::AddressSpace::default());
+        self.llvm.context.struct_type(&[ptr_type.into(), self.llvm.context.i32_type().into()], false)
+    }
+
+    /// Compiles the handler of a try statement, returning whether it terminates rather than continuing after it.
+    fn compile_handler(&mut self, handler: &Handler, exception: VarId, merge_block: llvm::Block<'ctx>) -> bool {
+        match handler {
+            Handler::Cleanup(x) => {
+                if !self.compile_block(x) {
+                    self.build_unwind_resume(exception);
+                }
+                true
+            }
+            // The exception is finished by the block rather than here, as only the runtime that raised it knows how
+            Handler::Catch { name, block } => {
+                let exception_object = self.builder
+                    .build_extract_value(self.read_exception(exception).into_struct_value(), 0, "")
+                    .unwrap();
+                let value = llvm::Value::new(exception_object, None);
+
+                self.levels.push(Level::new());
+                let id = self.declare_variable(name, value);
+                self.levels.last_mut().unwrap().vars.insert(name.to_string(), id);
+                self.assign_variable(id, value.raw);
+                let terminates = self.compile_block(block);
+                self.levels.pop();
+
+                if !terminates {
+                    self.build_unconditional_branch(merge_block);
+                }
+                terminates
+            }
+        }
+    }
+
+    /// Continues unwinding with the landing pad value in `exception`, in the handler of the enclosing try statement if
+    /// there is one and out of the function otherwise.
+    fn build_unwind_resume(&self, exception: VarId) {
+        let value = self.read_exception(exception);
+        match self.tries.last() {
+            Some(outer) => {
+                self.assign_variable(outer.exception, value);
+                self.build_unconditional_branch(outer.handler_block);
+            }
+            None => {
+                self.builder.build_resume(value).unwrap();
+            }
+        }
+    }
+
+    fn read_exception(&self, exception: VarId) -> llvm::RawValue<'ctx> {
+        self.read_variable(exception, self.builder.get_insert_block().unwrap())
+    }
+
     fn compile_bind_stmt(&mut self, stmt: &BindStmt) {
-        let stored_value = self.llvm.compile_value(&stmt.value, Scope::Local(self)).unwrap();
-        let stored_type = stored_value.raw.get_type();
+        let value = self.llvm.compile_value(&stmt.value, Scope::Local(self)).unwrap();
+
+        // Variables live in SSA values rather than stack slots, as nothing in the AST can take the address of one
+        let id = self.declare_variable(&stmt.name, value);
+        self.levels.last_mut().unwrap().vars.insert(stmt.name.to_string(), id);
+        self.assign_variable(id, value.raw);
+    }
+
+    fn compile_bind_tuple_stmt(&mut self, stmt: &BindTupleStmt) {
+        let values = self.llvm.compile_values(&stmt.value, self);
+        if values.len() != stmt.names.len() {
+            panic!("Expected {} values to bind but found {} values", stmt.names.len(), values.len());
+        }
+
+        for (name, value) in stmt.names.iter().zip(values) {
+            let id = self.declare_variable(name, value);
+            self.levels.last_mut().unwrap().vars.insert(name.to_string(), id);
+            self.assign_variable(id, value.raw);
+        }
+    }
+
+    fn compile_assert_stmt(&mut self, stmt: &AssertStmt) {
+        let condition = self.llvm.compile_value(&stmt.condition, Scope::Local(self)).unwrap();
+        let failed = self.builder.build_not(condition.raw.into_int_value(), "").unwrap();
+        self.build_trap_if(failed, stmt.code);
+    }
 
-        // TODO: there are more efficient ways to handle variables and avoid stack allocation by increasing context awareness
-        let pointer_value = self.builder.build_alloca(stored_type, "").unwrap();
-        self.builder.build_store(pointer_value, stored_value.raw).unwrap();
+    fn compile_atomic_store_stmt(&mut self, stmt: &AtomicStoreStmt) {
+        if !stmt.ordering.supports_store() {
+            panic!("Atomic store does not support {:?} ordering", stmt.ordering);
+        }
 
-        let raw_value = pointer_value.as_basic_value_enum();
-        let value = llvm::Value::new(raw_value, stored_value.signage);
+        let ptr = self.llvm.compile_address(&stmt.ptr, self);
+        let value = self.llvm.compile_value(&stmt.value, Scope::Local(self)).unwrap();
+        self.llvm.atomic_type(value);
 
-        self.levels[self.depth].values.insert(stmt.name.to_string(), StoredValue { base_type: stored_type, value });
+        let store = self.builder.build_store(ptr, value.raw).unwrap();
+        store.set_atomic_ordering(Llvm::compile_atomic_ordering(stmt.ordering)).unwrap();
+        store.set_alignment(Llvm::atomic_alignment(value.raw.get_type())).unwrap();
+    }
+
+    fn compile_mem_copy_stmt(&mut self, stmt: &MemCopyStmt, non_overlapping: bool) {
+        let dest = self.llvm.compile_address(&stmt.dest, self);
+        let src = self.llvm.compile_address(&stmt.src, self);
+        let len = self.compile_length(&stmt.len);
+
+        if non_overlapping {
+            self.builder.build_memcpy(dest, stmt.align, src, stmt.align, len).unwrap();
+        } else {
+            self.builder.build_memmove(dest, stmt.align, src, stmt.align, len).unwrap();
+        }
+    }
+
+    fn compile_mem_set_stmt(&mut self, stmt: &MemSetStmt) {
+        let dest = self.llvm.compile_address(&stmt.dest, self);
+        let value = self.llvm.compile_value(&stmt.value, Scope::Local(self)).unwrap();
+        let ty = self.llvm.ast_type(value.raw.get_type(), value.signage);
+        if ty != Some(IntType::U8.into()) {
+            panic!("Value of memset must be a u8 but found {:?}", ty);
+        }
+
+        let len = self.compile_length(&stmt.len);
+        self.builder.build_memset(dest, stmt.align, value.raw.into_int_value(), len).unwrap();
+    }
+
+    fn compile_length(&mut self, len: &Value) -> llvm::Int<'ctx> {
+        let len = self.llvm.compile_value(len, Scope::Local(self)).unwrap();
+        let ty = self.llvm.ast_type(len.raw.get_type(), len.signage);
+        if ty != Some(IntType::U64.into()) {
+            panic!("Length of memory operation must be a u64 but found {:?}", ty);
+        }
+
+        len.raw.into_int_value()
     }
 
     fn compile_assign_stmt(&mut self, stmt: &AssignStmt) {
-        let var = self.get_var(&stmt.var, false).unwrap();
-        let new_value = self.llvm.compile_value(&stmt.value, Scope::Local(self)).unwrap();
-        self.builder.build_store(var.raw.into_pointer_value(), new_value.raw).unwrap();
+        let id = self.resolve_var(&stmt.var).expect("Failed to get variable from scope");
+        let value = self.llvm.compile_value(&stmt.value, Scope::Local(self)).unwrap();
+        self.assign_variable(id, value.raw);
     }
 }
 
-pub(crate) struct Level<'ctx> {
-    values: HashMap<String, StoredValue<'ctx>>,
+struct TryScope<'ctx> {
+    unwind_block: llvm::Block<'ctx>,
+    handler_block: llvm::Block<'ctx>,
+    exception: VarId,
+    // Whether the landing pad catches exceptions for this or an enclosing try statement
+    catches: bool,
 }
 
-impl<'ctx> Level<'ctx> {
-    pub(crate) fn new() -> Self {
-        Level { values: HashMap::new() }
-    }
+struct Level {
+    vars: HashMap<String, VarId>,
 }
 
-#[derive(Copy, Clone)]
-pub(crate) struct StoredValue<'ctx> {
-    base_type: llvm::Type<'ctx>,
-    value: llvm::Value<'ctx>,
+impl Level {
+    fn new() -> Self {
+        Level { vars: HashMap::new() }
+    }
 }
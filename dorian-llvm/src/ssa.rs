@@ -0,0 +1,232 @@
+use std::collections::{HashMap, HashSet};
+use inkwell::values::BasicValue;
+use crate::llvm;
+use crate::scope::LocalScope;
+
+/// A variable introduced by a `bind` statement. Shadowing introduces a new variable under the same name.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct VarId(usize);
+
+struct Variable<'ctx> {
+    ty: llvm::Type<'ctx>,
+    signage: Option<bool>,
+    debug: Option<llvm::DILocalVariable<'ctx>>,
+}
+
+/// The state of SSA construction for a single function, following Braun et al., "Simple and Efficient Construction of
+/// Static Single Assignment Form".
+///
+/// A block is sealed once all of its predecessors are known. Reading a variable in an unsealed block creates an
+/// incomplete phi, whose operands are added when the block is sealed.
+#[derive(Default)]
+pub(crate) struct Ssa<'ctx> {
+    variables: Vec<Variable<'ctx>>,
+    definitions: HashMap<(llvm::Block<'ctx>, VarId), llvm::RawValue<'ctx>>,
+    predecessors: HashMap<llvm::Block<'ctx>, Vec<llvm::Block<'ctx>>>,
+    sealed: HashSet<llvm::Block<'ctx>>,
+    incomplete_phis: HashMap<llvm::Block<'ctx>, Vec<(VarId, llvm::Phi<'ctx>)>>,
+}
+
+impl<'ctx> LocalScope<'ctx, '_> {
+    pub(crate) fn declare_variable(&self, name: &str, value: llvm::Value<'ctx>) -> VarId {
+        let debug = match (self.debug, self.location.get()) {
+            (Some(debug), Some(location)) => Some(debug.create_variable(self.llvm, name, value, location)),
+            _ => None,
+        };
+
+        let mut ssa = self.ssa.borrow_mut();
+        ssa.variables.push(Variable {
+            ty: value.raw.get_type(),
+            signage: value.signage,
+            debug,
+        });
+
+        VarId(ssa.variables.len() - 1)
+    }
+
+    /// Declares a variable that only the backend uses, which has no name and is left out of the debug info.
+    pub(crate) fn declare_internal_variable(&self, ty: llvm::Type<'ctx>) -> VarId {
+        let mut ssa = self.ssa.borrow_mut();
+        ssa.variables.push(Variable { ty, signage: None, debug: None });
+
+        VarId(ssa.variables.len() - 1)
+    }
+
+    pub(crate) fn variable_signage(&self, id: VarId) -> Option<bool> {
+        self.ssa.borrow().variables[id.0].signage
+    }
+
+    /// Assigns `value` to a variable at the current position of the builder.
+    pub(crate) fn assign_variable(&self, id: VarId, value: llvm::RawValue<'ctx>) {
+        let block = self.builder.get_insert_block().unwrap();
+        self.write_variable(id, block, value);
+
+        let variable = self.ssa.borrow().variables[id.0].debug;
+        if let (Some(debug), Some(variable), Some(location)) = (self.debug, variable, self.location.get()) {
+            debug.set_variable_value(variable, value, location, block);
+        }
+    }
+
+    pub(crate) fn read_variable(&self, id: VarId, block: llvm::Block<'ctx>) -> llvm::RawValue<'ctx> {
+        if let Some(value) = self.ssa.borrow().definitions.get(&(block, id)) {
+            return *value;
+        }
+
+        let sealed = self.ssa.borrow().sealed.contains(&block);
+        let value = if !sealed {
+            let phi = self.build_phi(id, block);
+            self.ssa.borrow_mut().incomplete_phis.entry(block).or_default().push((id, phi));
+            phi.as_basic_value()
+        } else {
+            let predecessors = self.predecessors(block);
+            if let [predecessor] = predecessors.as_slice() {
+                self.read_variable(id, *predecessor)
+            } else {
+                // Break cycles by defining the variable as the phi before reading its operands
+                let phi = self.build_phi(id, block);
+                self.write_variable(id, block, phi.as_basic_value());
+                self.add_phi_operands(id, phi, &predecessors)
+            }
+        };
+
+        self.write_variable(id, block, value);
+        value
+    }
+
+    pub(crate) fn add_predecessor(&self, block: llvm::Block<'ctx>, predecessor: llvm::Block<'ctx>) {
+        self.ssa.borrow_mut().predecessors.entry(block).or_default().push(predecessor);
+    }
+
+    /// Marks that every predecessor of `block` is known, completing the phis that were created while it was unsealed.
+    pub(crate) fn seal_block(&self, block: llvm::Block<'ctx>) {
+        let incomplete_phis = self.ssa.borrow_mut().incomplete_phis.remove(&block).unwrap_or_default();
+        let predecessors = self.predecessors(block);
+
+        for (id, phi) in incomplete_phis {
+            self.add_phi_operands(id, phi, &predecessors);
+        }
+
+        self.ssa.borrow_mut().sealed.insert(block);
+    }
+
+    fn write_variable(&self, id: VarId, block: llvm::Block<'ctx>, value: llvm::RawValue<'ctx>) {
+        self.ssa.borrow_mut().definitions.insert((block, id), value);
+    }
+
+    fn predecessors(&self, block: llvm::Block<'ctx>) -> Vec<llvm::Block<'ctx>> {
+        self.ssa.borrow().predecessors.get(&block).cloned().unwrap_or_default()
+    }
+
+    fn build_phi(&self, id: VarId, block: llvm::Block<'ctx>) -> llvm::Phi<'ctx> {
+        match block.get_first_instruction() {
+            Some(instruction) => self.phi_builder.position_before(&instruction),
+            None => self.phi_builder.position_at_end(block),
+        }
+
+        let ty = self.ssa.borrow().variables[id.0].ty;
+        self.phi_builder.build_phi(ty, "").unwrap()
+    }
+
+    fn add_phi_operands(
+        &self,
+        id: VarId,
+        phi: llvm::Phi<'ctx>,
+        predecessors: &[llvm::Block<'ctx>],
+    ) -> llvm::RawValue<'ctx> {
+        for predecessor in predecessors {
+            let value = self.read_variable(id, *predecessor);
+            phi.add_incoming(&[(&value, *predecessor)]);
+        }
+
+        self.try_remove_trivial_phi(phi)
+    }
+
+    /// Replaces a phi whose operands are all the same value (or the phi itself) with that value, and returns what the
+    /// phi now is.
+    fn try_remove_trivial_phi(&self, phi: llvm::Phi<'ctx>) -> llvm::RawValue<'ctx> {
+        let mut replaced = HashMap::new();
+        self.remove_trivial_phi(phi, &mut replaced);
+
+        // The replacement may have been a phi that became trivial in turn
+        let mut value = phi.as_basic_value();
+        while let Some(replacement) = replaced.get(&value) {
+            value = *replacement;
+        }
+        value
+    }
+
+    /// Removes `phi` if it is trivial, recording its replacement in `replaced`. The phis that used it may have had it
+    /// as their only other operand, so they are tried again.
+    fn remove_trivial_phi(
+        &self,
+        phi: llvm::Phi<'ctx>,
+        replaced: &mut HashMap<llvm::RawValue<'ctx>, llvm::RawValue<'ctx>>,
+    ) {
+        let phi_value = phi.as_basic_value();
+
+        let mut same = None;
+        for i in 0..phi.count_incoming() {
+            let (value, _) = phi.get_incoming(i).unwrap();
+            if Some(value) == same || value == phi_value {
+                continue;
+            }
+            if same.is_some() {
+                return;
+            }
+            same = Some(value);
+        }
+
+        // A phi without operands is only reachable from unreachable code
+        let same = same.unwrap_or_else(|| phi_value.get_type().const_zero());
+
+        let users = phi_users(phi);
+        replace_all_uses(phi_value, same);
+        for value in self.ssa.borrow_mut().definitions.values_mut() {
+            if *value == phi_value {
+                *value = same;
+            }
+        }
+        phi.as_instruction().erase_from_basic_block();
+        replaced.insert(phi_value, same);
+
+        for user in users {
+            // A phi whose operands are still being added is tried once they all are
+            let block = user.as_instruction().get_parent().unwrap();
+            let complete = user.count_incoming() as usize == self.predecessors(block).len();
+            if complete && !replaced.contains_key(&user.as_basic_value()) {
+                self.remove_trivial_phi(user, replaced);
+            }
+        }
+    }
+}
+
+/// The other phis that have `phi` as an operand, each once.
+fn phi_users<'ctx>(phi: llvm::Phi<'ctx>) -> Vec<llvm::Phi<'ctx>> {
+    let mut users = Vec::new();
+    let mut next = phi.as_instruction().get_first_use();
+    while let Some(current) = next {
+        let user = llvm::RawValue::try_from(current.get_user())
+            .ok()
+            .and_then(|x| x.as_instruction_value())
+            .and_then(|x| llvm::Phi::try_from(x).ok())
+            .filter(|x| *x != phi && !users.contains(x));
+        users.extend(user);
+        next = current.get_next_use();
+    }
+
+    users
+}
+
+fn replace_all_uses<'ctx>(value: llvm::RawValue<'ctx>, other: llvm::RawValue<'ctx>) {
+    match value {
+        llvm::RawValue::IntValue(x) => x.replace_all_uses_with(other.into_int_value()),
+        llvm::RawValue::FloatValue(x) => x.replace_all_uses_with(other.into_float_value()),
+        llvm::RawValue::PointerValue(x) => x.replace_all_uses_with(other.into_pointer_value()),
+        llvm::RawValue::VectorValue(x) => x.replace_all_uses_with(other.into_vector_value()),
+        llvm::RawValue::ArrayValue(x) => x.replace_all_uses_with(other.into_array_value()),
+        llvm::RawValue::StructValue(x) => x.replace_all_uses_with(other.into_struct_value()),
+        llvm::RawValue::ScalableVectorValue(x) => {
+            x.replace_all_uses_with(other.into_scalable_vector_value())
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{llvm, Llvm};
 use ast::ty::{BoolType, FloatType, IntType, IntWidth, NumType, PtrType, ScalarType, Type, VectorType};
 use inkwell::types::BasicType;
-use ast::function::Signature;
+use ast::function::{CallConv, Signature};
 
 impl Llvm {
     pub(crate) fn compile_type(&self, ty: &Type) -> llvm::Type {
@@ -45,6 +45,32 @@ impl Llvm {
         }
     }
 
+    /// The AST type of a value of type `ty` with `signage`, where integers without a signage are booleans.
+    pub(crate) fn ast_type(&self, ty: llvm::Type, signage: Option<bool>) -> Option<Type> {
+        let scalar: ScalarType = match ty {
+            llvm::Type::IntType(x) => {
+                let width = match (x.get_bit_width(), signage) {
+                    (1, None) => return Some(BoolType.into()),
+                    (8, Some(_)) => IntWidth::I8,
+                    (16, Some(_)) => IntWidth::I16,
+                    (32, Some(_)) => IntWidth::I32,
+                    (64, Some(_)) => IntWidth::I64,
+                    (128, Some(_)) => IntWidth::I128,
+                    _ => return None,
+                };
+                IntType { width, signed: signage.unwrap() }.into()
+            }
+            llvm::Type::FloatType(x) => [FloatType::F16, FloatType::F32, FloatType::F64, FloatType::F128]
+                .into_iter()
+                .find(|ty| self.compile_float_type(ty) == x)?
+                .into(),
+            llvm::Type::PointerType(_) => PtrType.into(),
+            _ => return None,
+        };
+
+        Some(scalar.into())
+    }
+
     fn compile_bool_type(&self, _: &BoolType) -> llvm::IntType {
         self.context.bool_type()
     }
@@ -58,12 +84,26 @@ impl Llvm {
     }
 
     pub(crate) fn compile_signature(&self, signature: &Signature) -> llvm::FunctionType {
+        if signature.variadic && signature.call_conv != CallConv::C {
+            panic!("Variadic functions must use the C calling convention but found {:?}", signature.call_conv);
+        }
+
         let param_types = signature.input.iter()
             .map(|param| self.compile_type(param).into())
             .collect::<Vec<_>>();
         let return_type = self.compile_aggregate_type(&signature.output);
 
-        return_type.fn_type(&param_types, false)
+        return_type.fn_type(&param_types, signature.variadic)
+    }
+
+    pub(crate) fn compile_call_conv(&self, call_conv: CallConv) -> u32 {
+        // Calling convention IDs as defined in `llvm/IR/CallingConv.h`
+        match call_conv {
+            CallConv::C => 0,
+            CallConv::Fast => 8,
+            CallConv::Cold => 9,
+            CallConv::Tail => 18,
+        }
     }
     
     fn compile_aggregate_type(&self, types: &[Type]) -> llvm::AggregateType {
@@ -1,5 +1,15 @@
+use inkwell::types::BasicType;
 use inkwell::values::BasicValue;
-use ast::val::{Arg, Bin, BinOp, Call, ContextValue, Expr, Float, Int, Lit, Num, SignedInt, Una, UnaOp, UnsignedInt, Value};
+use ast::block::stmt::TrapCode;
+use ast::function::Signature;
+use ast::span::Spanned;
+use ast::ty::{NumType, ScalarType, Type};
+use ast::val::{
+    Arg, AtomicLoad, AtomicOrdering, AtomicRmw, AtomicRmwOp, Bin, BinOp, Call, CallIndirect, Checked, CheckedOp,
+    CmpXchg, ContextValue, Expr, ExtractElement, Float, FloatCmp, FloatCmpOp, FuncAddr, InsertElement, Int, Intrinsic,
+    IntrinsicOp, Lit, Num, OnOverflow, Reduce, ReduceOp, Shuffle, SignedInt, Splat, Una, UnaOp, UnsignedInt, Value,
+    Vector,
+};
 use crate::{llvm, Llvm};
 use crate::scope::{LocalScope, Scope};
 
@@ -14,9 +24,64 @@ impl Llvm {
             Value::Expr(x) => self.compile_expr(x, scope),
             Value::Lit(x) => Some(self.compile_lit(x)),
             Value::Call(x) => self.compile_call(x, scope),
+            Value::FuncAddr(x) => Some(self.compile_func_addr(x, scope.to_local()?)),
+            Value::CallIndirect(x) => self.compile_call_indirect(x, scope.to_local()?),
+            Value::Intrinsic(x) => self.compile_intrinsic(x, scope.to_local()?),
+            Value::AtomicLoad(x) => self.compile_atomic_load(x, scope.to_local()?),
+            Value::AtomicRmw(x) => self.compile_atomic_rmw(x, scope.to_local()?),
+            Value::CmpXchg(x) => self.compile_cmpxchg(x, scope.to_local()?),
+            Value::Spanned(x) => self.compile_spanned_value(x, scope),
         }
     }
 
+    /// Compiles a value that may produce more than one value, i.e. a call with multiple outputs or a checked operation
+    /// that flags overflow, into each of its values.
+    pub(crate) fn compile_values<'ctx>(
+        &'ctx self,
+        value: &Value,
+        scope: &LocalScope<'ctx, '_>,
+    ) -> Vec<llvm::Value<'ctx>> {
+        let compiled = self.compile_value(value, Scope::Local(scope)).unwrap();
+        if !compiled.raw.is_struct_value() {
+            return vec![compiled];
+        }
+
+        let signages = match strip_spans(value) {
+            Value::Call(x) => {
+                let id = scope.symbols.callee(x);
+                scope.symbols.function(id).signature.output.iter().map(|x| x.get_signage()).collect()
+            }
+            Value::CallIndirect(x) => x.signature.output.iter().map(|x| x.get_signage()).collect(),
+            // The overflow flag of a checked operation is a boolean
+            _ => vec![compiled.signage, None],
+        };
+
+        let aggregate = compiled.raw.into_struct_value();
+        (0..aggregate.count_fields())
+            .zip(signages)
+            .map(|(i, signage)| {
+                let raw = scope.builder.build_extract_value(aggregate, i, "").unwrap();
+                llvm::Value::new(raw, signage)
+            })
+            .collect()
+    }
+
+    fn compile_spanned_value<'ctx>(
+        &'ctx self,
+        value: &Spanned<Value>,
+        scope: Scope<'ctx, '_, '_>,
+    ) -> Option<llvm::Value<'ctx>> {
+        let Some(local) = scope.to_local() else {
+            return self.compile_value(&value.node, scope);
+        };
+
+        let previous = local.enter_span(&value.span);
+        let compiled = self.compile_value(&value.node, scope);
+        local.exit_span(previous);
+
+        compiled
+    }
+
     fn compile_context_value<'ctx>(
         &'ctx self,
         value: &ContextValue,
@@ -25,7 +90,7 @@ impl Llvm {
         match value {
             ContextValue::Arg(arg) => self.compile_arg_value(arg, scope),
             ContextValue::Var(var) => scope
-                .get_var(var, true)
+                .get_var(var)
                 .expect("Failed to get variable from scope"),
         }
     }
@@ -39,11 +104,7 @@ impl Llvm {
             .function
             .get_nth_param(arg.param_index)
             .expect("Failed to get function parameter");
-        
-        let attribute_loc = llvm::AttributeLoc::Param(arg.param_index);
-        let signage = scope.function
-            .get_string_attribute(attribute_loc, "signage")
-            .map(|x| x.get_string_value().to_bytes() == b"signed");
+        let signage = scope.signature.input[arg.param_index as usize].get_signage();
 
         llvm::Value::new(value, signage)
     }
@@ -56,6 +117,14 @@ impl Llvm {
         match value {
             Expr::Bin(x) => self.compile_bin(x, scope),
             Expr::Una(x) => self.compile_una(x, scope),
+            Expr::Checked(x) => self.compile_checked(x, scope.to_local()?),
+            Expr::FloatCmp(x) => self.compile_float_cmp(x, scope.to_local()?),
+            Expr::Splat(x) => self.compile_splat(x, scope.to_local()?),
+            Expr::Vector(x) => self.compile_vector(x, scope.to_local()?),
+            Expr::ExtractElement(x) => self.compile_extract_element(x, scope.to_local()?),
+            Expr::InsertElement(x) => self.compile_insert_element(x, scope.to_local()?),
+            Expr::Shuffle(x) => self.compile_shuffle(x, scope.to_local()?),
+            Expr::Reduce(x) => self.compile_reduce(x, scope.to_local()?),
         }
     }
 
@@ -63,6 +132,12 @@ impl Llvm {
         let lhs = self.compile_value(&value.lhs, scope)?;
         let rhs = self.compile_value(&value.rhs, scope)?;
 
+        // Comparisons produce booleans, which have no signage
+        let signage = match value.op {
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => None,
+            _ => lhs.signage,
+        };
+
         let value = if lhs.raw.is_int_value() {
             let signed = lhs.signage.unwrap_or(false);
             let lhs = lhs.raw.into_int_value();
@@ -73,11 +148,16 @@ impl Llvm {
             let lhs = lhs.raw.into_float_value();
 
             self.compile_float_bin(value.op, lhs, rhs.raw, scope.to_local()?)
+        } else if lhs.raw.is_vector_value() {
+            let signed = lhs.signage.unwrap_or(false);
+            let lhs = lhs.raw.into_vector_value();
+
+            self.compile_vector_bin(value.op, signed, lhs, rhs.raw, scope.to_local()?)
         } else {
             panic!("Value does not support binary operations: {:?}", lhs.raw);
         };
 
-        Some(llvm::Value::new(value, lhs.signage))
+        Some(llvm::Value::new(value, signage))
     }
 
     fn compile_int_bin<'ctx>(
@@ -126,7 +206,7 @@ impl Llvm {
             BinOp::Or | BinOp::BitOr => scope.builder.build_or(lhs, rhs, "").unwrap(),
             BinOp::BitXor => scope.builder.build_xor(lhs, rhs, "").unwrap(),
             BinOp::Shl => scope.builder.build_left_shift(lhs, rhs, "").unwrap(),
-            BinOp::Shr => scope.builder.build_right_shift(lhs, rhs, true, "").unwrap(),
+            BinOp::Shr => scope.builder.build_right_shift(lhs, rhs, signed, "").unwrap(),
             BinOp::Eq => scope
                 .builder
                 .build_int_compare(llvm::IntCmpOp::EQ, lhs, rhs, "")
@@ -215,9 +295,10 @@ impl Llvm {
                 .build_float_compare(llvm::FloatCmpOp::OEQ, lhs, rhs, "")
                 .unwrap()
                 .as_basic_value_enum(),
+            // `Ne` is the only comparison that is true for NaN, like Cranelift's `NotEqual`
             BinOp::Ne => scope
                 .builder
-                .build_float_compare(llvm::FloatCmpOp::ONE, lhs, rhs, "")
+                .build_float_compare(llvm::FloatCmpOp::UNE, lhs, rhs, "")
                 .unwrap()
                 .as_basic_value_enum(),
             BinOp::Lt => scope
@@ -244,6 +325,120 @@ impl Llvm {
         }
     }
 
+    fn compile_vector_bin<'ctx>(
+        &'ctx self,
+        op: BinOp,
+        signed: bool,
+        lhs: llvm::Vector<'ctx>,
+        rhs: llvm::RawValue<'ctx>,
+        scope: &LocalScope<'ctx, '_>,
+    ) -> llvm::RawValue<'ctx> {
+        let ty = lhs.get_type();
+        if rhs.get_type() != ty.as_basic_type_enum() {
+            panic!("Right-hand value type of binary operation does not match left-hand type which is {:?}", ty);
+        }
+
+        let float = ty.get_element_type().is_float_type();
+        let opcode = match op {
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => {
+                panic!("Comparison operation is not supported for vector values: {:?}", op)
+            }
+            BinOp::Add if float => llvm::InstructionOpcode::FAdd,
+            BinOp::Sub if float => llvm::InstructionOpcode::FSub,
+            BinOp::Mul if float => llvm::InstructionOpcode::FMul,
+            BinOp::Div if float => llvm::InstructionOpcode::FDiv,
+            BinOp::Rem if float => llvm::InstructionOpcode::FRem,
+            BinOp::Add => llvm::InstructionOpcode::Add,
+            BinOp::Sub => llvm::InstructionOpcode::Sub,
+            BinOp::Mul => llvm::InstructionOpcode::Mul,
+            BinOp::Div if signed => llvm::InstructionOpcode::SDiv,
+            BinOp::Div => llvm::InstructionOpcode::UDiv,
+            BinOp::Rem if signed => llvm::InstructionOpcode::SRem,
+            BinOp::Rem => llvm::InstructionOpcode::URem,
+            BinOp::And | BinOp::BitAnd if !float => llvm::InstructionOpcode::And,
+            BinOp::Or | BinOp::BitOr if !float => llvm::InstructionOpcode::Or,
+            BinOp::BitXor if !float => llvm::InstructionOpcode::Xor,
+            BinOp::Shl if !float => llvm::InstructionOpcode::Shl,
+            BinOp::Shr if !float && signed => llvm::InstructionOpcode::AShr,
+            BinOp::Shr if !float => llvm::InstructionOpcode::LShr,
+            _ => panic!("Unsupported vector operation: {:?}", op),
+        };
+
+        scope.builder.build_binop(opcode, lhs, rhs.into_vector_value(), "").unwrap()
+    }
+
+    fn compile_float_cmp<'ctx>(
+        &'ctx self,
+        value: &FloatCmp,
+        scope: &LocalScope<'ctx, '_>,
+    ) -> Option<llvm::Value<'ctx>> {
+        let lhs = self.compile_value(&value.lhs, Scope::Local(scope))?;
+        let rhs = self.compile_value(&value.rhs, Scope::Local(scope))?;
+
+        if !lhs.raw.is_float_value() || !rhs.raw.is_float_value() {
+            panic!("Value does not support float comparisons: {:?}", lhs.raw);
+        }
+
+        let op = match value.op {
+            FloatCmpOp::Ordered => llvm::FloatCmpOp::ORD,
+            FloatCmpOp::Unordered => llvm::FloatCmpOp::UNO,
+            FloatCmpOp::OrderedEq => llvm::FloatCmpOp::OEQ,
+            FloatCmpOp::OrderedNe => llvm::FloatCmpOp::ONE,
+            FloatCmpOp::OrderedLt => llvm::FloatCmpOp::OLT,
+            FloatCmpOp::OrderedGt => llvm::FloatCmpOp::OGT,
+            FloatCmpOp::OrderedLe => llvm::FloatCmpOp::OLE,
+            FloatCmpOp::OrderedGe => llvm::FloatCmpOp::OGE,
+            FloatCmpOp::UnorderedEq => llvm::FloatCmpOp::UEQ,
+            FloatCmpOp::UnorderedNe => llvm::FloatCmpOp::UNE,
+            FloatCmpOp::UnorderedLt => llvm::FloatCmpOp::ULT,
+            FloatCmpOp::UnorderedGt => llvm::FloatCmpOp::UGT,
+            FloatCmpOp::UnorderedLe => llvm::FloatCmpOp::ULE,
+            FloatCmpOp::UnorderedGe => llvm::FloatCmpOp::UGE,
+        };
+        let value = scope.builder
+            .build_float_compare(op, lhs.raw.into_float_value(), rhs.raw.into_float_value(), "")
+            .unwrap();
+
+        Some(llvm::Value::new(value.as_basic_value_enum(), None))
+    }
+
+    fn compile_checked<'ctx>(&'ctx self, value: &Checked, scope: &LocalScope<'ctx, '_>) -> Option<llvm::Value<'ctx>> {
+        let lhs = self.compile_value(&value.lhs, Scope::Local(scope))?;
+        let rhs = self.compile_value(&value.rhs, Scope::Local(scope))?;
+
+        let Some(signed) = lhs.signage.filter(|_| lhs.raw.is_int_value()) else {
+            panic!("Value does not support checked operations: {:?}", lhs.raw);
+        };
+
+        let name = match value.op {
+            CheckedOp::Add if signed => "llvm.sadd.with.overflow",
+            CheckedOp::Add => "llvm.uadd.with.overflow",
+            CheckedOp::Sub if signed => "llvm.ssub.with.overflow",
+            CheckedOp::Sub => "llvm.usub.with.overflow",
+            CheckedOp::Mul if signed => "llvm.smul.with.overflow",
+            CheckedOp::Mul => "llvm.umul.with.overflow",
+        };
+        let intrinsic = scope.intrinsic(name, &[lhs.raw.get_type()]);
+        // The intrinsic returns the wrapped result and whether it overflowed as `{ iN, i1 }`
+        let pair = scope.builder
+            .build_call(intrinsic, &[lhs.raw.into(), rhs.raw.into()], "")
+            .unwrap()
+            .try_as_basic_value()
+            .unwrap_left();
+
+        let value = match value.on_overflow {
+            OnOverflow::Flag => pair,
+            OnOverflow::Trap => {
+                let pair = pair.into_struct_value();
+                let overflowed = scope.builder.build_extract_value(pair, 1, "").unwrap();
+                scope.build_trap_if(overflowed.into_int_value(), TrapCode::INTEGER_OVERFLOW);
+                scope.builder.build_extract_value(pair, 0, "").unwrap()
+            }
+        };
+
+        Some(llvm::Value::new(value, lhs.signage))
+    }
+
     fn compile_una<'ctx>(&'ctx self, value: &Una, scope: Scope<'ctx, '_, '_>) -> Option<llvm::Value<'ctx>> {
         let operand = self.compile_value(&value.operand, scope)?;
         let signed = operand.signage.unwrap_or(false);
@@ -253,7 +448,10 @@ impl Llvm {
             self.compile_int_una(value.op, value.no_wrap, signed, operand, scope).as_basic_value_enum()
         } else if operand.raw.is_float_value() {
             let operand = operand.raw.into_float_value();
-            self.compile_float_una(value.op, operand, scope.to_local()?).as_basic_value_enum()
+            self.compile_float_una(value.op, operand, scope.to_local()?)
+        } else if operand.raw.is_vector_value() {
+            let operand = operand.raw.into_vector_value();
+            self.compile_vector_una(value.op, operand, scope.to_local()?)
         } else {
             panic!("Value does not support unary operations: {:?}", operand.raw);
         };
@@ -275,12 +473,14 @@ impl Llvm {
                 UnaOp::Neg if no_wrap && !signed => operand.const_nuw_neg(),
                 UnaOp::Neg => operand.const_neg(),
                 UnaOp::Not => operand.const_not(),
+                UnaOp::IsNan => panic!("IsNan operation is not supported for integer values"),
             }
             Scope::Local(LocalScope { builder: b, .. }) => match op {
                 UnaOp::Neg if no_wrap && signed => b.build_int_nsw_neg(operand, ""),
                 UnaOp::Neg if no_wrap && !signed => b.build_int_nuw_neg(operand, ""),
                 UnaOp::Neg => b.build_int_neg(operand, ""),
                 UnaOp::Not => b.build_not(operand, ""),
+                UnaOp::IsNan => panic!("IsNan operation is not supported for integer values"),
             }.unwrap(),
         }
     }
@@ -290,13 +490,34 @@ impl Llvm {
         op: UnaOp,
         operand: llvm::Float<'ctx>,
         scope: &LocalScope<'ctx, '_>,
-    ) -> llvm::Float<'ctx> {
+    ) -> llvm::RawValue<'ctx> {
         match op {
-            UnaOp::Neg => scope.builder.build_float_neg(operand, "").unwrap(),
+            UnaOp::Neg => scope.builder.build_float_neg(operand, "").unwrap().as_basic_value_enum(),
             UnaOp::Not => panic!("Not operation is not supported for float values"),
+            // Only NaN is unordered with itself
+            UnaOp::IsNan => scope.builder
+                .build_float_compare(llvm::FloatCmpOp::UNO, operand, operand, "")
+                .unwrap()
+                .as_basic_value_enum(),
         }
     }
 
+    fn compile_vector_una<'ctx>(
+        &'ctx self,
+        op: UnaOp,
+        operand: llvm::Vector<'ctx>,
+        scope: &LocalScope<'ctx, '_>,
+    ) -> llvm::RawValue<'ctx> {
+        let float = operand.get_type().get_element_type().is_float_type();
+
+        match op {
+            UnaOp::Neg if float => scope.builder.build_float_neg(operand, ""),
+            UnaOp::Neg => scope.builder.build_int_neg(operand, ""),
+            UnaOp::Not if !float => scope.builder.build_not(operand, ""),
+            _ => panic!("Unsupported vector operation: {:?}", op),
+        }.unwrap().as_basic_value_enum()
+    }
+
     fn compile_lit(&self, value: &Lit) -> llvm::Value {
         match value {
             Lit::Num(x) => self.compile_num(x),
@@ -360,6 +581,7 @@ impl Llvm {
                     
                     llvm::Value::new(raw_value, Some(false))
                 },
+                Int::Untyped(_) => panic!("Untyped literal {:?} was not typed before compilation", x),
             },
             Num::Float(x) => {
                 let raw_value = match x {
@@ -369,6 +591,7 @@ impl Llvm {
                     Float::F64(x) => self.context.f64_type()
                         .const_float(*x)
                         .as_basic_value_enum(),
+                    Float::Untyped(_) => panic!("Untyped literal {:?} was not typed before compilation", x),
                 };
                 llvm::Value::new(raw_value, None)
             },
@@ -380,28 +603,472 @@ impl Llvm {
         value: &Call,
         scope: Scope<'ctx, '_, '_>,
     ) -> Option<llvm::Value<'ctx>> {
-        let function_value = scope.to_local()?.module
-            .get_function(&value.function_name)
-            .expect("Failed to get function");
-        let signage = function_value
-            .get_string_attribute(llvm::AttributeLoc::Return, "signage")
-            .map(|x| x.get_string_value().to_bytes() == b"signed");
+        let local_scope = scope.to_local()?;
+        let id = local_scope.symbols.callee(value);
+        let signage = match local_scope.symbols.function(id).signature.output.as_slice() {
+            [output] => output.get_signage(),
+            _ => None,
+        };
+
+        let call_site = self.build_call(value, local_scope)?;
+        let value = call_site.try_as_basic_value()
+            .left()
+            .unwrap_or_else(|| panic!("Function {} has no return type", value.function_name));
 
-        if function_value.get_type().get_return_type().is_none() {
-            panic!("Function {} has no return type", value.function_name);
+        Some(llvm::Value::new(value, signage))
+    }
+
+    /// Builds a call to a function in the module, whose result is the value of the call site if it has outputs. Inside
+    /// of a try statement, the call is an invoke.
+    pub(crate) fn build_call<'ctx>(
+        &'ctx self,
+        value: &Call,
+        scope: &LocalScope<'ctx, '_>,
+    ) -> Option<llvm::CallSite<'ctx>> {
+        let id = scope.symbols.callee(value);
+        let function_value = scope.functions[id.0 as usize];
+
+        let args = self.compile_args(&value.args, &scope.symbols.function(id).signature, scope)?;
+        let callee = function_value.as_global_value().as_pointer_value();
+        let call_site = scope.build_call_site(function_value.get_type(), callee, &args);
+        // A call with a convention that differs from the callee's is undefined behavior
+        call_site.set_call_convention(function_value.get_call_conventions());
+
+        Some(call_site)
+    }
+
+    fn compile_func_addr<'ctx>(&'ctx self, value: &FuncAddr, scope: &LocalScope<'ctx, '_>) -> llvm::Value<'ctx> {
+        let id = scope.symbols.addressed(value);
+        let ptr = scope.functions[id.0 as usize].as_global_value().as_pointer_value();
+
+        llvm::Value::new(ptr.into(), None)
+    }
+
+    fn compile_call_indirect<'ctx>(
+        &'ctx self,
+        value: &CallIndirect,
+        scope: &LocalScope<'ctx, '_>,
+    ) -> Option<llvm::Value<'ctx>> {
+        let signage = match value.signature.output.as_slice() {
+            [output] => output.get_signage(),
+            _ => None,
+        };
+
+        let call_site = self.build_call_indirect(value, scope)?;
+        let value = call_site.try_as_basic_value()
+            .left()
+            .unwrap_or_else(|| panic!("Indirectly called function has no return type"));
+
+        Some(llvm::Value::new(value, signage))
+    }
+
+    pub(crate) fn build_call_indirect<'ctx>(
+        &'ctx self,
+        value: &CallIndirect,
+        scope: &LocalScope<'ctx, '_>,
+    ) -> Option<llvm::CallSite<'ctx>> {
+        let callee = self.compile_address(&value.callee, scope);
+        let function_type = self.compile_signature(&value.signature);
+
+        let args = self.compile_args(&value.args, &value.signature, scope)?;
+        let call_site = scope.build_call_site(function_type, callee, &args);
+        call_site.set_call_convention(self.compile_call_conv(value.signature.call_conv));
+
+        Some(call_site)
+    }
+
+    /// Compiles the arguments of a call, applying the default argument promotions of C to those after the inputs of a
+    /// variadic function.
+    fn compile_args<'ctx>(
+        &'ctx self,
+        args: &[Value],
+        signature: &Signature,
+        scope: &LocalScope<'ctx, '_>,
+    ) -> Option<Vec<llvm::RawValue<'ctx>>> {
+        if signature.variadic && args.len() < signature.input.len() {
+            panic!("Variadic call passes {} arguments but expected at least {}", args.len(), signature.input.len());
         }
 
-        let mut args = Vec::with_capacity(value.args.len());
+        let mut compiled_args = Vec::with_capacity(args.len());
+        for (i, arg) in args.iter().enumerate() {
+            let compiled_arg = self.compile_value(arg, Scope::Local(scope))?;
+            if signature.variadic && i >= signature.input.len() {
+                compiled_args.push(self.promote_variadic_arg(compiled_arg, scope));
+            } else {
+                compiled_args.push(compiled_arg.raw);
+            }
+        }
+
+        Some(compiled_args)
+    }
+
+    fn promote_variadic_arg<'ctx>(
+        &'ctx self,
+        value: llvm::Value<'ctx>,
+        scope: &LocalScope<'ctx, '_>,
+    ) -> llvm::RawValue<'ctx> {
+        let ty = self.ast_type(value.raw.get_type(), value.signage)
+            .unwrap_or_else(|| panic!("Type cannot be passed to a variadic function: {:?}", value.raw.get_type()));
+
+        let promoted = ty.c_promoted();
+        if promoted == ty {
+            return value.raw;
+        }
+
+        let promoted_type = self.compile_type(&promoted);
+        match promoted {
+            Type::Scalar(ScalarType::Num(NumType::Float(_))) => scope.builder
+                .build_float_ext(value.raw.into_float_value(), promoted_type.into_float_type(), "")
+                .unwrap()
+                .into(),
+            _ if value.signage == Some(true) => scope.builder
+                .build_int_s_extend(value.raw.into_int_value(), promoted_type.into_int_type(), "")
+                .unwrap()
+                .into(),
+            _ => scope.builder
+                .build_int_z_extend(value.raw.into_int_value(), promoted_type.into_int_type(), "")
+                .unwrap()
+                .into(),
+        }
+    }
+
+    fn compile_intrinsic<'ctx>(
+        &'ctx self,
+        value: &Intrinsic,
+        scope: &LocalScope<'ctx, '_>,
+    ) -> Option<llvm::Value<'ctx>> {
+        if value.args.len() != value.op.arity() {
+            panic!("Intrinsic {:?} takes {} arguments but found {}", value.op, value.op.arity(), value.args.len());
+        }
+
+        let mut args = Vec::with_capacity(value.args.len() + 1);
         for arg in &value.args {
-            let compiled_arg = self.compile_value(arg, scope)?;
-            args.push(compiled_arg.raw.into());
+            args.push(self.compile_value(arg, Scope::Local(scope))?);
         }
-        let value = scope.to_local()?.builder
-            .build_call(function_value, &args, "")
+        let (ty, signage) = (args[0].raw.get_type(), args[0].signage);
+
+        if !self.ast_type(ty, signage).is_some_and(|x| value.op.supports(x)) {
+            panic!("Intrinsic {:?} does not support values of type {:?}", value.op, ty);
+        }
+
+        let float = ty.is_float_type();
+        let signed = signage.unwrap_or(false);
+        let name = match value.op {
+            IntrinsicOp::Sqrt => "llvm.sqrt",
+            IntrinsicOp::Fma => "llvm.fma",
+            IntrinsicOp::Floor => "llvm.floor",
+            IntrinsicOp::Ceil => "llvm.ceil",
+            IntrinsicOp::Trunc => "llvm.trunc",
+            IntrinsicOp::CopySign => "llvm.copysign",
+            IntrinsicOp::Abs if float => "llvm.fabs",
+            IntrinsicOp::Abs => "llvm.abs",
+            // Unlike `minnum` and `maxnum`, these propagate NaN and order the zeros like Cranelift
+            IntrinsicOp::Min if float => "llvm.minimum",
+            IntrinsicOp::Min if signed => "llvm.smin",
+            IntrinsicOp::Min => "llvm.umin",
+            IntrinsicOp::Max if float => "llvm.maximum",
+            IntrinsicOp::Max if signed => "llvm.smax",
+            IntrinsicOp::Max => "llvm.umax",
+            IntrinsicOp::CtPop => "llvm.ctpop",
+            IntrinsicOp::Clz => "llvm.ctlz",
+            IntrinsicOp::Ctz => "llvm.cttz",
+            IntrinsicOp::BSwap => "llvm.bswap",
+        };
+        let intrinsic = scope.intrinsic(name, &[ty]);
+
+        let mut args = args.into_iter().map(|x| x.raw.into()).collect::<Vec<llvm::MetadataValue>>();
+        // The flag makes the minimum signed integer or zero poison, rather than wrapping or producing the width
+        if !float && matches!(value.op, IntrinsicOp::Abs | IntrinsicOp::Clz | IntrinsicOp::Ctz) {
+            args.push(self.context.bool_type().const_zero().into());
+        }
+
+        let value = scope.builder
+            .build_call(intrinsic, &args, "")
             .unwrap()
             .try_as_basic_value()
             .unwrap_left();
-        
+
         Some(llvm::Value::new(value, signage))
     }
+
+    fn compile_atomic_load<'ctx>(
+        &'ctx self,
+        value: &AtomicLoad,
+        scope: &LocalScope<'ctx, '_>,
+    ) -> Option<llvm::Value<'ctx>> {
+        if !value.ordering.supports_load() {
+            panic!("Atomic load does not support {:?} ordering", value.ordering);
+        }
+        if !value.ty.is_atomic() {
+            panic!("Type cannot be accessed atomically: {:?}", value.ty);
+        }
+
+        let ptr = self.compile_address(&value.ptr, scope);
+        let ty = self.compile_type(&value.ty);
+        let raw = scope.builder.build_load(ty, ptr, "").unwrap();
+
+        let load = raw.as_instruction_value().unwrap();
+        load.set_atomic_ordering(Llvm::compile_atomic_ordering(value.ordering)).unwrap();
+        load.set_alignment(Llvm::atomic_alignment(ty)).unwrap();
+
+        Some(llvm::Value::new(raw, value.ty.get_signage()))
+    }
+
+    fn compile_atomic_rmw<'ctx>(
+        &'ctx self,
+        value: &AtomicRmw,
+        scope: &LocalScope<'ctx, '_>,
+    ) -> Option<llvm::Value<'ctx>> {
+        let ptr = self.compile_address(&value.ptr, scope);
+        let operand = self.compile_value(&value.value, Scope::Local(scope))?;
+
+        let ty = self.atomic_type(operand);
+        if !value.op.supports(ty) {
+            panic!("Atomic operation {:?} does not support values of type {:?}", value.op, ty);
+        }
+
+        let signed = operand.signage.unwrap_or(false);
+        let op = match value.op {
+            AtomicRmwOp::Add => llvm::AtomicRMWBinOp::Add,
+            AtomicRmwOp::Sub => llvm::AtomicRMWBinOp::Sub,
+            AtomicRmwOp::And => llvm::AtomicRMWBinOp::And,
+            AtomicRmwOp::Or => llvm::AtomicRMWBinOp::Or,
+            AtomicRmwOp::Xor => llvm::AtomicRMWBinOp::Xor,
+            AtomicRmwOp::Xchg => llvm::AtomicRMWBinOp::Xchg,
+            AtomicRmwOp::Min if signed => llvm::AtomicRMWBinOp::Min,
+            AtomicRmwOp::Min => llvm::AtomicRMWBinOp::UMin,
+            AtomicRmwOp::Max if signed => llvm::AtomicRMWBinOp::Max,
+            AtomicRmwOp::Max => llvm::AtomicRMWBinOp::UMax,
+        };
+        let ordering = Llvm::compile_atomic_ordering(value.ordering);
+
+        // Inkwell only exchanges integers, so a pointer is exchanged as an integer of the same width
+        let raw = if operand.raw.is_pointer_value() {
+            let int_type = self.context.custom_width_int_type(usize::BITS);
+            let operand = scope.builder.build_ptr_to_int(operand.raw.into_pointer_value(), int_type, "").unwrap();
+            let previous = scope.builder.build_atomicrmw(op, ptr, operand, ordering).unwrap();
+            let ptr_type = self.context.ptr_type(llvm::AddressSpace::default());
+            scope.builder.build_int_to_ptr(previous, ptr_type, "").unwrap().as_basic_value_enum()
+        } else {
+            let operand = operand.raw.into_int_value();
+            scope.builder.build_atomicrmw(op, ptr, operand, ordering).unwrap().as_basic_value_enum()
+        };
+
+        Some(llvm::Value::new(raw, operand.signage))
+    }
+
+    fn compile_cmpxchg<'ctx>(&'ctx self, value: &CmpXchg, scope: &LocalScope<'ctx, '_>) -> Option<llvm::Value<'ctx>> {
+        let ptr = self.compile_address(&value.ptr, scope);
+        let expected = self.compile_value(&value.expected, Scope::Local(scope))?;
+        let replacement = self.compile_value(&value.replacement, Scope::Local(scope))?;
+
+        let ty = self.atomic_type(expected);
+        if replacement.raw.get_type() != expected.raw.get_type() {
+            panic!("Replacement type of cmpxchg does not match expected type which is {:?}", ty);
+        }
+
+        // A failed comparison does not write, so it cannot release
+        let failure = match value.ordering {
+            AtomicOrdering::Release => AtomicOrdering::Relaxed,
+            AtomicOrdering::AcqRel => AtomicOrdering::Acquire,
+            ordering => ordering,
+        };
+        let result = scope.builder
+            .build_cmpxchg(
+                ptr,
+                expected.raw,
+                replacement.raw,
+                Llvm::compile_atomic_ordering(value.ordering),
+                Llvm::compile_atomic_ordering(failure),
+            )
+            .unwrap();
+        let previous = scope.builder.build_extract_value(result, 0, "").unwrap();
+
+        Some(llvm::Value::new(previous, expected.signage))
+    }
+
+    pub(crate) fn compile_address<'ctx>(&'ctx self, ptr: &Value, scope: &LocalScope<'ctx, '_>) -> llvm::Pointer<'ctx> {
+        let ptr = self.compile_value(ptr, Scope::Local(scope)).unwrap();
+        match ptr.raw {
+            llvm::RawValue::PointerValue(x) => x,
+            _ => panic!("Address must be a pointer but found {:?}", ptr.raw.get_type()),
+        }
+    }
+
+    /// The AST type of a value accessed atomically, which must be an integer of up to 64 bits or a pointer.
+    pub(crate) fn atomic_type(&self, value: llvm::Value) -> Type {
+        self.ast_type(value.raw.get_type(), value.signage)
+            .filter(|x| x.is_atomic())
+            .unwrap_or_else(|| panic!("Type cannot be accessed atomically: {:?}", value.raw.get_type()))
+    }
+
+    pub(crate) fn compile_atomic_ordering(ordering: AtomicOrdering) -> llvm::AtomicOrdering {
+        match ordering {
+            AtomicOrdering::Relaxed => llvm::AtomicOrdering::Monotonic,
+            AtomicOrdering::Acquire => llvm::AtomicOrdering::Acquire,
+            AtomicOrdering::Release => llvm::AtomicOrdering::Release,
+            AtomicOrdering::AcqRel => llvm::AtomicOrdering::AcquireRelease,
+            AtomicOrdering::SeqCst => llvm::AtomicOrdering::SequentiallyConsistent,
+        }
+    }
+
+    /// The alignment of an atomic access to a value of type `ty`, which must be its size.
+    pub(crate) fn atomic_alignment(ty: llvm::Type) -> u32 {
+        match ty {
+            llvm::Type::IntType(x) => x.get_bit_width() / 8,
+            // Modules are always compiled for the host, so pointers have the size of a host pointer
+            _ => usize::BITS / 8,
+        }
+    }
+
+    fn compile_splat<'ctx>(&'ctx self, value: &Splat, scope: &LocalScope<'ctx, '_>) -> Option<llvm::Value<'ctx>> {
+        let lane = self.compile_value(&value.value, Scope::Local(scope))?;
+        let lanes = vec![lane.raw; value.len as usize];
+
+        Some(llvm::Value::new(self.build_vector(&lanes, scope), lane.signage))
+    }
+
+    fn compile_vector<'ctx>(&'ctx self, value: &Vector, scope: &LocalScope<'ctx, '_>) -> Option<llvm::Value<'ctx>> {
+        let mut lanes = Vec::with_capacity(value.elems.len());
+        for elem in &value.elems {
+            lanes.push(self.compile_value(elem, Scope::Local(scope))?);
+        }
+        let Some(signage) = lanes.first().map(|x| x.signage) else {
+            panic!("Vector has no lanes");
+        };
+        let lanes = lanes.into_iter().map(|x| x.raw).collect::<Vec<_>>();
+
+        Some(llvm::Value::new(self.build_vector(&lanes, scope), signage))
+    }
+
+    fn compile_extract_element<'ctx>(
+        &'ctx self,
+        value: &ExtractElement,
+        scope: &LocalScope<'ctx, '_>,
+    ) -> Option<llvm::Value<'ctx>> {
+        let vector = self.compile_value(&value.vector, Scope::Local(scope))?;
+        let raw = self.check_lane(vector.raw, value.index);
+
+        let index = self.context.i32_type().const_int(value.index as u64, false);
+        let lane = scope.builder.build_extract_element(raw, index, "").unwrap();
+
+        Some(llvm::Value::new(lane, vector.signage))
+    }
+
+    fn compile_insert_element<'ctx>(
+        &'ctx self,
+        value: &InsertElement,
+        scope: &LocalScope<'ctx, '_>,
+    ) -> Option<llvm::Value<'ctx>> {
+        let vector = self.compile_value(&value.vector, Scope::Local(scope))?;
+        let lane = self.compile_value(&value.value, Scope::Local(scope))?;
+        let raw = self.check_lane(vector.raw, value.index);
+
+        let index = self.context.i32_type().const_int(value.index as u64, false);
+        let raw = scope.builder.build_insert_element(raw, lane.raw, index, "").unwrap();
+
+        Some(llvm::Value::new(raw.as_basic_value_enum(), vector.signage))
+    }
+
+    fn compile_shuffle<'ctx>(&'ctx self, value: &Shuffle, scope: &LocalScope<'ctx, '_>) -> Option<llvm::Value<'ctx>> {
+        let lhs = self.compile_value(&value.lhs, Scope::Local(scope))?;
+        let rhs = self.compile_value(&value.rhs, Scope::Local(scope))?;
+
+        let ty = lhs.raw.get_type();
+        let llvm::Type::VectorType(vector_type) = ty else {
+            panic!("Shuffle mask {:?} does not have a lane for each lane of {:?}", value.mask, ty);
+        };
+        if value.mask.len() != vector_type.get_size() as usize {
+            panic!("Shuffle mask {:?} does not have a lane for each lane of {:?}", value.mask, ty);
+        }
+        if value.mask.iter().any(|x| *x >= 2 * vector_type.get_size()) {
+            panic!("Shuffle mask {:?} is out of bounds of two {:?}", value.mask, ty);
+        }
+        if rhs.raw.get_type() != ty {
+            panic!("Right-hand value type of shuffle does not match left-hand type which is {:?}", ty);
+        }
+
+        let mask = value.mask
+            .iter()
+            .map(|x| self.context.i32_type().const_int(*x as u64, false))
+            .collect::<Vec<_>>();
+        let mask = llvm::VectorType::const_vector(&mask);
+        let raw = scope.builder
+            .build_shuffle_vector(lhs.raw.into_vector_value(), rhs.raw.into_vector_value(), mask, "")
+            .unwrap();
+
+        Some(llvm::Value::new(raw.as_basic_value_enum(), lhs.signage))
+    }
+
+    fn compile_reduce<'ctx>(&'ctx self, value: &Reduce, scope: &LocalScope<'ctx, '_>) -> Option<llvm::Value<'ctx>> {
+        let vector = self.compile_value(&value.vector, Scope::Local(scope))?;
+        let llvm::Type::VectorType(ty) = vector.raw.get_type() else {
+            panic!("Type does not support reductions: {:?}", vector.raw.get_type());
+        };
+
+        let float = ty.get_element_type().is_float_type();
+        let signed = vector.signage.unwrap_or(false);
+        let name = match value.op {
+            ReduceOp::Sum if float => "llvm.vector.reduce.fadd",
+            ReduceOp::Sum => "llvm.vector.reduce.add",
+            ReduceOp::Min if float => "llvm.vector.reduce.fminimum",
+            ReduceOp::Min if signed => "llvm.vector.reduce.smin",
+            ReduceOp::Min => "llvm.vector.reduce.umin",
+            ReduceOp::Max if float => "llvm.vector.reduce.fmaximum",
+            ReduceOp::Max if signed => "llvm.vector.reduce.smax",
+            ReduceOp::Max => "llvm.vector.reduce.umax",
+        };
+        let intrinsic = scope.intrinsic(name, &[ty.as_basic_type_enum()]);
+
+        let mut args: Vec<llvm::MetadataValue> = Vec::with_capacity(2);
+        // Without fast-math flags the lanes are added in order to the start value, and `-0.0 + x` is always `x`
+        if float && value.op == ReduceOp::Sum {
+            args.push(ty.get_element_type().into_float_type().const_float(-0.0).into());
+        }
+        args.push(vector.raw.into());
+
+        let raw = scope.builder
+            .build_call(intrinsic, &args, "")
+            .unwrap()
+            .try_as_basic_value()
+            .unwrap_left();
+
+        Some(llvm::Value::new(raw, vector.signage))
+    }
+
+    fn build_vector<'ctx>(
+        &'ctx self,
+        lanes: &[llvm::RawValue<'ctx>],
+        scope: &LocalScope<'ctx, '_>,
+    ) -> llvm::RawValue<'ctx> {
+        let ty = match lanes[0].get_type() {
+            llvm::Type::IntType(x) => x.vec_type(lanes.len() as u32),
+            llvm::Type::FloatType(x) => x.vec_type(lanes.len() as u32),
+            llvm::Type::PointerType(x) => x.vec_type(lanes.len() as u32),
+            ty => panic!("Failed to create a vector of {} lanes of {:?}", lanes.len(), ty),
+        };
+
+        // The builder folds the insertion of constants, so a vector of constants is a constant
+        let mut vector = ty.get_poison();
+        for (i, lane) in lanes.iter().enumerate() {
+            let index = self.context.i32_type().const_int(i as u64, false);
+            vector = scope.builder.build_insert_element(vector, *lane, index, "").unwrap();
+        }
+
+        vector.as_basic_value_enum()
+    }
+
+    fn check_lane<'ctx>(&self, vector: llvm::RawValue<'ctx>, index: u32) -> llvm::Vector<'ctx> {
+        match vector {
+            llvm::RawValue::VectorValue(x) if index < x.get_type().get_size() => x,
+            _ => panic!("Lane {} is out of bounds of {:?}", index, vector.get_type()),
+        }
+    }
+}
+
+fn strip_spans<'v, 's>(value: &'v Value<'s>) -> &'v Value<'s> {
+    match value {
+        Value::Spanned(x) => strip_spans(&x.node),
+        _ => value,
+    }
 }
@@ -0,0 +1,39 @@
+//! Modules and functions that several tests run on the backends.
+
+use dorian::prelude::*;
+
+/// Collects `functions` into a module.
+pub fn module<'s>(functions: impl IntoIterator<Item = Function<'s>>) -> Module<'s> {
+    let mut module = Module::new("differential");
+    for function in functions {
+        module.add_function(function);
+    }
+    module
+}
+
+/// Computes the Fibonacci number of its argument in a loop.
+pub fn iterative_fib() -> Module<'static> {
+    use val::*;
+
+    module([Function::new("iterative_fib")
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.if_then(le(arg(0), lit(1u32)), |scope| {
+                scope.ret([arg(0)]);
+            });
+
+            scope.bind("a", lit(0u32));
+            scope.bind("b", lit(1u32));
+            scope.bind("i", lit(2u32));
+
+            scope.loop_while(le(var("i"), arg(0)), |scope| {
+                scope.assign(var("i"), add(var("i"), lit(1u32)));
+                scope.bind("c", add(var("a"), var("b")));
+                scope.assign(var("a"), var("b"));
+                scope.assign(var("b"), var("c"));
+            });
+
+            scope.ret([var("b")]);
+        })])
+}
@@ -0,0 +1,214 @@
+//! Differential testing of the Dorian backends.
+//!
+//! Every enabled backend compiles the same [`Module`] and runs the same entry function with the same arguments. Any
+//! difference in the results, including a backend failing to compile, panicking or trapping, is reported together with
+//! the AST.
+
+pub mod fixtures;
+
+use std::any::Any;
+use std::fmt::{Debug, Write};
+use std::panic::{self, AssertUnwindSafe};
+use dorian::ast::module::Module;
+use dorian::jit::{Jit, JitFn};
+
+/// The result of running an entry function with a single backend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Run<T> {
+    pub backend: &'static str,
+    pub result: Result<T, String>,
+}
+
+/// The backends that the harness runs, and how they are configured.
+#[derive(Debug, Clone, Default)]
+pub struct Backends {
+    #[cfg(feature = "llvm")]
+    optimization: dorian::llvm::Optimization,
+}
+
+impl Backends {
+    pub fn new() -> Self {
+        Backends::default()
+    }
+
+    /// Runs the optimization pipeline on the modules compiled by the LLVM backend, which runs none by default.
+    #[cfg(feature = "llvm")]
+    pub fn with_optimization(mut self, optimization: dorian::llvm::Optimization) -> Self {
+        self.optimization = optimization;
+        self
+    }
+
+    /// Runs `entry` with `args` on every enabled backend.
+    ///
+    /// # Safety
+    ///
+    /// Every backend calls `entry`, see [`JitFunction::apply`](dorian::jit::JitFunction::apply).
+    pub unsafe fn run_all<F>(&self, ast_module: &Module, entry: &str, args: F::Args) -> Vec<Run<F::Output>>
+    where
+        F: JitFn,
+        F::Args: Clone,
+    {
+        unsafe { self.run_all_linked::<F>(std::slice::from_ref(ast_module), entry, args) }
+    }
+
+    /// Runs `entry` with `args` on every enabled backend, after compiling `ast_modules` together.
+    ///
+    /// # Safety
+    ///
+    /// Every backend calls `entry`, see [`JitFunction::apply`](dorian::jit::JitFunction::apply).
+    pub unsafe fn run_all_linked<F>(&self, ast_modules: &[Module], entry: &str, args: F::Args) -> Vec<Run<F::Output>>
+    where
+        F: JitFn,
+        F::Args: Clone,
+    {
+        self.each(|backend| unsafe { run::<F>(backend, ast_modules, entry, args.clone()) })
+    }
+
+    /// Compiles `ast_modules` together with every enabled backend, without running anything.
+    pub fn compile_all(&self, ast_modules: &[Module]) -> Vec<Run<()>> {
+        self.each(|backend| catch_panic(|| backend.jit_modules(ast_modules).map(|_| ()).map_err(|e| e.to_string())))
+    }
+
+    /// Runs `entry` with `args` on every enabled backend and panics unless they all succeed with identical results.
+    ///
+    /// # Safety
+    ///
+    /// Every backend calls `entry`, see [`JitFunction::apply`](dorian::jit::JitFunction::apply).
+    pub unsafe fn assert_agree<F>(&self, ast_module: &Module, entry: &str, args: F::Args) -> F::Output
+    where
+        F: JitFn,
+        F::Args: Clone + Debug,
+        F::Output: PartialEq + Debug,
+    {
+        let runs = unsafe { self.run_all::<F>(ast_module, entry, args.clone()) };
+
+        let agree = runs.iter().all(|x| x.result.is_ok())
+            && runs.windows(2).all(|x| x[0].result == x[1].result);
+        if !agree || runs.is_empty() {
+            panic!("{}", report(ast_module, entry, &args, &runs));
+        }
+
+        runs.into_iter().next().unwrap().result.unwrap()
+    }
+
+    /// Compiles `ast_modules` together with every enabled backend and panics unless each one fails with an error that
+    /// starts with `message`.
+    pub fn assert_reject(&self, ast_modules: &[Module], message: &str) {
+        let runs = self.compile_all(ast_modules);
+
+        let rejected = runs.iter().all(|x| x.result.as_ref().is_err_and(|e| e.starts_with(message)));
+        if !rejected || runs.is_empty() {
+            panic!("Expected every backend to fail with '{}...', got {:#?}", message, runs);
+        }
+    }
+
+    /// Calls `f` with each enabled backend, configured as described by `self`.
+    #[allow(clippy::vec_init_then_push)]
+    fn each<T>(&self, mut f: impl FnMut(&mut dyn Jit) -> Result<T, String>) -> Vec<Run<T>> {
+        let mut runs = Vec::new();
+
+        #[cfg(feature = "llvm")]
+        runs.push(Run {
+            backend: "llvm",
+            result: f(&mut dorian::llvm::Llvm::new().with_optimization(self.optimization.clone())),
+        });
+
+        #[cfg(feature = "cranelift")]
+        runs.push(Run {
+            backend: "cranelift",
+            result: f(&mut dorian::cranelift::Cranelift::new()),
+        });
+
+        runs
+    }
+}
+
+/// Runs `entry` with `args` on every enabled backend.
+///
+/// # Safety
+///
+/// Every backend calls `entry`, see [`JitFunction::apply`](dorian::jit::JitFunction::apply).
+pub unsafe fn run_all<F>(ast_module: &Module, entry: &str, args: F::Args) -> Vec<Run<F::Output>>
+where
+    F: JitFn,
+    F::Args: Clone,
+{
+    unsafe { Backends::new().run_all::<F>(ast_module, entry, args) }
+}
+
+/// Runs `entry` with `args` on every enabled backend, after compiling `ast_modules` together.
+///
+/// # Safety
+///
+/// Every backend calls `entry`, see [`JitFunction::apply`](dorian::jit::JitFunction::apply).
+pub unsafe fn run_all_linked<F>(ast_modules: &[Module], entry: &str, args: F::Args) -> Vec<Run<F::Output>>
+where
+    F: JitFn,
+    F::Args: Clone,
+{
+    unsafe { Backends::new().run_all_linked::<F>(ast_modules, entry, args) }
+}
+
+/// Runs `entry` with `args` on every enabled backend and panics unless they all succeed with identical results.
+///
+/// # Safety
+///
+/// Every backend calls `entry`, see [`JitFunction::apply`](dorian::jit::JitFunction::apply).
+pub unsafe fn assert_backends_agree<F>(ast_module: &Module, entry: &str, args: F::Args) -> F::Output
+where
+    F: JitFn,
+    F::Args: Clone + Debug,
+    F::Output: PartialEq + Debug,
+{
+    unsafe { Backends::new().assert_agree::<F>(ast_module, entry, args) }
+}
+
+/// Compiles `ast_modules` together with every enabled backend and panics unless each one fails with an error that
+/// starts with `message`.
+pub fn assert_backends_reject(ast_modules: &[Module], message: &str) {
+    Backends::new().assert_reject(ast_modules, message)
+}
+
+unsafe fn run<F: JitFn>(
+    backend: &mut dyn Jit,
+    ast_modules: &[Module],
+    entry: &str,
+    args: F::Args,
+) -> Result<F::Output, String> {
+    catch_panic(|| {
+        let jit_module = backend.jit_modules(ast_modules).map_err(|e| e.to_string())?;
+        let function = jit_module.get::<F>(entry).map_err(|e| e.to_string())?;
+
+        unsafe { function.try_apply(args) }.map_err(|e| e.to_string())
+    })
+}
+
+/// Calls `f`, and reports a panic in it as an error.
+fn catch_panic<T>(f: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|payload| Err(format!("panicked: {}", panic_message(payload.as_ref()))))
+}
+
+fn panic_message(payload: &dyn Any) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "<non-string panic payload>"
+    }
+}
+
+fn report<T: Debug>(ast_module: &Module, entry: &str, args: &impl Debug, runs: &[Run<T>]) -> String {
+    let mut report = format!("Backends disagree when calling '{}' with {:?}\n", entry, args);
+
+    if runs.is_empty() {
+        report.push_str("No backends are enabled\n");
+    }
+    for run in runs {
+        let _ = writeln!(report, "  {}: {:?}", run.backend, run.result);
+    }
+
+    let _ = write!(report, "\n{:#?}", ast_module);
+    report
+}
@@ -0,0 +1,126 @@
+//! Integer and float arithmetic, including checked operations and intrinsics.
+
+use dorian::prelude::*;
+use dorian_test::fixtures::module;
+use dorian_test::assert_backends_agree;
+
+#[test]
+fn shift_right_respects_signage() {
+    use val::*;
+
+    let signed = Function::new("signed_shr")
+        .add_input(ty::s32())
+        .add_output(ty::s32())
+        .build_block(|scope| scope.ret([shr(arg(0), lit(1i32))]));
+    let unsigned = Function::new("unsigned_shr")
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| scope.ret([shr(arg(0), lit(1u32))]));
+
+    let result = unsafe { assert_backends_agree::<fn(i32) -> i32>(&module([signed]), "signed_shr", (-8,)) };
+    assert_eq!(result, -4);
+
+    let result = unsafe { assert_backends_agree::<fn(u32) -> u32>(&module([unsigned]), "unsigned_shr", (u32::MAX,)) };
+    assert_eq!(result, u32::MAX >> 1);
+}
+
+#[test]
+fn checked_arithmetic_flags_overflow() {
+    use val::*;
+
+    let flags = Function::new("flags")
+        .add_input(ty::s8())
+        .add_input(ty::s8())
+        .add_output(ty::u8())
+        .build_block(|scope| {
+            scope.bind_tuple(["sum", "add_overflowed"], checked_add(arg(0), arg(1)));
+            scope.bind_tuple(["difference", "sub_overflowed"], checked_sub(arg(0), arg(1)));
+            scope.bind_tuple(["product", "mul_overflowed"], checked_mul(arg(0), arg(1)));
+            scope.bind("flags", lit_int(0));
+            scope.if_then(var("add_overflowed"), |scope| {
+                scope.assign(var("flags"), bit_or(var("flags"), lit_int(1)));
+            });
+            scope.if_then(var("sub_overflowed"), |scope| {
+                scope.assign(var("flags"), bit_or(var("flags"), lit_int(2)));
+            });
+            scope.if_then(var("mul_overflowed"), |scope| {
+                scope.assign(var("flags"), bit_or(var("flags"), lit_int(4)));
+            });
+            scope.ret([var("flags")]);
+        });
+    let wrapped_sum = Function::new("wrapped_sum")
+        .add_input(ty::u8())
+        .add_input(ty::u8())
+        .add_output(ty::u8())
+        .add_output(ty::bool())
+        .build_block(|scope| {
+            scope.ret([checked_add(arg(0), arg(1))]);
+        });
+    let entry = Function::new("entry")
+        .add_input(ty::u8())
+        .add_input(ty::u8())
+        .add_output(ty::u8())
+        .build_block(|scope| {
+            scope.bind_tuple(["sum", "overflowed"], call("wrapped_sum", vec![arg(0), arg(1)]));
+            scope.if_then(var("overflowed"), |scope| {
+                scope.ret([trapping_sub(var("sum"), lit_int(1))]);
+            });
+            scope.ret([trapping_mul(var("sum"), lit_int(2))]);
+        });
+
+    let ast_module = module([flags, wrapped_sum, entry]);
+
+    for (a, b, expected) in [(100, 100, 5), (-128, 1, 2), (3, 4, 0), (-100, 100, 6), (16, 8, 4)] {
+        let result = unsafe { assert_backends_agree::<fn(i8, i8) -> u8>(&ast_module, "flags", (a, b)) };
+        assert_eq!(result, expected, "flags({}, {})", a, b);
+    }
+
+    assert_eq!(unsafe { assert_backends_agree::<fn(u8, u8) -> u8>(&ast_module, "entry", (200, 100)) }, 43);
+    assert_eq!(unsafe { assert_backends_agree::<fn(u8, u8) -> u8>(&ast_module, "entry", (20, 10)) }, 60);
+}
+
+#[test]
+fn intrinsics_compute_math_and_bit_operations() {
+    use val::*;
+
+    let floats = Function::new("floats")
+        .add_input(ty::f64())
+        .add_input(ty::f64())
+        .add_output(ty::f64())
+        .build_block(|scope| {
+            let magnitude = abs(trunc(arg(1)));
+            let sign = min(arg(0), neg(arg(1)));
+            scope.ret([fma(sqrt(arg(0)), floor(arg(1)), copysign(magnitude, sign))]);
+        });
+    let ints = Function::new("ints")
+        .add_input(ty::s64())
+        .add_input(ty::s64())
+        .add_output(ty::s64())
+        .build_block(|scope| {
+            scope.ret([add(abs(arg(0)), mul(min(arg(0), arg(1)), max(arg(0), arg(1))))]);
+        });
+    let bits = Function::new("bits")
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            let counts = add(ctpop(arg(0)), shl(clz(arg(0)), lit_int(8)));
+            scope.ret([add(counts, shl(ctz(arg(0)), lit_int(16)))]);
+        });
+    let swap = Function::new("swap")
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.ret([max(bswap(arg(0)), lit_int(0x100))]);
+        });
+
+    let ast_module = module([floats, ints, bits, swap]);
+
+    assert_eq!(unsafe { assert_backends_agree::<fn(f64, f64) -> f64>(&ast_module, "floats", (16.0, 2.5)) }, 6.0);
+    assert_eq!(unsafe { assert_backends_agree::<fn(f64, f64) -> f64>(&ast_module, "floats", (2.25, -3.5)) }, -3.0);
+    assert_eq!(unsafe { assert_backends_agree::<fn(i64, i64) -> i64>(&ast_module, "ints", (-5, 3)) }, -10);
+    assert_eq!(unsafe { assert_backends_agree::<fn(i64, i64) -> i64>(&ast_module, "ints", (7, -2)) }, -7);
+    assert_eq!(unsafe { assert_backends_agree::<fn(u32) -> u32>(&ast_module, "bits", (0x00F0_0000,)) }, 1312772);
+    assert_eq!(unsafe { assert_backends_agree::<fn(u32) -> u32>(&ast_module, "bits", (0,)) }, 2105344);
+    assert_eq!(unsafe { assert_backends_agree::<fn(u32) -> u32>(&ast_module, "swap", (0x12345678,)) }, 0x78563412);
+    assert_eq!(unsafe { assert_backends_agree::<fn(u32) -> u32>(&ast_module, "swap", (0x01000000,)) }, 0x100);
+}
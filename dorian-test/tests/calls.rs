@@ -0,0 +1,191 @@
+//! Direct, indirect and variadic calls.
+
+use dorian::prelude::*;
+use dorian_test::fixtures::module;
+use dorian_test::assert_backends_agree;
+
+#[test]
+fn calls_between_calling_conventions() {
+    use val::*;
+
+    let square = Function::new("square")
+        .with_call_conv(CallConv::Fast)
+        .with_linkage(Linkage::Internal)
+        .add_input(ty::s64())
+        .add_output(ty::s64())
+        .build_block(|scope| {
+            scope.ret([mul(arg(0), arg(0))]);
+        });
+    let negate = Function::new("negate")
+        .with_call_conv(CallConv::Cold)
+        .with_linkage(Linkage::Weak)
+        .add_input(ty::s64())
+        .add_output(ty::s64())
+        .build_block(|scope| {
+            scope.ret([neg(arg(0))]);
+        });
+    let entry = Function::new("negated_square")
+        .add_input(ty::s64())
+        .add_output(ty::s64())
+        .build_block(|scope| {
+            scope.ret([call("negate", vec![call("square", vec![arg(0)])])]);
+        });
+
+    let ast_module = module([entry, square, negate]);
+
+    let result = unsafe { assert_backends_agree::<fn(i64) -> i64>(&ast_module, "negated_square", (12,)) };
+    assert_eq!(result, -144);
+}
+
+#[test]
+fn call_results_keep_their_signage() {
+    use val::*;
+
+    let identity = Function::new("identity")
+        .add_input(ty::s32())
+        .add_output(ty::s32())
+        .build_block(|scope| {
+            scope.ret([arg(0)]);
+        });
+    let entry = Function::new("halve")
+        .add_input(ty::s32())
+        .add_output(ty::s32())
+        .build_block(|scope| {
+            scope.ret([div(call("identity", vec![arg(0)]), lit(2i32))]);
+        });
+
+    let ast_module = module([entry, identity]);
+
+    let result = unsafe { assert_backends_agree::<fn(i32) -> i32>(&ast_module, "halve", (-7,)) };
+    assert_eq!(result, -3);
+}
+
+#[test]
+fn indirect_calls_go_through_function_addresses() {
+    use val::*;
+
+    let square = Function::new("square")
+        .with_call_conv(CallConv::Fast)
+        .add_input(ty::s64())
+        .add_output(ty::s64())
+        .build_block(|scope| {
+            scope.ret([mul(arg(0), arg(0))]);
+        });
+    let negate = Function::new("negate")
+        .with_call_conv(CallConv::Fast)
+        .add_input(ty::s64())
+        .add_output(ty::s64())
+        .build_block(|scope| {
+            scope.ret([neg(arg(0))]);
+        });
+    let signature = Signature::default()
+        .add_input(ty::s64())
+        .add_output(ty::s64())
+        .with_call_conv(CallConv::Fast);
+    let entry = Function::new("dispatch")
+        .add_input(ty::bool())
+        .add_input(ty::s64())
+        .add_output(ty::s64())
+        .build_block(|scope| {
+            scope.bind("callee", func_addr("square"));
+            scope.if_then(arg(0), |scope| {
+                scope.assign(var("callee"), func_addr("negate"));
+            });
+
+            // The output is signed, so it is divided as a signed integer
+            scope.ret([div(call_indirect(var("callee"), signature, vec![arg(1)]), lit(2i64))]);
+        });
+
+    let ast_module = module([entry, square, negate]);
+
+    let squared = unsafe { assert_backends_agree::<fn(bool, i64) -> i64>(&ast_module, "dispatch", (false, 6)) };
+    assert_eq!(squared, 18);
+    let negated = unsafe { assert_backends_agree::<fn(bool, i64) -> i64>(&ast_module, "dispatch", (true, 7)) };
+    assert_eq!(negated, -3);
+}
+
+#[test]
+fn call_statements_discard_outputs() {
+    use val::*;
+
+    let store = Function::new("store")
+        .add_input(ty::ptr())
+        .add_input(ty::u32())
+        .build_block(|scope| {
+            scope.atomic_store(arg(0), arg(1), AtomicOrdering::Relaxed);
+            scope.ret([]);
+        });
+    let increment = Function::new("increment")
+        .add_input(ty::ptr())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.bind("old", atomic_load(arg(0), ty::u32(), AtomicOrdering::Relaxed));
+            scope.call("store", vec![arg(0), add(var("old"), lit(1u32))]);
+            scope.ret([var("old")]);
+        });
+    let signature = Signature::default().add_input(ty::ptr()).add_output(ty::u32());
+    let entry = Function::new("entry")
+        .add_input(ty::ptr())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.bind("before", atomic_load(arg(0), ty::u32(), AtomicOrdering::Relaxed));
+            scope.call("increment", vec![arg(0)]);
+            scope.call_indirect(func_addr("increment"), signature, vec![arg(0)]);
+            scope.ret([sub(atomic_load(arg(0), ty::u32(), AtomicOrdering::Relaxed), var("before"))]);
+        });
+
+    let ast_module = module([entry, increment, store]);
+
+    // Every backend increments the same counter, so each one returns how much it grew
+    let mut counter = 0u32;
+    let counter = &mut counter as *mut u32;
+    let result = unsafe { assert_backends_agree::<fn(*mut u32) -> u32>(&ast_module, "entry", (counter,)) };
+    assert_eq!(result, 2);
+}
+
+#[test]
+fn variadic_calls_promote_their_arguments() {
+    use val::*;
+
+    let snprintf = Function::new("snprintf")
+        .with_linkage(Linkage::Import)
+        .with_variadic(true)
+        .add_input(ty::ptr())
+        .add_input(ty::u64())
+        .add_input(ty::ptr())
+        .add_output(ty::s32());
+    let entry = Function::new("format")
+        .add_input(ty::ptr())
+        .add_input(ty::ptr())
+        .add_input(ty::s16())
+        .add_input(ty::u8())
+        .add_input(ty::f32())
+        .add_input(ty::f64())
+        .add_output(ty::s32())
+        .build_block(|scope| {
+            let args = vec![
+                arg(0), lit(64u64), arg(1),
+                // Every argument after the format is variadic, and all but the f64 and the s64 are promoted
+                arg(2), arg(3), lit(true), arg(4), arg(5), lit(-9_000_000_000i64),
+                // A comparison of signed integers is a boolean, which is zero-extended like `true`
+                lt(arg(2), lit(0i16)),
+            ];
+            // Calls that pass as many floats share a trampoline on x86-64
+            scope.bind("first", call("snprintf", args.clone()));
+            scope.ret([add(var("first"), call("snprintf", args))]);
+        });
+
+    let ast_module = module([entry, snprintf]);
+
+    let mut buffer = [0u8; 64];
+    let format = c"%d %d %d %.2f %.3f %lld %d";
+    let args = (buffer.as_mut_ptr(), format.as_ptr() as *const u8, -3i16, 200u8, 1.5f32, 2.25f64);
+    // SAFETY: the output fits in the buffer and the format is null-terminated
+    let len = unsafe {
+        assert_backends_agree::<fn(*mut u8, *const u8, i16, u8, f32, f64) -> i32>(&ast_module, "format", args)
+    };
+
+    let expected = "-3 200 1 1.50 2.250 -9000000000 1";
+    assert_eq!(len, 2 * expected.len() as i32);
+    assert_eq!(&buffer[..expected.len() + 1], format!("{}\0", expected).as_bytes());
+}
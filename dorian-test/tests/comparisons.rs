@@ -0,0 +1,76 @@
+//! Comparisons and the bools they produce.
+
+use dorian::prelude::*;
+use dorian_test::fixtures::module;
+use dorian_test::assert_backends_agree;
+
+#[test]
+fn bool_round_trip() {
+    use val::*;
+
+    let function = Function::new("less_than")
+        .add_input(ty::s64())
+        .add_input(ty::s64())
+        .add_output(ty::bool())
+        .build_block(|scope| scope.ret([lt(arg(0), arg(1))]));
+    let module = module([function]);
+
+    assert!(unsafe { assert_backends_agree::<fn(i64, i64) -> bool>(&module, "less_than", (-1, 1)) });
+    assert!(!unsafe { assert_backends_agree::<fn(i64, i64) -> bool>(&module, "less_than", (1, -1)) });
+}
+
+#[test]
+fn bool_operations_produce_booleans() {
+    use val::*;
+
+    // Negating a bool only flips its lowest bit, and comparing signed values produces a bool without signage
+    let function = Function::new("matches")
+        .add_input(ty::bool())
+        .add_input(ty::s8())
+        .add_output(ty::bool())
+        .build_block(|scope| {
+            scope.bind("negated", not(arg(0)));
+            scope.bind("negative", lt(arg(1), lit(0i8)));
+            scope.ret([eq(eq(var("negated"), var("negative")), lit(true))]);
+        });
+    let module = module([function]);
+
+    for (args, expected) in [((true, 5), true), ((false, -3), true), ((true, -3), false), ((false, 5), false)] {
+        assert_eq!(unsafe { assert_backends_agree::<fn(bool, i8) -> bool>(&module, "matches", args) }, expected);
+    }
+}
+
+#[test]
+fn float_comparisons_handle_nan() {
+    use val::*;
+
+    let compare = Function::new("compare")
+        .add_input(ty::f64())
+        .add_input(ty::f64())
+        .add_output(ty::u8())
+        .build_block(|scope| {
+            let comparisons: [Value; 7] = [
+                ne(arg(0), arg(1)),
+                lt(arg(0), arg(1)),
+                is_nan(arg(0)),
+                ordered(arg(0), arg(1)),
+                ordered_ne(arg(0), arg(1)),
+                unordered_eq(arg(0), arg(1)),
+                unordered_ge(arg(0), arg(1)),
+            ];
+
+            scope.bind("flags", lit_int(0));
+            for (i, comparison) in comparisons.into_iter().enumerate() {
+                scope.if_then(comparison, |scope| {
+                    scope.assign(var("flags"), bit_or(var("flags"), lit_int(1 << i)));
+                });
+            }
+            scope.ret([var("flags")]);
+        });
+    let ast_module = module([compare]);
+
+    for (a, b, expected) in [(f64::NAN, 1.0, 0b1100101), (1.0, 2.0, 0b0011011), (1.0, 1.0, 0b1101000)] {
+        let result = unsafe { assert_backends_agree::<fn(f64, f64) -> u8>(&ast_module, "compare", (a, b)) };
+        assert_eq!(result, expected, "compare({}, {})", a, b);
+    }
+}
@@ -0,0 +1,70 @@
+//! Loops, branches and recursion, and the variables they merge.
+
+use dorian::prelude::*;
+use dorian_test::fixtures::{iterative_fib, module};
+use dorian_test::assert_backends_agree;
+
+#[test]
+fn iterative_fib_runs() {
+    let result = unsafe { assert_backends_agree::<fn(u32) -> u32>(&iterative_fib(), "iterative_fib", (10,)) };
+    assert_eq!(result, 55);
+}
+
+#[test]
+fn recursive_fib() {
+    use val::*;
+
+    let function = Function::new("recursive_fib")
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope
+                .if_then(le(arg(0), lit(1u32)), |scope| {
+                    scope.ret([arg(0)]);
+                })
+                .or_else(|scope| {
+                    scope.ret([add(
+                        call("recursive_fib", vec![sub(arg(0), lit(1u32))]),
+                        call("recursive_fib", vec![sub(arg(0), lit(2u32))]),
+                    )]);
+                });
+        });
+
+    let result = unsafe { assert_backends_agree::<fn(u32) -> u32>(&module([function]), "recursive_fib", (10,)) };
+    assert_eq!(result, 55);
+}
+
+#[test]
+fn variables_merge_across_branches_and_loops() {
+    use val::*;
+
+    let function = Function::new("collatz_steps")
+        .add_input(ty::u64())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.bind("n", arg(0));
+            scope.bind("steps", lit(0u32));
+
+            scope.loop_while(ne(var("n"), lit(1u64)), |scope| {
+                scope
+                    .if_then(eq(rem(var("n"), lit(2u64)), lit(0u64)), |scope| {
+                        scope.assign(var("n"), div(var("n"), lit(2u64)));
+                    })
+                    .or_else(|scope| {
+                        scope.bind("m", add(mul(var("n"), lit(3u64)), lit(1u64)));
+                        scope.if_then(lit(true), |scope| {
+                            // Shadows the outer `m` only within this block
+                            scope.bind("m", div(var("m"), lit(2u64)));
+                            scope.assign(var("steps"), add(var("steps"), lit(1u32)));
+                            scope.assign(var("n"), var("m"));
+                        });
+                    });
+                scope.assign(var("steps"), add(var("steps"), lit(1u32)));
+            });
+
+            scope.ret([var("steps")]);
+        });
+
+    let result = unsafe { assert_backends_agree::<fn(u64) -> u32>(&module([function]), "collatz_steps", (6,)) };
+    assert_eq!(result, 8);
+}
@@ -0,0 +1,56 @@
+//! Source spans and the debug info emitted for them.
+
+use dorian::prelude::*;
+use dorian_test::fixtures::module;
+use dorian_test::assert_backends_agree;
+
+/// Clamps its input to at most 100, with spans in two files.
+fn clamp() -> Module<'static> {
+    use val::*;
+
+    let function = Function::new("clamp")
+        .with_span(Span::new("src/clamp.dn", 1, 1))
+        .add_input(ty::s32())
+        .add_output(ty::s32())
+        .build_block(|scope| {
+            scope.spanned(Span::new("src/clamp.dn", 2, 5), |scope| {
+                scope.bind("limit", spanned(Span::new("src/clamp.dn", 2, 17), lit(100i32)));
+            });
+            scope.spanned(Span::new("src/clamp.dn", 3, 5), |scope| {
+                scope.if_then(gt(arg(0), var("limit")), |scope| {
+                    scope.ret([var("limit")]);
+                });
+            });
+            scope.spanned(Span::new("src/generated.dn", 1, 1), |scope| {
+                scope.ret([arg(0)]);
+            });
+        });
+
+    module([function])
+}
+
+#[test]
+fn spans_do_not_change_results() {
+    let ast_module = clamp();
+    assert_eq!(unsafe { assert_backends_agree::<fn(i32) -> i32>(&ast_module, "clamp", (7,)) }, 7);
+    assert_eq!(unsafe { assert_backends_agree::<fn(i32) -> i32>(&ast_module, "clamp", (250,)) }, 100);
+}
+
+#[cfg(feature = "llvm")]
+#[test]
+fn llvm_emits_dwarf_for_spans() {
+    let mut llvm = Llvm::new();
+    let ir = llvm.compile_module(&clamp()).unwrap().print_to_string().to_string();
+
+    let subprogram = ir
+        .lines()
+        .find(|x| x.contains("!DISubprogram(name: \"clamp\""))
+        .unwrap_or_else(|| panic!("Expected a subprogram for clamp in:\n{}", ir));
+    assert!(subprogram.contains("line: 1,") && subprogram.contains("scopeLine: 1,"), "{}", subprogram);
+
+    // The comparison is the first instruction with a span, and the last span is in a block of its own file
+    assert!(ir.contains("!DILocation(line: 3, column: 5, scope:"), "{}", ir);
+    assert!(ir.contains("!DIFile(filename: \"clamp.dn\", directory: \"src\")"), "{}", ir);
+    assert!(ir.contains("!DIFile(filename: \"generated.dn\", directory: \"src\")"), "{}", ir);
+    assert!(ir.contains("!DILexicalBlock("), "{}", ir);
+}
@@ -0,0 +1,84 @@
+//! Constant folding and other rewrites of the AST before it reaches a backend.
+
+use dorian::prelude::*;
+use dorian_test::fixtures::module;
+use dorian_test::assert_backends_agree;
+
+#[test]
+fn folding_preserves_results() {
+    use val::*;
+
+    let function = Function::new("folded")
+        .add_input(ty::s8())
+        .add_output(ty::s8())
+        .build_block(|scope| {
+            scope.if_then(not(lit(true)), |scope| {
+                scope.ret([lit(1i8)]);
+            });
+            // Wraps to -6
+            scope.bind("a", add(lit(125i8), lit(125i8)));
+            scope.bind("b", sub(lit(-128i8), lit(0i8)));
+            scope.bind("c", shr(lit(-64i8), lit(3i8)));
+            scope.bind("d", mul(bit_xor(arg(0), arg(0)), lit(7i8)));
+            scope.bind("e", add(mul(arg(0), lit(1i8)), lit(0i8)));
+            scope.if_then(lt(lit(-1i8), lit(1i8)), |scope| {
+                scope.ret([add(add(var("a"), var("b")), add(add(var("c"), var("d")), var("e")))]);
+            });
+            scope.ret([lit(0i8)]);
+        });
+
+    let original = module([function]);
+    let mut optimized = original.clone();
+    opt::optimize_module(&mut optimized);
+
+    let Stmt::Bind(bind) = &optimized.functions[0].body.stmts[1] else { unreachable!() };
+    assert_eq!(bind.value, lit(-6i8));
+    let Stmt::Bind(bind) = &optimized.functions[0].body.stmts[4] else { unreachable!() };
+    assert_eq!(bind.value, lit(0i8));
+    let Stmt::Bind(bind) = &optimized.functions[0].body.stmts[5] else { unreachable!() };
+    assert_eq!(bind.value, arg(0));
+
+    let expected = unsafe { assert_backends_agree::<fn(i8) -> i8>(&original, "folded", (3,)) };
+    let result = unsafe { assert_backends_agree::<fn(i8) -> i8>(&optimized, "folded", (3,)) };
+    assert_eq!(result, expected);
+    assert_eq!(result, (-6i8).wrapping_add(-128).wrapping_add(-8).wrapping_add(3));
+}
+
+#[test]
+fn folded_modules_keep_their_results() {
+    use val::*;
+
+    struct Rename;
+
+    impl<'s> Fold<'s> for Rename {
+        fn fold_function(&mut self, function: Function<'s>) -> Function<'s> {
+            let function = dorian::ast::fold::walk_function(self, function);
+            Function { name: format!("renamed_{}", function.name).into(), ..function }
+        }
+
+        fn fold_call(&mut self, call: Call<'s>) -> Call<'s> {
+            let call = dorian::ast::fold::walk_call(self, call);
+            Call { function_name: format!("renamed_{}", call.function_name).into(), ..call }
+        }
+    }
+
+    let square = Function::new("square")
+        .add_input(ty::u64())
+        .add_output(ty::u64())
+        .build_block(|scope| {
+            scope.ret([mul(arg(0), arg(0))]);
+        });
+    let entry = Function::new("sum_of_squares")
+        .add_input(ty::u64())
+        .add_input(ty::u64())
+        .add_output(ty::u64())
+        .build_block(|scope| {
+            scope.ret([add(call("square", vec![arg(0)]), call("square", vec![arg(1)]))]);
+        });
+
+    let ast_module = module([entry, square]);
+    let ast_module = Rename.fold_module(ast_module);
+
+    let result = unsafe { assert_backends_agree::<fn(u64, u64) -> u64>(&ast_module, "renamed_sum_of_squares", (3, 4)) };
+    assert_eq!(result, 25);
+}
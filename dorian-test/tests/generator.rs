@@ -0,0 +1,79 @@
+//! Modules from the fuzzing generator, run from fixed seeds.
+
+use dorian::prelude::*;
+use std::fmt::Debug;
+use dorian::jit::JitFn;
+use dorian_test::run_all;
+
+/// Expands `seed` into input for the module generator with xorshift, so that every seed gives fixed but varied bytes.
+fn generator_input(seed: u64) -> Vec<u8> {
+    let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+    (0..4096)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+/// Runs `entry` on every backend and panics unless they all succeed with results that are the `same`.
+fn assert_generated_runs_agree<T: Debug>(ast_module: &Module, entry: &str, same: impl Fn(&T, &T) -> bool)
+where
+    fn() -> T: JitFn<Args = (), Output = T>,
+{
+    // SAFETY: generated modules divide only by nonzero literals and shift by less than the width, and access no memory
+    let runs = unsafe { run_all::<fn() -> T>(ast_module, entry, ()) };
+    let agree = runs.windows(2).all(|x| match (&x[0].result, &x[1].result) {
+        (Ok(a), Ok(b)) => same(a, b),
+        _ => false,
+    });
+    assert!(agree && runs.iter().all(|x| x.result.is_ok()), "{} in {:#?}: {:?}", entry, ast_module, runs);
+}
+
+#[test]
+fn generated_modules_compile_and_agree() {
+    use arbitrary::{Arbitrary, Unstructured};
+    use dorian_fuzz::ArbitraryModule;
+
+    fn jit_generated(mut backend: impl Jit, ast_module: &Module) {
+        if let Err(error) = backend.jit(ast_module) {
+            panic!("Failed to compile a generated module: {}\n{:#?}", error, ast_module);
+        }
+    }
+
+    for seed in 0..64 {
+        let input = generator_input(seed);
+        let ArbitraryModule(ast_module) = ArbitraryModule::arbitrary(&mut Unstructured::new(&input)).unwrap();
+
+        #[cfg(feature = "llvm")]
+        jit_generated(Llvm::new(), &ast_module);
+        #[cfg(feature = "cranelift")]
+        jit_generated(Cranelift::new(), &ast_module);
+
+        // Functions without parameters are run as they are. The backends may produce different NaNs, so floats only
+        // need to agree on whether they are NaN
+        let entries = ast_module.functions.iter().filter(|x| x.signature.input.is_empty());
+        for function in entries {
+            let entry = function.name.as_ref();
+            let float_eq = |a: f64, b: f64| a == b || a.is_nan() && b.is_nan();
+            match function.signature.output[0] {
+                x if x == ty::s8() => assert_generated_runs_agree::<i8>(&ast_module, entry, i8::eq),
+                x if x == ty::s16() => assert_generated_runs_agree::<i16>(&ast_module, entry, i16::eq),
+                x if x == ty::s32() => assert_generated_runs_agree::<i32>(&ast_module, entry, i32::eq),
+                x if x == ty::s64() => assert_generated_runs_agree::<i64>(&ast_module, entry, i64::eq),
+                x if x == ty::u8() => assert_generated_runs_agree::<u8>(&ast_module, entry, u8::eq),
+                x if x == ty::u16() => assert_generated_runs_agree::<u16>(&ast_module, entry, u16::eq),
+                x if x == ty::u32() => assert_generated_runs_agree::<u32>(&ast_module, entry, u32::eq),
+                x if x == ty::u64() => assert_generated_runs_agree::<u64>(&ast_module, entry, u64::eq),
+                x if x == ty::f32() => {
+                    assert_generated_runs_agree::<f32>(&ast_module, entry, |a, b| float_eq(*a as f64, *b as f64));
+                }
+                x if x == ty::f64() => assert_generated_runs_agree::<f64>(&ast_module, entry, |a, b| float_eq(*a, *b)),
+                x if x == ty::bool() => assert_generated_runs_agree::<bool>(&ast_module, entry, bool::eq),
+                _ => unreachable!("Only scalar number and bool types are generated"),
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Modules compiled together, and backends that compile more than once.
+
+use dorian::prelude::*;
+use dorian_test::fixtures::module;
+use dorian_test::{assert_backends_reject, run_all_linked};
+
+#[test]
+fn linked_modules_call_each_other_by_name() {
+    use val::*;
+
+    let twice = |factor: u32| {
+        Function::new("twice")
+            .with_linkage(Linkage::Internal)
+            .add_input(ty::u32())
+            .add_output(ty::u32())
+            .build_block(|scope| {
+                scope.ret([mul(arg(0), lit(factor))]);
+            })
+    };
+    let offset = |linkage: Linkage, offset: u32| {
+        Function::new("offset")
+            .with_linkage(linkage)
+            .add_output(ty::u32())
+            .build_block(|scope| {
+                scope.ret([lit(offset)]);
+            })
+    };
+
+    let mut helpers = Module::new("helpers");
+    helpers.add_function(Function::new("square")
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.ret([add(mul(arg(0), arg(0)), call("twice", vec![arg(0)]))]);
+        }));
+    helpers.add_function(twice(2));
+    helpers.add_function(offset(Linkage::Weak, 1));
+
+    // Internal functions of different modules may share a name, and a strong definition overrides a weak one
+    let mut main = Module::new("main");
+    main.add_function(Function::new("entry")
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            let square = call("square", vec![arg(0)]);
+            scope.ret([add(add(square, call("twice", vec![arg(0)])), call("offset", vec![]))]);
+        }));
+    main.add_function(Function::new("square")
+        .with_linkage(Linkage::Import)
+        .add_input(ty::u32())
+        .add_output(ty::u32()));
+    main.add_function(twice(3));
+    main.add_function(offset(Linkage::Export, 10));
+
+    for run in unsafe { run_all_linked::<fn(u32) -> u32>(&[helpers, main], "entry", (5,)) } {
+        assert_eq!(run.result, Ok(25 + 10 + 15 + 10), "{}", run.backend);
+    }
+}
+
+#[test]
+fn link_errors_are_reported() {
+    use val::*;
+
+    let answer = |linkage: Linkage, output: Type| {
+        Function::new("answer")
+            .with_linkage(linkage)
+            .add_output(output)
+            .build_block(|scope| {
+                scope.ret([lit(42)]);
+            })
+    };
+
+    let mut first = Module::new("first");
+    first.add_function(answer(Linkage::Export, ty::u32()));
+    let mut second = Module::new("second");
+    second.add_function(answer(Linkage::Export, ty::u32()));
+    let mut third = Module::new("third");
+    third.add_function(answer(Linkage::Import, ty::u64()));
+
+    let modules = [first, second, third];
+    let errors = SymbolTable::resolve_linked(&modules).unwrap_err();
+    assert_eq!(errors, vec![
+        ResolveError::DuplicateSymbol("answer".to_string()),
+        ResolveError::IncompatibleDeclarations("answer".to_string()),
+    ]);
+
+    assert_backends_reject(&modules, "Failed to resolve module");
+}
+
+#[test]
+fn backends_jit_the_same_names_again() {
+    use val::*;
+
+    let answer = |answer: u32| {
+        module([Function::new("answer")
+            .add_output(ty::u32())
+            .build_block(|scope| {
+                scope.ret([lit(answer)]);
+            })])
+    };
+
+    fn jit_twice(mut backend: impl Jit, first: &Module, second: &Module) {
+        for (ast_module, expected) in [(first, 1), (second, 2)] {
+            let jit_module = backend.jit(ast_module).unwrap();
+            // SAFETY: the function only returns a literal
+            assert_eq!(unsafe { jit_module.get::<fn() -> u32>("answer").unwrap().call() }, expected);
+        }
+    }
+
+    #[cfg(feature = "llvm")]
+    jit_twice(Llvm::new(), &answer(1), &answer(2));
+    #[cfg(feature = "cranelift")]
+    jit_twice(Cranelift::new(), &answer(1), &answer(2));
+}
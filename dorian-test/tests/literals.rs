@@ -0,0 +1,49 @@
+//! Untyped literals, whose types are inferred from where they are used.
+
+use dorian::prelude::*;
+use dorian_test::fixtures::module;
+use dorian_test::{assert_backends_agree, assert_backends_reject};
+
+#[test]
+fn untyped_literals_take_their_context_type() {
+    use val::*;
+
+    let halve = Function::new("halve")
+        .add_input(ty::u8())
+        .add_output(ty::u8())
+        .build_block(|scope| {
+            scope.ret([div(arg(0), lit_int(2))]);
+        });
+    let entry = Function::new("entry")
+        .add_input(ty::u8())
+        .add_output(ty::u8())
+        .build_block(|scope| {
+            scope.bind("x", lit_int(200));
+            scope.if_then(gt(arg(0), lit_int(100)), |scope| {
+                scope.assign(var("x"), call("halve", vec![lit_int(250)]));
+            });
+            scope.ret([add(var("x"), lit_int(1))]);
+        });
+
+    let ast_module = module([entry, halve]);
+
+    assert_eq!(unsafe { assert_backends_agree::<fn(u8) -> u8>(&ast_module, "entry", (3,)) }, 201);
+    assert_eq!(unsafe { assert_backends_agree::<fn(u8) -> u8>(&ast_module, "entry", (150,)) }, 126);
+
+    let entry = Function::new("entry")
+        .add_input(ty::u8())
+        .add_output(ty::u8())
+        .build_block(|scope| {
+            scope.bind("unused", lit_int(1));
+            scope.ret([add(arg(0), lit_int(300))]);
+        });
+    let ast_module = module([entry]);
+
+    let errors = infer::type_literals(&ast_module).unwrap_err();
+    assert_eq!(errors, vec![
+        ResolveError::UntypedLiteral { function: Some("entry".to_string()), lit: lit_int(1), ty: None },
+        ResolveError::UntypedLiteral { function: Some("entry".to_string()), lit: lit_int(300), ty: Some(ty::u8()) },
+    ]);
+
+    assert_backends_reject(&[ast_module], "Failed to resolve module");
+}
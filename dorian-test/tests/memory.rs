@@ -0,0 +1,70 @@
+//! Atomics and bulk memory operations.
+
+use dorian::prelude::*;
+use dorian_test::fixtures::module;
+use dorian_test::assert_backends_agree;
+
+#[test]
+fn atomics_read_modify_and_write_memory() {
+    use val::*;
+
+    let entry = Function::new("entry")
+        .add_input(ty::ptr())
+        .add_output(ty::u64())
+        .build_block(|scope| {
+            scope.atomic_store(arg(0), lit(5u64), AtomicOrdering::Release);
+            scope.bind("added", atomic_add(arg(0), lit(3u64), AtomicOrdering::AcqRel));
+            scope.bind("maxed", atomic_max(arg(0), lit(2u64), AtomicOrdering::SeqCst));
+            scope.bind("swapped", cmpxchg(arg(0), lit(8u64), lit(20u64), AtomicOrdering::SeqCst));
+            scope.bind("failed", cmpxchg(arg(0), lit(8u64), lit(30u64), AtomicOrdering::Acquire));
+            scope.fence(AtomicOrdering::SeqCst);
+            scope.bind("exchanged", atomic_xchg(arg(0), lit(7u64), AtomicOrdering::Relaxed));
+            scope.bind("loaded", atomic_load(arg(0), ty::u64(), AtomicOrdering::Acquire));
+
+            // Each result is below 100, so they are returned as the base 100 digits of one number
+            let names = ["loaded", "exchanged", "failed", "swapped", "maxed", "added"];
+            scope.ret([names.iter().fold(lit_int(0), |sum, name| add(mul(sum, lit_int(100)), var(*name)))]);
+        });
+    let ast_module = module([entry]);
+
+    // Every run stores first, so the backends see the same memory
+    let mut memory = 0u64;
+    // SAFETY: the pointer is valid and aligned for the 8-byte atomics
+    let args = (&mut memory as *mut u64,);
+    let result = unsafe { assert_backends_agree::<fn(*mut u64) -> u64>(&ast_module, "entry", args) };
+    assert_eq!(result, [7, 20, 20, 8, 8, 5].into_iter().fold(0, |sum, x| sum * 100 + x));
+    assert_eq!(memory, 7);
+}
+
+#[test]
+fn memory_operations_copy_move_and_set_bytes() {
+    use val::*;
+
+    let entry = Function::new("entry")
+        .add_input(ty::ptr())
+        .add_input(ty::ptr())
+        .add_input(ty::ptr())
+        .add_input(ty::u64())
+        .build_block(|scope| {
+            // Literal lengths are inlined by Cranelift, while the others call into libc
+            scope.memset(arg(0), lit(0xAAu8), lit(32u64), 8);
+            scope.memcpy(arg(0), arg(2), lit(16u64), 8);
+            scope.memmove(arg(1), arg(0), arg(3), 1);
+            scope.memset(arg(0), lit(0x55u8), sub(arg(3), lit(6u64)), 1);
+            scope.ret([]);
+        });
+    let ast_module = module([entry]);
+
+    // Every run sets the whole destination first, so the backends see the same memory
+    let mut dst = [0u64; 4];
+    let src: [u8; 16] = std::array::from_fn(|i| i as u8);
+    let dst_ptr = dst.as_mut_ptr() as *mut u8;
+    let args = (dst_ptr, dst_ptr.wrapping_add(4), src.as_ptr(), 8);
+    // SAFETY: every access stays within the 32 bytes of `dst` and the 16 bytes of `src`
+    unsafe { assert_backends_agree::<fn(*mut u8, *mut u8, *const u8, u64)>(&ast_module, "entry", args) };
+
+    let bytes: Vec<u8> = dst.iter().flat_map(|x| x.to_ne_bytes()).collect();
+    let mut expected = vec![0x55, 0x55, 2, 3, 0, 1, 2, 3, 4, 5, 6, 7, 12, 13, 14, 15];
+    expected.extend([0xAA; 16]);
+    assert_eq!(bytes, expected);
+}
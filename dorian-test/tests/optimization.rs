@@ -0,0 +1,37 @@
+//! The optimization pipelines of the LLVM backend.
+
+use dorian::prelude::*;
+use dorian_test::fixtures::iterative_fib;
+use dorian_test::Backends;
+
+#[cfg(feature = "llvm")]
+#[test]
+fn optimization_pipelines_preserve_results() {
+    let optimizations = [
+        Optimization::None,
+        Optimization::O1,
+        Optimization::O2,
+        Optimization::O3,
+        Optimization::Os,
+        Optimization::Oz,
+        Optimization::Custom("mem2reg,instcombine,simplifycfg".into()),
+    ];
+
+    for optimization in optimizations {
+        let backends = Backends::new().with_optimization(optimization);
+        let result = unsafe { backends.assert_agree::<fn(u32) -> u32>(&iterative_fib(), "iterative_fib", (20,)) };
+        assert_eq!(result, 6765);
+    }
+}
+
+#[cfg(feature = "llvm")]
+#[test]
+fn invalid_optimization_pipelines_are_reported() {
+    let mut llvm = Llvm::new().with_optimization(Optimization::Custom("no-such-pass".into()));
+    let error = llvm.jit(&iterative_fib()).err().expect("Expected the pipeline to be rejected");
+
+    match error {
+        JitError::Compile(CompileError::Optimization { pipeline, .. }) => assert_eq!(pipeline, "no-such-pass"),
+        _ => panic!("Expected an optimization error, got {:?}", error),
+    }
+}
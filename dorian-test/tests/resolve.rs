@@ -0,0 +1,84 @@
+//! Modules that fail to resolve.
+
+use dorian::prelude::*;
+use dorian_test::fixtures::module;
+use dorian_test::assert_backends_reject;
+
+#[test]
+fn resolution_errors_are_reported() {
+    use val::*;
+
+    let entry = Function::new("entry")
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.if_then(lit(true), |scope| {
+                scope.bind("x", arg(0));
+            });
+            scope.ret([add(var("x"), call("missing", vec![]))]);
+        });
+
+    let ast_module = module([entry.clone(), entry]);
+
+    let errors = SymbolTable::resolve(&ast_module).unwrap_err();
+    assert_eq!(errors, vec![
+        ResolveError::DuplicateSymbol("entry".to_string()),
+        ResolveError::UndefinedVariable { function: "entry".to_string(), name: "x".to_string() },
+        ResolveError::UndefinedFunction { function: "entry".to_string(), name: "missing".to_string() },
+        ResolveError::UndefinedVariable { function: "entry".to_string(), name: "x".to_string() },
+        ResolveError::UndefinedFunction { function: "entry".to_string(), name: "missing".to_string() },
+    ]);
+
+    // The backends report the same errors when they compile the module
+    #[cfg(feature = "llvm")]
+    assert_eq!(Llvm::new().compile_module(&ast_module).err(), Some(CompileError::Resolve(errors.clone())));
+    #[cfg(feature = "cranelift")]
+    assert_eq!(Cranelift::new().compile_module(&ast_module).err(), Some(CompileError::Resolve(errors.clone())));
+
+    assert_backends_reject(&[ast_module], "Failed to resolve module");
+}
+
+#[test]
+fn argument_errors_are_reported() {
+    use val::*;
+
+    let printf = Function::new("printf")
+        .with_linkage(Linkage::Import)
+        .with_variadic(true)
+        .add_input(ty::ptr())
+        .add_output(ty::s32());
+    let signature = Signature::default()
+        .add_input(ty::u32())
+        .add_output(ty::u32());
+    let entry = Function::new("entry")
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.bind("printed", call("printf", vec![]));
+            scope.bind("indirect", call_indirect(func_addr("entry"), signature, vec![arg(0), arg(0)]));
+            scope.ret([call("entry", vec![arg(1)])]);
+        });
+
+    let ast_module = module([entry, printf]);
+
+    let errors = SymbolTable::resolve(&ast_module).unwrap_err();
+    assert_eq!(errors, vec![
+        ResolveError::ArgumentCount {
+            function: "entry".to_string(),
+            callee: Some("printf".to_string()),
+            expected: 1,
+            variadic: true,
+            found: 0,
+        },
+        ResolveError::ArgumentCount {
+            function: "entry".to_string(),
+            callee: None,
+            expected: 1,
+            variadic: false,
+            found: 2,
+        },
+        ResolveError::UndefinedArg { function: "entry".to_string(), param_index: 1 },
+    ]);
+
+    assert_backends_reject(&[ast_module], "Failed to resolve module");
+}
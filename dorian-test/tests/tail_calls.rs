@@ -0,0 +1,70 @@
+//! Guaranteed tail calls.
+
+use dorian::prelude::*;
+use dorian_test::fixtures::module;
+use dorian_test::{assert_backends_agree, assert_backends_reject};
+
+#[test]
+fn tail_calls_run_in_constant_stack_space() {
+    use val::*;
+
+    let sum_to = Function::new("sum_to")
+        .with_call_conv(CallConv::Tail)
+        .add_input(ty::u64())
+        .add_input(ty::u64())
+        .add_output(ty::u64())
+        .build_block(|scope| {
+            scope.if_then(eq(arg(0), lit(0u64)), |scope| {
+                scope.ret([arg(1)]);
+            });
+            scope.tail_call("sum_to", vec![sub(arg(0), lit(1u64)), add(arg(1), arg(0))]);
+        });
+    let entry = Function::new("entry")
+        .add_input(ty::u64())
+        .add_output(ty::u64())
+        .build_block(|scope| {
+            scope.ret([call("sum_to", vec![arg(0), lit(0u64)])]);
+        });
+
+    let ast_module = module([entry, sum_to]);
+
+    // Far deeper than the stack could hold if every call had a frame
+    let n = 10_000_000u64;
+    let result = unsafe { assert_backends_agree::<fn(u64) -> u64>(&ast_module, "entry", (n,)) };
+    assert_eq!(result, n * (n + 1) / 2);
+}
+
+#[test]
+fn invalid_tail_calls_are_reported() {
+    use val::*;
+
+    let callee = Function::new("callee")
+        .with_call_conv(CallConv::Tail)
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.ret([lit(1u32)]);
+        });
+    let signature = Signature::default()
+        .add_output(ty::u64())
+        .with_call_conv(CallConv::Tail);
+    let entry = Function::new("entry")
+        .add_input(ty::bool())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.if_then(arg(0), |scope| {
+                scope.tail_call("callee", vec![]);
+            });
+            scope.tail_call_indirect(func_addr("callee"), signature, vec![]);
+        });
+
+    let ast_module = module([entry, callee]);
+
+    // The caller uses the C convention, and the indirect callee also has other outputs
+    let errors = SymbolTable::resolve(&ast_module).unwrap_err();
+    assert_eq!(errors, vec![
+        ResolveError::InvalidTailCall { function: "entry".to_string(), callee: Some("callee".to_string()) },
+        ResolveError::InvalidTailCall { function: "entry".to_string(), callee: None },
+    ]);
+
+    assert_backends_reject(&[ast_module], "Failed to resolve module");
+}
@@ -0,0 +1,99 @@
+//! Traps, which are reported as errors instead of killing the process.
+
+use dorian::jit::JitFunction;
+use dorian::prelude::*;
+use dorian_test::fixtures::module;
+use dorian_test::{assert_backends_agree, run_all};
+
+// Elsewhere, traps abort the process
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+#[test]
+fn traps_are_reported_as_errors() {
+    use val::*;
+
+    let entry = Function::new("entry")
+        .add_input(ty::u32())
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.assert(ne(arg(1), lit_int(0)), 7);
+            scope.if_then(eq(arg(0), lit_int(13)), |scope| {
+                scope.trap(13);
+            });
+            scope.if_then(eq(arg(0), lit_int(99)), |scope| {
+                scope.ret([trapping_add(arg(0), lit(u32::MAX))]);
+            });
+            scope.if_then(ne(arg(1), lit_int(1000)), |scope| {
+                scope.ret([div(arg(0), arg(1))]);
+            }).or_else(|scope| {
+                scope.unreachable();
+            });
+        });
+    let ast_module = module([entry]);
+
+    assert_eq!(unsafe { assert_backends_agree::<fn(u32, u32) -> u32>(&ast_module, "entry", (10, 2)) }, 5);
+
+    let cases = [
+        ((10, 0), "Function trapped with code 7"),
+        ((13, 1), "Function trapped with code 13"),
+        ((99, 1), "Function trapped with integer overflow"),
+        ((42, 1000), "Function trapped with unreachable code"),
+    ];
+    for (args, expected) in cases {
+        for run in unsafe { run_all::<fn(u32, u32) -> u32>(&ast_module, "entry", args) } {
+            assert_eq!(run.result, Err(expected.to_string()), "{}: {:?}", run.backend, args);
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+#[test]
+fn traps_only_skip_compiled_code() {
+    use val::*;
+
+    /// Calls compiled code that traps for zero from a function that compiled code calls, and carries on either way.
+    unsafe extern "C" fn check(function: *const u8, x: u32) -> u32 {
+        // SAFETY: the function only checks its argument
+        unsafe { (*(function as *const JitFunction<fn(u32) -> u32>)).try_call(x) }.unwrap_or(100)
+    }
+
+    let check_signature = Signature::default().add_input(ty::ptr()).add_input(ty::u32()).add_output(ty::u32());
+    let checked = Function::new("checked")
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.assert(ne(arg(0), lit(0u32)), 2);
+            scope.ret([arg(0)]);
+        });
+    let entry = Function::new("entry")
+        .add_input(ty::ptr())
+        .add_input(ty::ptr())
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.bind("checked", call_indirect(arg(0), check_signature, vec![arg(1), arg(2)]));
+            scope.assert(ne(arg(2), lit(7u32)), 1);
+            scope.ret([add(var("checked"), lit(1u32))]);
+        });
+    let ast_module = module([checked, entry]);
+
+    fn run(mut backend: impl Jit, ast_module: &Module) {
+        let jit_module = backend.jit(ast_module).unwrap();
+        let checked = jit_module.get::<fn(u32) -> u32>("checked").unwrap();
+        let entry = jit_module.get::<fn(*const u8, *const u8, u32) -> u32>("entry").unwrap();
+        let callback = check as unsafe extern "C" fn(_, _) -> _ as *const u8;
+        let checked = &checked as *const JitFunction<_> as *const u8;
+
+        let cases = [(5, Ok(6)), (0, Ok(101)), (7, Err("Function trapped with code 1".to_string()))];
+        for (x, expected) in cases {
+            // SAFETY: `callback` is called with the signature it was cast from, and `checked` outlives the call
+            let result = unsafe { entry.try_call(callback, checked, x) };
+            assert_eq!(result.map_err(|e| e.to_string()), expected, "{:?}", x);
+        }
+    }
+
+    #[cfg(feature = "llvm")]
+    run(Llvm::new(), &ast_module);
+    #[cfg(feature = "cranelift")]
+    run(Cranelift::new(), &ast_module);
+}
@@ -0,0 +1,332 @@
+//! Try statements, and panics that unwind through compiled code.
+
+use dorian::prelude::*;
+use dorian_test::fixtures::module;
+use dorian_test::{Run, run_all};
+use std::fmt::Debug;
+
+#[test]
+fn try_statements_run_their_body_when_nothing_unwinds() {
+    use val::*;
+
+    extern "C" fn double(x: u32) -> u32 {
+        x * 2
+    }
+
+    let signature = Signature::default().add_input(ty::u32()).add_output(ty::u32());
+    let personality = Function::new("__gcc_personality_v0").with_linkage(Linkage::Import);
+    let entry = Function::new("entry")
+        .with_personality("__gcc_personality_v0")
+        .add_input(ty::ptr())
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.bind("result", lit(0u32));
+            scope.try_catch(|scope| {
+                scope.try_cleanup(|scope| {
+                    scope.assign(var("result"), call_indirect(arg(0), signature, vec![arg(1)]));
+                }, |scope| {
+                    scope.assign(var("result"), lit(1u32));
+                });
+            }, "exception", |scope| {
+                scope.assign(var("result"), add(var("result"), lit(1000u32)));
+            });
+            scope.ret([var("result")]);
+        });
+
+    let ast_module = module([entry, personality]);
+
+    let args = (double as extern "C" fn(u32) -> u32 as *const u8, 21u32);
+    // SAFETY: the pointer is only called, as a function with the signature it was cast from
+    assert_unwinding_runs(unsafe { run_all::<fn(*const u8, u32) -> u32>(&ast_module, "entry", args) }, Ok(42));
+}
+
+/// Checks that every backend returns `expected`, except for Cranelift, which does not support try statements because
+/// nothing can unwind through the code that it compiles.
+fn assert_unwinding_runs<T: PartialEq + Debug>(runs: Vec<Run<T>>, expected: Result<T, String>) {
+    for run in runs {
+        match run.backend {
+            "cranelift" => assert_eq!(run.result, Err("Cranelift does not support try statements".to_string())),
+            _ => assert_eq!(run.result, expected, "{}", run.backend),
+        }
+    }
+}
+
+/// The entry points of the unwinding tests, which take the addresses of two callees and a value to check.
+#[cfg(target_os = "linux")]
+type UnwindEntry = fn(*const u8, *const u8, u32) -> u32;
+
+/// Panics with `x` as the payload unless it is small, unwinding into the compiled code that called it.
+#[cfg(target_os = "linux")]
+extern "C-unwind" fn check_small(x: u32) -> u32 {
+    if x > 100 {
+        std::panic::panic_any(x);
+    }
+    x
+}
+
+/// Finishes a panic caught by compiled code and returns its payload. Only Rust can finish its own panics, so the
+/// exception is raised again inside `catch_unwind`.
+#[cfg(target_os = "linux")]
+extern "C" fn finish_panic(exception: *mut u8) -> u32 {
+    unsafe extern "C-unwind" {
+        fn _Unwind_RaiseException(exception: *mut u8) -> i32;
+    }
+
+    // SAFETY: the exception was caught by a catch handler, which has not finished it yet
+    let payload = std::panic::catch_unwind(|| unsafe { _Unwind_RaiseException(exception) }).unwrap_err();
+    *payload.downcast::<u32>().expect("Panic payload should be a u32")
+}
+
+/// Throws `x` as a C++ `int` unless it is small, unwinding into the compiled code that called it.
+#[cfg(target_os = "linux")]
+extern "C-unwind" fn throw_large(x: u32) -> u32 {
+    type Allocate = unsafe extern "C" fn(usize) -> *mut u8;
+    type Throw = unsafe extern "C-unwind" fn(*mut u8, *const u8, *const u8) -> !;
+
+    if x > 100 {
+        // SAFETY: the symbols are those of the C++ runtime, which `load_cxx_runtime` loaded, with their C++ signatures
+        unsafe {
+            let allocate = std::mem::transmute::<*const u8, Allocate>(cxx_symbol(c"__cxa_allocate_exception"));
+            let throw = std::mem::transmute::<*const u8, Throw>(cxx_symbol(c"__cxa_throw"));
+            let exception = allocate(size_of::<i32>());
+            exception.cast::<i32>().write(x as i32);
+            throw(exception, cxx_symbol(c"_ZTIi"), std::ptr::null());
+        }
+    }
+    x
+}
+
+/// Finds a symbol of the C++ runtime, which the Rust runtime does not link against.
+#[cfg(target_os = "linux")]
+fn cxx_symbol(name: &std::ffi::CStr) -> *const u8 {
+    unsafe extern "C" {
+        fn dlsym(handle: *mut std::ffi::c_void, symbol: *const std::ffi::c_char) -> *const u8;
+    }
+
+    // SAFETY: a null handle searches the global symbols, and the name is a valid C string
+    let symbol = unsafe { dlsym(std::ptr::null_mut(), name.as_ptr()) };
+    assert!(!symbol.is_null(), "Failed to find {:?}", name);
+    symbol
+}
+
+/// Loads the C++ runtime, whose personality catches foreign exceptions such as Rust panics, into the global symbols
+/// that compiled code is linked against.
+#[cfg(target_os = "linux")]
+fn load_cxx_runtime() {
+    unsafe extern "C" {
+        fn dlopen(filename: *const std::ffi::c_char, flags: std::ffi::c_int) -> *mut std::ffi::c_void;
+    }
+    const RTLD_NOW: std::ffi::c_int = 2;
+    const RTLD_GLOBAL: std::ffi::c_int = 0x100;
+
+    // SAFETY: loading libstdc++ runs no initializers that conflict with the Rust runtime
+    let handle = unsafe { dlopen(c"libstdc++.so.6".as_ptr(), RTLD_NOW | RTLD_GLOBAL) };
+    assert!(!handle.is_null(), "Failed to load libstdc++");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn cleanup_handlers_run_while_panics_propagate() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use val::*;
+
+    let signature = Signature::default().add_input(ty::u32()).add_output(ty::u32());
+    let personality = Function::new("__gcc_personality_v0").with_linkage(Linkage::Import);
+    let entry = Function::new("entry")
+        .with_personality("__gcc_personality_v0")
+        .add_input(ty::ptr())
+        .add_input(ty::ptr())
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.bind("result", lit(0u32));
+            scope.try_cleanup(|scope| {
+                scope.assign(var("result"), call_indirect(arg(0), signature, vec![arg(2)]));
+            }, |scope| {
+                scope.atomic_store(arg(1), lit(1u32), AtomicOrdering::SeqCst);
+            });
+            scope.ret([var("result")]);
+        });
+
+    let ast_module = module([entry, personality]);
+
+    let check = check_small as extern "C-unwind" fn(u32) -> u32 as *const u8;
+    let panicked = Err("panicked: <non-string panic payload>".to_string());
+    for (x, expected, cleaned_up) in [(7, Ok(7), 0), (1000, panicked, 1)] {
+        let cleanups = AtomicU32::new(0);
+        let args = (check, &cleanups as *const AtomicU32 as *const u8, x);
+        // SAFETY: the first pointer is only called, as a function with the signature it was cast from, and the
+        // second one is only stored to, while the atomic it points to is alive
+        let runs = unsafe { run_all::<UnwindEntry>(&ast_module, "entry", args) };
+        let unwound = runs.iter().filter(|x| x.backend != "cranelift").count() as u32;
+        assert_unwinding_runs(runs, expected);
+        assert_eq!(cleanups.load(Ordering::SeqCst), cleaned_up * unwound);
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn catch_handlers_catch_panics() {
+    use val::*;
+
+    load_cxx_runtime();
+
+    let check_signature = Signature::default().add_input(ty::u32()).add_output(ty::u32());
+    let finish_signature = Signature::default().add_input(ty::ptr()).add_output(ty::u32());
+    let personality = Function::new("__gxx_personality_v0").with_linkage(Linkage::Import);
+    let entry = Function::new("entry")
+        .with_personality("__gxx_personality_v0")
+        .add_input(ty::ptr())
+        .add_input(ty::ptr())
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.bind("result", lit(0u32));
+            scope.try_catch(|scope| {
+                scope.assign(var("result"), call_indirect(arg(0), check_signature, vec![arg(2)]));
+            }, "exception", |scope| {
+                let payload = call_indirect(arg(1), finish_signature, vec![var("exception")]);
+                scope.assign(var("result"), add(payload, lit(1u32)));
+            });
+            scope.ret([var("result")]);
+        });
+
+    let ast_module = module([entry, personality]);
+
+    let check = check_small as extern "C-unwind" fn(u32) -> u32 as *const u8;
+    let finish = finish_panic as extern "C" fn(*mut u8) -> u32 as *const u8;
+    for (x, expected) in [(7, 7), (1000, 1001)] {
+        // SAFETY: the pointers are only called, as functions with the signatures they were cast from
+        let runs = unsafe { run_all::<UnwindEntry>(&ast_module, "entry", (check, finish, x)) };
+        assert_unwinding_runs(runs, Ok(expected));
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn nested_try_statements_clean_up_before_catching() {
+    use val::*;
+
+    load_cxx_runtime();
+
+    let check_signature = Signature::default().add_input(ty::u32()).add_output(ty::u32());
+    let finish_signature = Signature::default().add_input(ty::ptr()).add_output(ty::u32());
+    let personality = Function::new("__gxx_personality_v0").with_linkage(Linkage::Import);
+    let entry = Function::new("entry")
+        .with_personality("__gxx_personality_v0")
+        .add_input(ty::ptr())
+        .add_input(ty::ptr())
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.bind("result", lit(0u32));
+            scope.bind("cleanups", lit(0u32));
+            scope.try_catch(|scope| {
+                scope.try_cleanup(|scope| {
+                    scope.try_cleanup(|scope| {
+                        scope.assign(var("result"), call_indirect(arg(0), check_signature, vec![arg(2)]));
+                    }, |scope| {
+                        scope.assign(var("cleanups"), add(var("cleanups"), lit(1u32)));
+                    });
+                }, |scope| {
+                    scope.assign(var("cleanups"), add(var("cleanups"), lit(10u32)));
+                });
+            }, "exception", |scope| {
+                let payload = call_indirect(arg(1), finish_signature, vec![var("exception")]);
+                scope.assign(var("result"), add(payload, var("cleanups")));
+            });
+            scope.ret([var("result")]);
+        });
+
+    let ast_module = module([entry, personality]);
+
+    // Both cleanup handlers run, innermost first, before the catch handler sees the panic
+    let check = check_small as extern "C-unwind" fn(u32) -> u32 as *const u8;
+    let finish = finish_panic as extern "C" fn(*mut u8) -> u32 as *const u8;
+    for (x, expected) in [(7, 7), (1000, 1011)] {
+        // SAFETY: the pointers are only called, as functions with the signatures they were cast from
+        let runs = unsafe { run_all::<UnwindEntry>(&ast_module, "entry", (check, finish, x)) };
+        assert_unwinding_runs(runs, Ok(expected));
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn catch_handlers_finish_cxx_exceptions() {
+    use val::*;
+
+    load_cxx_runtime();
+
+    let throw_signature = Signature::default().add_input(ty::u32()).add_output(ty::u32());
+    let personality = Function::new("__gxx_personality_v0").with_linkage(Linkage::Import);
+    let begin_catch = Function::new("__cxa_begin_catch")
+        .with_linkage(Linkage::Import)
+        .add_input(ty::ptr())
+        .add_output(ty::ptr());
+    let end_catch = Function::new("__cxa_end_catch").with_linkage(Linkage::Import);
+    let entry = Function::new("entry")
+        .with_personality("__gxx_personality_v0")
+        .add_input(ty::ptr())
+        .add_input(ty::u32())
+        .add_output(ty::u32())
+        .build_block(|scope| {
+            scope.bind("result", lit(0u32));
+            scope.try_catch(|scope| {
+                scope.assign(var("result"), call_indirect(arg(0), throw_signature, vec![arg(1)]));
+            }, "exception", |scope| {
+                // The C++ runtime hands out the thrown `int` and destroys it once the catch is finished
+                scope.bind("thrown", call("__cxa_begin_catch", vec![var("exception")]));
+                let thrown = atomic_load(var("thrown"), ty::u32(), AtomicOrdering::Relaxed);
+                scope.assign(var("result"), add(thrown, lit(1u32)));
+                scope.call("__cxa_end_catch", vec![]);
+            });
+            scope.ret([var("result")]);
+        });
+
+    let ast_module = module([entry, personality, begin_catch, end_catch]);
+
+    let throw = throw_large as extern "C-unwind" fn(u32) -> u32 as *const u8;
+    for (x, expected) in [(7, 7), (1000, 1001)] {
+        // SAFETY: the pointer is only called, as a function with the signature it was cast from
+        let runs = unsafe { run_all::<fn(*const u8, u32) -> u32>(&ast_module, "entry", (throw, x)) };
+        assert_unwinding_runs(runs, Ok(expected));
+    }
+}
+
+#[test]
+fn invalid_try_statements_are_reported() {
+    use val::*;
+
+    let callee = Function::new("callee")
+        .with_call_conv(CallConv::Tail)
+        .build_block(|scope| {
+            scope.ret([]);
+        });
+    let entry = Function::new("entry")
+        .with_call_conv(CallConv::Tail)
+        .build_block(|scope| {
+            scope.try_catch(|scope| {
+                scope.tail_call("callee", vec![]);
+            }, "exception", |scope| {
+                scope.bind("copy", var("exception"));
+            });
+            scope.bind("outside", var("exception"));
+            scope.ret([]);
+        });
+    let personal = Function::new("personal")
+        .with_personality("missing")
+        .build_block(|scope| {
+            scope.ret([]);
+        });
+
+    let ast_module = module([entry, callee, personal]);
+
+    let errors = SymbolTable::resolve(&ast_module).unwrap_err();
+    assert_eq!(errors, vec![
+        ResolveError::MissingPersonality { function: "entry".to_string() },
+        ResolveError::InvalidTailCall { function: "entry".to_string(), callee: Some("callee".to_string()) },
+        ResolveError::UndefinedVariable { function: "entry".to_string(), name: "exception".to_string() },
+        ResolveError::UndefinedFunction { function: "personal".to_string(), name: "missing".to_string() },
+    ]);
+}
@@ -0,0 +1,92 @@
+//! Vector types and their lane-wise operations.
+
+use dorian::prelude::*;
+use dorian_test::fixtures::module;
+use dorian_test::{assert_backends_agree, run_all};
+
+#[test]
+fn vectors_compute_lane_wise_and_reduce() {
+    use val::*;
+
+    let ints = Function::new("ints")
+        .add_input(ty::s32())
+        .add_input(ty::s32())
+        .add_output(ty::s32())
+        .build_block(|scope| {
+            scope.bind("v", vector([arg(0), arg(1), lit(3i32), lit(4i32)]));
+            scope.bind("s", splat(arg(1), 4));
+            scope.bind("products", mul(var("v"), var("s")));
+            scope.bind("quotients", div(var("v"), shuffle(var("v"), var("s"), [1, 4, 3, 2])));
+            let amounts = vector([lit(1i32), lit(2i32), lit(3i32), lit(0i32)]);
+            scope.bind("shifted", shl(insert_element(var("v"), lit(1i32), 1), amounts));
+            scope.ret([add(
+                add(reduce_sum(var("products")), mul(reduce_min(var("quotients")), lit_int(100))),
+                add(mul(extract_element(var("shifted"), 2), lit_int(1000)), reduce_max(neg(var("shifted")))),
+            )]);
+        });
+    let floats = Function::new("floats")
+        .add_input(ty::f64())
+        .add_output(ty::f64())
+        .build_block(|scope| {
+            scope.bind("v", vector([arg(0), lit(2.0f64)]));
+            scope.bind("w", div(var("v"), splat(lit(4.0f64), 2)));
+            scope.ret([add(reduce_sum(var("w")), reduce_max(sub(var("v"), var("w"))))]);
+        });
+    let ast_module = module([ints, floats]);
+
+    // [10, -3, 3, 4] * -3 sums to -42, divided by [-3, -3, 4, 3] has a minimum of -3, and shifted is [20, 4, 24, 4]
+    assert_eq!(unsafe { assert_backends_agree::<fn(i32, i32) -> i32>(&ast_module, "ints", (10, -3)) }, 23654);
+    assert_eq!(unsafe { assert_backends_agree::<fn(f64) -> f64>(&ast_module, "floats", (8.0,)) }, 8.5);
+}
+
+#[test]
+fn float_remainders_compute_lane_wise() {
+    use val::*;
+
+    let scalar = Function::new("scalar")
+        .add_input(ty::f32())
+        .add_input(ty::f32())
+        .add_output(ty::f32())
+        .build_block(|scope| {
+            scope.ret([rem(arg(0), arg(1))]);
+        });
+    let lanes = Function::new("lanes")
+        .add_input(ty::f64())
+        .add_output(ty::f64())
+        .build_block(|scope| {
+            scope.bind("v", rem(vector([arg(0), neg(arg(0))]), vector([lit(4.0f64), lit(0.75f64)])));
+            scope.ret([add(mul(extract_element(var("v"), 0), lit(10.0f64)), extract_element(var("v"), 1))]);
+        });
+    let ast_module = module([scalar, lanes]);
+
+    // The remainder has the sign of the dividend, like `%` in Rust and C's `fmod`
+    assert_eq!(unsafe { assert_backends_agree::<fn(f32, f32) -> f32>(&ast_module, "scalar", (-7.5, 2.0)) }, -1.5);
+    assert!(unsafe { assert_backends_agree::<fn(f32, f32) -> f32>(&ast_module, "scalar", (1.0, 0.0)) }.is_nan());
+    assert_eq!(unsafe { assert_backends_agree::<fn(f64) -> f64>(&ast_module, "lanes", (6.5,)) }, 25.0 - 0.5);
+}
+
+#[test]
+fn shuffles_are_not_limited_to_128_bits() {
+    use val::*;
+
+    let entry = Function::new("entry")
+        .add_input(ty::s32())
+        .add_input(ty::s32())
+        .add_output(ty::s32())
+        .build_block(|scope| {
+            scope.bind("v", shuffle(vector([arg(0), arg(1)]), splat(lit(5i32), 2), [3, 0]));
+            scope.ret([sub(extract_element(var("v"), 0), extract_element(var("v"), 1))]);
+        });
+    let ast_module = module([entry]);
+
+    // Cranelift only lowers 128-bit vectors on x86-64 and AArch64, and reports the others as unsupported
+    for run in unsafe { run_all::<fn(i32, i32) -> i32>(&ast_module, "entry", (2, 3)) } {
+        match run.backend {
+            "cranelift" => {
+                let message = run.result.unwrap_err();
+                assert!(message.starts_with("Cranelift cannot compile function 'entry'"), "{}", message);
+            }
+            _ => assert_eq!(run.result, Ok(3), "{}", run.backend),
+        }
+    }
+}
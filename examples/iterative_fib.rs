@@ -9,18 +9,11 @@ fn main() {
     let mut module = Module::new("iterative_fib_example");
     module.add_function(fib_function);
 
-    let mut llvm = Llvm::new();
-    let compiled_module = llvm.compile_module(&module);
-
-    let execution_engine = compiled_module
-        .create_jit_execution_engine(inkwell::OptimizationLevel::None)
-        .unwrap();
-    let fib_fn = unsafe {
-        execution_engine
-            .get_function::<unsafe extern "C" fn(u32) -> u32>("iterative_fib")
-            .unwrap()
-    };
+    let mut llvm = Llvm::new().with_optimization(Optimization::O2);
+    let jit_module = llvm.jit(&module).unwrap();
+    let fib_fn = jit_module.get::<fn(u32) -> u32>("iterative_fib").unwrap();
 
+    // SAFETY: the function only does arithmetic on its argument
     let result = unsafe { fib_fn.call(10) };
 
     assert_eq!(result, 55, "The 10th Fibonacci number should be 55");
@@ -29,7 +22,7 @@ fn main() {
 fn build_fib_body(scope: &mut BlockBuilder) {
     use val::*;
 
-    let condition = le(arg(0), lit(1i32));
+    let condition = le(arg(0), lit_int(1));
     scope
         .if_then(condition, |scope| {
             scope.ret([arg(0)]);
@@ -10,17 +10,10 @@ fn main() {
     module.add_function(fib_function);
     
     let mut llvm = Llvm::new();
-    let compiled_module = llvm.compile_module(&module);
-    
-    let execution_engine = compiled_module
-        .create_jit_execution_engine(inkwell::OptimizationLevel::None)
-        .unwrap();
-    let fib_fn = unsafe {
-        execution_engine
-            .get_function::<unsafe extern "C" fn(u32) -> u32>("recursive_fib")
-            .unwrap()
-    };
+    let jit_module = llvm.jit(&module).unwrap();
+    let fib_fn = jit_module.get::<fn(u32) -> u32>("recursive_fib").unwrap();
 
+    // SAFETY: the function only does arithmetic on its argument
     let result = unsafe { fib_fn.call(10) };
 
     assert_eq!(result, 55, "The 10th Fibonacci number should be 55");
@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use crate::ast::backend::Backend;
+use crate::ast::function::Linkage;
+use crate::ast::module::Module;
+use crate::cranelift::Cranelift;
+use crate::jit::{Jit, JitEntry, JitError, JitModule};
+
+impl Jit for Cranelift {
+    fn jit_modules<'ctx>(&'ctx mut self, ast_modules: &[Module]) -> Result<JitModule<'ctx>, JitError> {
+        let module = self.compile_modules(ast_modules)?;
+
+        let mut functions = HashMap::new();
+        // Internal functions are not visible outside of the module, and may not even exist after optimization, while
+        // imported functions are not defined by it
+        let exported = ast_modules
+            .iter()
+            .flat_map(|x| &x.functions)
+            .filter(|x| matches!(x.linkage, Linkage::Export | Linkage::Weak));
+        for ast_function in exported {
+            let address = module
+                .get_function_address(&ast_function.name)
+                .ok_or_else(|| JitError::UnknownFunction(ast_function.name.to_string()))?;
+
+            functions.insert(ast_function.name.to_string(), JitEntry {
+                signature: ast_function.signature.clone(),
+                address,
+            });
+        }
+
+        Ok(JitModule {
+            functions,
+            code: module.code().iter().cloned().map(Into::into).collect(),
+            trap_code: Box::new(move |address| module.trap_code(address)),
+            #[cfg(feature = "llvm")]
+            _engine: None,
+            _marker: PhantomData,
+        })
+    }
+}
@@ -0,0 +1,151 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::rc::Rc;
+use inkwell::memory_manager::McjitMemoryManager;
+use inkwell::targets::CodeModel;
+use crate::ast::backend::Backend;
+use crate::ast::block::stmt::TrapCode;
+use crate::ast::function::Linkage;
+use crate::ast::module::Module;
+use crate::jit::{Jit, JitEntry, JitError, JitModule};
+use crate::llvm::Llvm;
+
+impl Jit for Llvm {
+    fn jit_modules<'ctx>(&'ctx mut self, ast_modules: &[Module]) -> Result<JitModule<'ctx>, JitError> {
+        let level = self.optimization().codegen_level();
+        let module = self.compile_modules(ast_modules)?;
+        let code = Rc::new(RefCell::new(Vec::new()));
+        let memory_manager = MemoryManager { sections: Vec::new(), code: code.clone() };
+        let engine = module
+            .create_mcjit_execution_engine_with_memory_manager(
+                memory_manager,
+                level,
+                CodeModel::JITDefault,
+                // Frame pointers may be eliminated and instructions are not selected quickly, as by default
+                false,
+                false,
+            )
+            .map_err(|e| JitError::Backend(e.to_string()))?;
+
+        let mut functions = HashMap::new();
+        // Internal functions are not visible outside of the module, and may not even exist after optimization, while
+        // imported functions are not defined by it
+        let exported = ast_modules
+            .iter()
+            .flat_map(|x| &x.functions)
+            .filter(|x| matches!(x.linkage, Linkage::Export | Linkage::Weak));
+        for ast_function in exported {
+            let address = engine
+                .get_function_address(&ast_function.name)
+                .map_err(|e| JitError::Backend(e.to_string()))?;
+
+            functions.insert(ast_function.name.to_string(), JitEntry {
+                signature: ast_function.signature.clone(),
+                address: address as *const u8,
+            });
+        }
+
+        // Looking up a function finalizes the code of the module, so all of it has been loaded by now
+        let code = code.borrow().iter().cloned().map(Into::into).collect();
+
+        Ok(JitModule {
+            functions,
+            code,
+            trap_code: Box::new(decode_trap_code),
+            _engine: Some(engine),
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// Allocates the sections that MCJIT loads compiled code into, keeping track of where the code is.
+struct MemoryManager {
+    sections: Vec<(region::Allocation, region::Protection)>,
+    code: Rc<RefCell<Vec<Range<usize>>>>,
+}
+
+impl MemoryManager {
+    fn allocate(&mut self, size: usize, alignment: u32, protection: region::Protection) -> *mut u8 {
+        // Every section gets its own pages, which are aligned enough for any section
+        assert!(alignment as usize <= region::page::size(), "Unsupported section alignment {}", alignment);
+        let mut allocation = region::alloc(size.max(1), region::Protection::READ_WRITE)
+            .expect("Failed to allocate a section");
+
+        let address = allocation.as_mut_ptr();
+        self.sections.push((allocation, protection));
+        address
+    }
+}
+
+impl McjitMemoryManager for MemoryManager {
+    fn allocate_code_section(&mut self, size: usize, alignment: u32, _: u32, _: &str) -> *mut u8 {
+        let address = self.allocate(size, alignment, region::Protection::READ_EXECUTE);
+        self.code.borrow_mut().push(address as usize..address as usize + size);
+        address
+    }
+
+    fn allocate_data_section(&mut self, size: usize, alignment: u32, _: u32, _: &str, read_only: bool) -> *mut u8 {
+        let protection = match read_only {
+            true => region::Protection::READ,
+            false => region::Protection::READ_WRITE,
+        };
+        self.allocate(size, alignment, protection)
+    }
+
+    fn finalize_memory(&mut self) -> Result<(), String> {
+        for (allocation, protection) in &self.sections {
+            let (address, len) = (allocation.as_ptr::<u8>(), allocation.len());
+            unsafe { region::protect(address, len, *protection) }.map_err(|e| e.to_string())?;
+
+            if *protection == region::Protection::READ_EXECUTE {
+                unsafe { wasmtime_jit_icache_coherence::clear_cache(address.cast(), len) }.map_err(|e| e.to_string())?;
+            }
+        }
+
+        wasmtime_jit_icache_coherence::pipeline_flush_mt().map_err(|e| e.to_string())
+    }
+
+    fn destroy(&mut self) {
+        self.sections.clear();
+    }
+}
+
+impl std::fmt::Debug for MemoryManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryManager").field("code", &self.code).finish_non_exhaustive()
+    }
+}
+
+/// The code of a trap raised by `llvm.ubsantrap`, which is encoded in the trapping instruction itself.
+#[allow(unreachable_code)]
+fn decode_trap_code(address: *const u8) -> Option<TrapCode> {
+    if address.is_null() {
+        return None;
+    }
+
+    // `ud1l code(%eax), %eax`, with the code as an 8-bit displacement if it fits in one and a 32-bit one otherwise
+    #[cfg(target_arch = "x86_64")]
+    {
+        let bytes = unsafe { std::slice::from_raw_parts(address, 5) };
+        return match bytes {
+            [0x67, 0x0f, 0xb9, 0x40, code] => TrapCode::from_raw(*code),
+            [0x67, 0x0f, 0xb9, 0x80, code] => TrapCode::from_raw(*code),
+            _ => None,
+        };
+    }
+
+    // `brk #(0x5500 | code)`
+    #[cfg(target_arch = "aarch64")]
+    {
+        let instruction = unsafe { address.cast::<u32>().read_unaligned() };
+        let immediate = (instruction >> 5) & 0xffff;
+        return match instruction & 0xffe0_001f == 0xd420_0000 && immediate >> 8 == 0x55 {
+            true => TrapCode::from_raw(immediate as u8),
+            false => None,
+        };
+    }
+
+    None
+}
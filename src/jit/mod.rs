@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use crate::ast::backend::CompileError;
+use crate::ast::block::stmt::TrapCode;
+use crate::ast::function::{CallConv, Signature};
+use crate::ast::module::Module;
+use crate::ast::ty::{BoolType, FloatType, IntType, PtrType, Type};
+
+#[cfg(feature = "cranelift")]
+mod cranelift;
+#[cfg(feature = "llvm")]
+mod llvm;
+mod trap;
+
+use trap::CodeRange;
+
+/// A backend that can compile a [`Module`] to machine code for the host and load it into memory.
+pub trait Jit {
+    fn jit<'ctx>(&'ctx mut self, ast_module: &Module) -> Result<JitModule<'ctx>, JitError> {
+        self.jit_modules(std::slice::from_ref(ast_module))
+    }
+
+    /// Compiles and loads modules together, so that a function imported by one of them calls the function with the
+    /// same name in another, see [`SymbolTable::resolve_linked`](crate::ast::resolve::SymbolTable::resolve_linked).
+    ///
+    /// Modules that fail to resolve are reported as [`CompileError::Resolve`] and features that the backend cannot
+    /// compile as [`CompileError::Unsupported`], while the backend panics on modules that are invalid in other ways.
+    fn jit_modules<'ctx>(&'ctx mut self, ast_modules: &[Module]) -> Result<JitModule<'ctx>, JitError>;
+}
+
+/// A module that has been compiled and loaded by a [`Jit`] backend.
+pub struct JitModule<'ctx> {
+    functions: HashMap<String, JitEntry>,
+    // Where the compiled code is, so that only its traps are caught
+    code: Vec<CodeRange>,
+    // Finds the code of the trap raised by the instruction at an address, if the backend can tell
+    trap_code: Box<dyn Fn(*const u8) -> Option<TrapCode> + 'ctx>,
+    #[cfg(feature = "llvm")]
+    _engine: Option<inkwell::execution_engine::ExecutionEngine<'ctx>>,
+    _marker: PhantomData<&'ctx ()>,
+}
+
+struct JitEntry {
+    signature: Signature,
+    address: *const u8,
+}
+
+impl JitModule<'_> {
+    /// Looks up a compiled function, checking that `F` matches the signature it was declared with in the AST.
+    ///
+    /// Functions with [`Linkage::Internal`](crate::ast::function::Linkage::Internal) or
+    /// [`Linkage::Import`](crate::ast::function::Linkage::Import) cannot be looked up.
+    pub fn get<F: JitFn>(&self, name: &str) -> Result<JitFunction<'_, F>, JitError> {
+        let entry = self.functions
+            .get(name)
+            .ok_or_else(|| JitError::UnknownFunction(name.to_string()))?;
+
+        let (input, output) = (F::input(), F::output());
+        if entry.signature.input != input || entry.signature.output != output {
+            return Err(JitError::SignatureMismatch {
+                name: name.to_string(),
+                declared: Box::new(entry.signature.clone()),
+                input,
+                output,
+            });
+        }
+
+        // Functions are always called through an `extern "C"` function pointer
+        if entry.signature.call_conv != CallConv::C {
+            return Err(JitError::UnsupportedCallConv {
+                name: name.to_string(),
+                call_conv: entry.signature.call_conv,
+            });
+        }
+
+        Ok(JitFunction {
+            address: entry.address,
+            code: &self.code,
+            trap_code: self.trap_code.as_ref(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// A compiled function whose signature has been checked against its AST declaration.
+pub struct JitFunction<'m, F> {
+    address: *const u8,
+    code: &'m [CodeRange],
+    trap_code: &'m dyn Fn(*const u8) -> Option<TrapCode>,
+    _marker: PhantomData<(&'m (), F)>,
+}
+
+impl<F: JitFn> JitFunction<'_, F> {
+    /// Calls the function, which aborts the process if it traps. A panic in a function that it calls continues
+    /// unwinding out of this, unless the function catches it.
+    ///
+    /// # Safety
+    ///
+    /// Compiled code is not checked for undefined behavior, so the call must not cause any. Among other things, the
+    /// function must not divide by zero, only access memory that is valid through the pointers it is given or
+    /// computes, and only call functions through addresses with their signature.
+    pub unsafe fn apply(&self, args: F::Args) -> F::Output {
+        trap::forward_traps(|| unsafe { F::invoke(self.address, args).assume_init() })
+    }
+
+    /// Calls the function, returning [`JitError::Trap`] if it traps.
+    ///
+    /// Only traps raised by the code of its module are caught, and only on Linux on x86-64 and AArch64, while elsewhere
+    /// they abort the process as with [`JitFunction::apply`]. The code of the trap is known for traps raised by `Trap`,
+    /// `Unreachable` and `Assert` statements and by trapping checked operations.
+    ///
+    /// # Safety
+    ///
+    /// See [`JitFunction::apply`].
+    pub unsafe fn try_apply(&self, args: F::Args) -> Result<F::Output, JitError> {
+        unsafe { trap::catch_traps::<F>(self.code, self.address, args) }
+            .map_err(|address| JitError::Trap((self.trap_code)(address)))
+    }
+
+    pub fn as_ptr(&self) -> *const u8 {
+        self.address
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JitError {
+    UnknownFunction(String),
+    SignatureMismatch {
+        name: String,
+        declared: Box<Signature>,
+        input: Vec<Type>,
+        output: Vec<Type>,
+    },
+    UnsupportedCallConv {
+        name: String,
+        call_conv: CallConv,
+    },
+    /// The backend failed to compile the modules.
+    Compile(CompileError),
+    Backend(String),
+    /// A compiled function trapped, with the code of the trap if it could be found.
+    Trap(Option<TrapCode>),
+}
+
+impl Display for JitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JitError::UnknownFunction(name) => write!(f, "Function '{}' was not compiled", name),
+            JitError::SignatureMismatch { name, declared, input, output } => write!(
+                f,
+                "Function '{}' was declared as {:?} -> {:?} but requested as {:?} -> {:?}",
+                name, declared.input, declared.output, input, output,
+            ),
+            JitError::UnsupportedCallConv { name, call_conv } => write!(
+                f,
+                "Function '{}' uses the {:?} calling convention, but only C functions can be called",
+                name, call_conv,
+            ),
+            JitError::Compile(error) => write!(f, "{}", error),
+            JitError::Backend(message) => write!(f, "Backend failed to load module: {}", message),
+            JitError::Trap(Some(code)) => write!(f, "Function trapped with {}", code),
+            JitError::Trap(None) => write!(f, "Function trapped"),
+        }
+    }
+}
+
+impl Error for JitError {}
+
+impl From<CompileError> for JitError {
+    fn from(error: CompileError) -> Self {
+        JitError::Compile(error)
+    }
+}
+
+/// A Rust type that is passed to and returned from compiled functions exactly like the AST type it maps to.
+///
+/// # Safety
+///
+/// The implementing type must have the same C ABI as the type returned by `ty` in every backend.
+pub unsafe trait JitType: Copy {
+    fn ty() -> Type;
+}
+
+macro_rules! jit_type {
+    ($($ty:ty => $value:expr),+ $(,)?) => {
+        $(
+        unsafe impl JitType for $ty {
+            fn ty() -> Type {
+                $value.into()
+            }
+        }
+        )+
+    };
+}
+
+jit_type! {
+    i8 => IntType::S8,
+    i16 => IntType::S16,
+    i32 => IntType::S32,
+    i64 => IntType::S64,
+    i128 => IntType::S128,
+    u8 => IntType::U8,
+    u16 => IntType::U16,
+    u32 => IntType::U32,
+    u64 => IntType::U64,
+    u128 => IntType::U128,
+    f32 => FloatType::F32,
+    f64 => FloatType::F64,
+    bool => BoolType,
+}
+
+// Addresses are opaque `ptr`s whatever they point to, such as memory shared with compiled code for atomics
+unsafe impl<T> JitType for *const T {
+    fn ty() -> Type {
+        PtrType.into()
+    }
+}
+
+unsafe impl<T> JitType for *mut T {
+    fn ty() -> Type {
+        PtrType.into()
+    }
+}
+
+/// The return type of a compiled function: either `()` or a single [`JitType`].
+pub trait JitReturn {
+    fn output() -> Vec<Type>;
+}
+
+impl JitReturn for () {
+    fn output() -> Vec<Type> {
+        Vec::new()
+    }
+}
+
+impl<T: JitType> JitReturn for T {
+    fn output() -> Vec<Type> {
+        vec![T::ty()]
+    }
+}
+
+/// A Rust function pointer type, e.g. `fn(u32) -> u32`, that describes how to call a compiled function.
+///
+/// # Safety
+///
+/// `invoke` must call `address` with the C calling convention using the types reported by `input` and `output`.
+pub unsafe trait JitFn {
+    type Args;
+    type Output;
+
+    fn input() -> Vec<Type>;
+
+    fn output() -> Vec<Type>;
+
+    /// Calls `address`, whose results are left uninitialized when a trap is caught, see [`JitFunction::try_apply`].
+    ///
+    /// # Safety
+    ///
+    /// `address` must point to a function with the signature described by `input` and `output`.
+    unsafe fn invoke(address: *const u8, args: Self::Args) -> MaybeUninit<Self::Output>;
+}
+
+macro_rules! jit_fn {
+    ($($arg:ident: $ty:ident),*) => {
+        unsafe impl<$($ty: JitType,)* R: JitReturn> JitFn for fn($($ty),*) -> R {
+            type Args = ($($ty,)*);
+            type Output = R;
+
+            fn input() -> Vec<Type> {
+                vec![$($ty::ty()),*]
+            }
+
+            fn output() -> Vec<Type> {
+                R::output()
+            }
+
+            unsafe fn invoke(address: *const u8, ($($arg,)*): Self::Args) -> MaybeUninit<R> {
+                // Compiled code unwinds when a function it calls does, such as a Rust function that panics, and
+                // `MaybeUninit` has the ABI of what it wraps
+                let function = unsafe {
+                    std::mem::transmute::<*const u8, unsafe extern "C-unwind" fn($($ty),*) -> MaybeUninit<R>>(address)
+                };
+                unsafe { function($($arg),*) }
+            }
+        }
+
+        impl<$($ty: JitType,)* R: JitReturn> JitFunction<'_, fn($($ty),*) -> R> {
+            /// # Safety
+            ///
+            /// See [`JitFunction::apply`].
+            pub unsafe fn call(&self, $($arg: $ty),*) -> R {
+                unsafe { self.apply(($($arg,)*)) }
+            }
+
+            /// # Safety
+            ///
+            /// See [`JitFunction::apply`].
+            pub unsafe fn try_call(&self, $($arg: $ty),*) -> Result<R, JitError> {
+                unsafe { self.try_apply(($($arg,)*)) }
+            }
+        }
+    };
+}
+
+jit_fn!();
+jit_fn!(a: A);
+jit_fn!(a: A, b: B);
+jit_fn!(a: A, b: B, c: C);
+jit_fn!(a: A, b: B, c: C, d: D);
+jit_fn!(a: A, b: B, c: C, d: D, e: E);
+jit_fn!(a: A, b: B, c: C, d: D, e: E, f: F);
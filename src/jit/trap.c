@@ -0,0 +1,148 @@
+// Catches the signals raised by trapping instructions in compiled code, so that the JIT can report them as errors
+// rather than letting them terminate the process.
+//
+// The signal handler recovers by resuming `dorian_trampoline` in `trampoline.S` after the call to the compiled code,
+// rather than by jumping out of it, so only the frames of compiled code are skipped.
+
+#define _GNU_SOURCE
+#include <signal.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <ucontext.h>
+
+static const int signals[] = { SIGILL, SIGTRAP, SIGFPE };
+static struct sigaction previous[sizeof(signals) / sizeof(signals[0])];
+
+// The addresses of compiled code, from `start` up to but excluding `end`
+struct code_range {
+    uintptr_t start;
+    uintptr_t end;
+};
+
+// A call to compiled code through `dorian_trampoline`, which only catches the traps raised by the code of one module,
+// and is mirrored by `Catcher` in `trap.rs`
+struct catcher {
+    // The compiled function, followed by the stack pointer and frame pointer of the trampoline that calls it
+    const void *target;
+    uintptr_t frame;
+    uintptr_t base;
+    const struct code_range *code;
+    size_t code_len;
+    // The address of the trapping instruction, or NULL if the code has not trapped
+    void *trap_address;
+    struct catcher *outer;
+};
+
+// The innermost catcher on this thread, if any
+static _Thread_local struct catcher *current;
+
+extern char dorian_trampoline_recover[];
+
+static void *instruction_address(void *context) {
+    ucontext_t *ucontext = context;
+#if defined(__x86_64__)
+    return (void *) ucontext->uc_mcontext.gregs[REG_RIP];
+#elif defined(__aarch64__)
+    return (void *) ucontext->uc_mcontext.pc;
+#else
+#error "Traps are only caught on x86-64 and AArch64"
+#endif
+}
+
+// Makes the signal handler return to the trampoline of `catcher`, right after its call to the compiled code
+static void resume(const struct catcher *catcher, void *context) {
+    ucontext_t *ucontext = context;
+#if defined(__x86_64__)
+    ucontext->uc_mcontext.gregs[REG_RSP] = (greg_t) catcher->frame;
+    ucontext->uc_mcontext.gregs[REG_RBP] = (greg_t) catcher->base;
+    ucontext->uc_mcontext.gregs[REG_RIP] = (greg_t) dorian_trampoline_recover;
+#elif defined(__aarch64__)
+    ucontext->uc_mcontext.sp = catcher->frame;
+    ucontext->uc_mcontext.regs[29] = catcher->base;
+    ucontext->uc_mcontext.pc = (uintptr_t) dorian_trampoline_recover;
+#endif
+}
+
+static int is_compiled_code(const struct catcher *catcher, void *address) {
+    for (size_t i = 0; i < catcher->code_len; i++) {
+        if ((uintptr_t) address >= catcher->code[i].start && (uintptr_t) address < catcher->code[i].end) {
+            return 1;
+        }
+    }
+
+    return 0;
+}
+
+// Passes a signal that was not raised by compiled code on to the handler that was installed before this one
+static void forward(int number, siginfo_t *info, void *context) {
+    const struct sigaction *action = NULL;
+    for (size_t i = 0; i < sizeof(signals) / sizeof(signals[0]); i++) {
+        if (signals[i] == number) {
+            action = &previous[i];
+        }
+    }
+
+    if (action->sa_flags & SA_SIGINFO) {
+        action->sa_sigaction(number, info, context);
+        return;
+    }
+    if (action->sa_handler != SIG_DFL && action->sa_handler != SIG_IGN) {
+        action->sa_handler(number);
+        return;
+    }
+
+    // An ignored signal that was sent by a process is dropped, but the kernel never lets a trap be ignored
+    if (action->sa_handler == SIG_IGN && info->si_code <= 0) {
+        return;
+    }
+
+    // The default action of these signals terminates the process, so this handler does not need to be reinstalled. The
+    // signal is blocked while it runs, so the raised signal is delivered once it returns
+    struct sigaction default_action = { 0 };
+    default_action.sa_handler = SIG_DFL;
+    sigemptyset(&default_action.sa_mask);
+    sigaction(number, &default_action, NULL);
+    raise(number);
+}
+
+static void handle(int number, siginfo_t *info, void *context) {
+    void *address = instruction_address(context);
+
+    // Resuming the trampoline skips every frame in between, so it is only done for traps raised by the compiled code it
+    // called, rather than by Rust code that the compiled code calls back into, which pushes a catcher of its own before
+    // calling compiled code again. Signals sent by other threads or processes are not traps either. The signal mask is
+    // restored when the handler returns
+    if (current != NULL && info->si_code > 0 && is_compiled_code(current, address)) {
+        current->trap_address = address;
+        resume(current, context);
+        return;
+    }
+
+    forward(number, info, context);
+}
+
+void dorian_install_trap_handlers(void) {
+    struct sigaction action = { 0 };
+    action.sa_sigaction = handle;
+    action.sa_flags = SA_SIGINFO;
+    sigemptyset(&action.sa_mask);
+
+    for (size_t i = 0; i < sizeof(signals) / sizeof(signals[0]); i++) {
+        sigaction(signals[i], &action, &previous[i]);
+    }
+}
+
+// Makes `catcher` the innermost catcher on this thread, until it is popped
+void dorian_push_catcher(struct catcher *catcher) {
+    catcher->outer = current;
+    current = catcher;
+}
+
+void dorian_pop_catcher(void) {
+    current = current->outer;
+}
+
+// Called by `dorian_trampoline`, as thread-local variables are simpler to reach from C
+struct catcher *dorian_current_catcher(void) {
+    return current;
+}
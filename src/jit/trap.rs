@@ -0,0 +1,145 @@
+//! Catching of the traps raised by compiled code, implemented in `trap.c` and `trampoline.S` on Linux on x86-64 and
+//! AArch64.
+
+use std::ops::Range;
+#[cfg(dorian_traps)]
+use std::sync::Once;
+use super::JitFn;
+
+/// The addresses of compiled code, from `start` up to but excluding `end`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CodeRange {
+    start: usize,
+    end: usize,
+}
+
+impl From<Range<usize>> for CodeRange {
+    fn from(range: Range<usize>) -> Self {
+        Self { start: range.start, end: range.end }
+    }
+}
+
+/// Mirrors `struct catcher` in `trap.c`.
+#[cfg(dorian_traps)]
+#[repr(C)]
+struct Catcher {
+    target: *const u8,
+    frame: usize,
+    base: usize,
+    code: *const CodeRange,
+    code_len: usize,
+    trap_address: *const u8,
+    outer: *mut Catcher,
+}
+
+#[cfg(dorian_traps)]
+unsafe extern "C" {
+    fn dorian_install_trap_handlers();
+
+    fn dorian_push_catcher(catcher: *mut Catcher);
+
+    fn dorian_pop_catcher();
+
+    // Called with the signature of the compiled function, see `trampoline.S`
+    fn dorian_trampoline();
+}
+
+/// Pushes a catcher until it is dropped, which also happens when a panic unwinds out of the call.
+#[cfg(dorian_traps)]
+struct Push;
+
+#[cfg(dorian_traps)]
+impl Push {
+    /// # Safety
+    ///
+    /// `catcher` must stay valid until this is dropped.
+    unsafe fn new(catcher: *mut Catcher) -> Push {
+        unsafe { dorian_push_catcher(catcher) };
+        Push
+    }
+}
+
+#[cfg(dorian_traps)]
+impl Drop for Push {
+    fn drop(&mut self) {
+        unsafe { dorian_pop_catcher() };
+    }
+}
+
+/// Calls the compiled function at `address`, returning the address of the trapping instruction instead if the code in
+/// `code` traps. Any other trap is left to the signal handlers that were installed before.
+///
+/// A trap only skips the frames of compiled code, which `trampoline.S` returns from. A panic in a function that the
+/// compiled code calls continues unwinding out of this.
+///
+/// # Safety
+///
+/// See [`JitFn::invoke`].
+#[cfg(dorian_traps)]
+pub(crate) unsafe fn catch_traps<F: JitFn>(
+    code: &[CodeRange],
+    address: *const u8,
+    args: F::Args,
+) -> Result<F::Output, *const u8> {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| unsafe { dorian_install_trap_handlers() });
+
+    let mut catcher = Catcher {
+        target: address,
+        frame: 0,
+        base: 0,
+        code: code.as_ptr(),
+        code_len: code.len(),
+        trap_address: std::ptr::null(),
+        outer: std::ptr::null_mut(),
+    };
+
+    let output = {
+        let _push = unsafe { Push::new(&mut catcher) };
+        unsafe { F::invoke(dorian_trampoline as *const u8, args) }
+    };
+
+    // The results of a call that trapped are whatever the compiled code left in their registers
+    match catcher.trap_address.is_null() {
+        true => Ok(unsafe { output.assume_init() }),
+        false => Err(catcher.trap_address),
+    }
+}
+
+/// Calls `function` with traps left to the signal handlers that were installed before, even if it was called by
+/// compiled code whose traps are caught, as catching them would skip the frames of `function`.
+#[cfg(dorian_traps)]
+pub(crate) fn forward_traps<R>(function: impl FnOnce() -> R) -> R {
+    let mut catcher = Catcher {
+        target: std::ptr::null(),
+        frame: 0,
+        base: 0,
+        code: std::ptr::null(),
+        code_len: 0,
+        trap_address: std::ptr::null(),
+        outer: std::ptr::null_mut(),
+    };
+
+    let _push = unsafe { Push::new(&mut catcher) };
+    function()
+}
+
+/// Calls the compiled function at `address`, which aborts the process if it traps, as traps cannot be caught here.
+///
+/// # Safety
+///
+/// See [`JitFn::invoke`].
+#[cfg(not(dorian_traps))]
+pub(crate) unsafe fn catch_traps<F: JitFn>(
+    _: &[CodeRange],
+    address: *const u8,
+    args: F::Args,
+) -> Result<F::Output, *const u8> {
+    Ok(unsafe { F::invoke(address, args).assume_init() })
+}
+
+#[cfg(not(dorian_traps))]
+pub(crate) fn forward_traps<R>(function: impl FnOnce() -> R) -> R {
+    function()
+}
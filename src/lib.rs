@@ -4,6 +4,9 @@ pub extern crate dorian_cranelift as cranelift;
 #[cfg(feature = "llvm")]
 pub extern crate dorian_llvm as llvm;
 
+#[cfg(any(feature = "llvm", feature = "cranelift"))]
+pub mod jit;
+
 pub mod prelude {
     pub use crate::ast::block::Block;
     pub use crate::ast::block::builder::*;
@@ -11,17 +14,30 @@ pub mod prelude {
     pub use crate::ast::ty::{Type, ScalarType, NumType, IntType, IntWidth, FloatType, BoolType, PtrType,
                              VectorType, VoidType};  
     pub use crate::ast::ty::util as ty;
-    pub use crate::ast::val::{Value, ContextValue, Arg, Var, Expr, Bin, BinOp, Una, UnaOp, Lit, Num, Int, SignedInt, 
-                              UnsignedInt, Float, Call};
+    pub use crate::ast::val::{Value, ContextValue, Arg, Var, Expr, Bin, BinOp, Una, UnaOp, Checked, CheckedOp,
+                              OnOverflow, FloatCmp, FloatCmpOp, Lit, Num, Int, SignedInt, UnsignedInt, Float, Call,
+                              FuncAddr, CallIndirect, Intrinsic, IntrinsicOp, Splat, Vector, ExtractElement,
+                              InsertElement, Shuffle, Reduce, ReduceOp, AtomicOrdering, AtomicLoad, AtomicRmw,
+                              AtomicRmwOp, CmpXchg};
     pub use crate::ast::val::util as val;
+    pub use crate::ast::opt;
+    pub use crate::ast::infer;
     pub use crate::ast::backend::*;
     pub use crate::ast::function::*;
     pub use crate::ast::global::*;
     pub use crate::ast::module::*;
+    pub use crate::ast::resolve::*;
+    pub use crate::ast::span::*;
     pub use crate::ast::structure::*;
+    pub use crate::ast::visit::Visit;
+    pub use crate::ast::visit_mut::VisitMut;
+    pub use crate::ast::fold::Fold;
     
+    #[cfg(any(feature = "llvm", feature = "cranelift"))]
+    pub use crate::jit::{Jit, JitModule, JitFunction, JitError};
+
     #[cfg(feature = "llvm")]
-    pub use crate::llvm::Llvm;
+    pub use crate::llvm::{Llvm, Optimization};
     
     #[cfg(feature = "cranelift")]
     pub use crate::cranelift::Cranelift;